@@ -0,0 +1,77 @@
+//! Heap allocation benchmark for plugin context pooling
+//!
+//! Initializes and tears down plugins in a loop with and without a context
+//! pool and reports the heap allocations measured by dhat.
+
+use tauri_windows_plugin_system::plugin_host::{PluginContext, PluginHost, DEFAULT_CONTEXT_POOL_SIZE};
+use tauri_windows_plugin_system::plugin_loader::{LoadedPlugin, PluginManifest, PluginMetadata};
+use chrono::Utc;
+use std::path::PathBuf;
+
+#[global_allocator]
+static ALLOCATOR: dhat::Alloc = dhat::Alloc;
+
+/// Number of plugins initialized and torn down per run
+const PLUGIN_CYCLES: usize = 100;
+
+/// Plugin init that always succeeds
+unsafe extern "C" fn bench_plugin_init(_context: *mut PluginContext) -> i32 {
+    0
+}
+
+/// Plugin teardown that always succeeds
+unsafe extern "C" fn bench_plugin_teardown(_context: *mut PluginContext) -> i32 {
+    0
+}
+
+/// Build a statically linked plugin
+fn static_plugin(name: &str) -> LoadedPlugin {
+    let metadata = PluginMetadata {
+        manifest: PluginManifest {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            entry: "plugin.dll".to_string(),
+            api_version: "1.0.0".to_string(),
+            permissions: Vec::new(),
+            description: "Context pool benchmark plugin".to_string(),
+            author: "Test Author".to_string(),
+            homepage: None,
+            resource_limits: None,
+            provides_capabilities: Vec::new(),
+            required_host_features: Vec::new(),
+            total_size_bytes: None,
+            file_count: None,
+        },
+        install_path: PathBuf::new(),
+        dll_path: PathBuf::new(),
+        installed_at: Utc::now(),
+    };
+    
+    LoadedPlugin::from_static(metadata, bench_plugin_init, bench_plugin_teardown)
+}
+
+/// Cycle plugins through a host and get the number of heap allocations made
+fn count_allocations(pool_size: usize) -> u64 {
+    let mut host = PluginHost::new().with_context_pool_size(pool_size);
+    let before = dhat::HeapStats::get().total_blocks;
+    
+    for i in 0..PLUGIN_CYCLES {
+        let plugin_id = format!("bench-plugin-{}", i);
+        host.init_plugin(plugin_id.clone(), static_plugin(&plugin_id)).expect("Failed to initialize plugin");
+        host.teardown_plugin(&plugin_id).expect("Failed to teardown plugin");
+    }
+    
+    dhat::HeapStats::get().total_blocks - before
+}
+
+fn main() {
+    let _profiler = dhat::Profiler::builder().testing().build();
+    
+    let unpooled = count_allocations(0);
+    let pooled = count_allocations(DEFAULT_CONTEXT_POOL_SIZE);
+    
+    println!("{} plugin cycles", PLUGIN_CYCLES);
+    println!("without pool: {} allocations", unpooled);
+    println!("with pool:    {} allocations", pooled);
+    println!("saved:        {} allocations", unpooled.saturating_sub(pooled));
+}
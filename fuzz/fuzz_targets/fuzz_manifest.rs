@@ -0,0 +1,18 @@
+//! Fuzz target for the plugin manifest parser
+//!
+//! Feeds arbitrary bytes to `PluginLoader::parse_manifest`, which must reject
+//! malformed manifests with an error instead of panicking. Run with
+//! `cargo fuzz run fuzz_manifest`; seeds live in `corpus/fuzz_manifest`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tauri_windows_plugin_system::plugin_loader::PluginLoader;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(manifest) = PluginLoader::parse_manifest(data) {
+        // Accepted manifests must survive a round trip
+        let json = serde_json::to_vec(&manifest).expect("Failed to serialize accepted manifest");
+        PluginLoader::parse_manifest(&json).expect("Failed to parse serialized manifest");
+    }
+});
@@ -0,0 +1,34 @@
+//! Plugin ABI coverage tool
+//!
+//! Prints which host ABI exports a plugin DLL implements and which non-system
+//! libraries it imports from, as JSON on stdout.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::Parser;
+
+use tauri_windows_plugin_system::plugin_loader::AbiCoverageAnalyzer;
+
+/// Report the host ABI coverage of a plugin DLL
+#[derive(Parser)]
+#[command(name = "analyze-abi", version)]
+struct Cli {
+    /// Path to the plugin DLL
+    #[arg(long)]
+    plugin: PathBuf,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    
+    match AbiCoverageAnalyzer::analyze(&cli.plugin) {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
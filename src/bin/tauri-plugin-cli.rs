@@ -0,0 +1,169 @@
+//! Plugin system command-line interface
+//!
+//! Manages the plugins of a running application through the plugin manager's
+//! named pipe. Results are printed to stdout as JSON; failures are reported on
+//! stderr with a non-zero exit code.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+
+use tauri_windows_plugin_system::plugin_manager::ipc::{send_ipc_request, RpcRequest, DEFAULT_PIPE_NAME};
+
+/// Manage the plugins of a running Tauri application
+#[derive(Parser)]
+#[command(name = "tauri-plugin-cli", version)]
+struct Cli {
+    /// Name of the plugin manager pipe
+    #[arg(long, global = true, default_value = DEFAULT_PIPE_NAME)]
+    pipe: String,
+    
+    /// Operation to perform
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Plugin manager operations
+#[derive(Subcommand)]
+enum Command {
+    /// Install a plugin package or unpacked plugin directory
+    Install {
+        /// Path to the package or directory
+        path: PathBuf,
+    },
+    
+    /// Uninstall a plugin
+    Uninstall {
+        /// ID of the plugin
+        plugin_id: String,
+    },
+    
+    /// Enable a plugin
+    Enable {
+        /// ID of the plugin
+        plugin_id: String,
+    },
+    
+    /// Disable a plugin
+    Disable {
+        /// ID of the plugin
+        plugin_id: String,
+    },
+    
+    /// List installed plugins
+    List {
+        /// Print the full plugin information as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Show information about a plugin
+    Info {
+        /// ID of the plugin
+        plugin_id: String,
+    },
+    
+    /// Update a plugin
+    Update {
+        /// ID of the plugin
+        plugin_id: String,
+        
+        /// Path to the new plugin package
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+    
+    /// Trigger an event on a plugin
+    Execute {
+        /// ID of the plugin
+        plugin_id: String,
+        
+        /// Name of the event
+        event: String,
+        
+        /// Event payload
+        data: String,
+    },
+    
+    /// Search the plugin store
+    Search {
+        /// Search query
+        query: String,
+    },
+}
+
+impl Command {
+    /// Get the JSON-RPC method and parameters for the command
+    fn to_request(&self) -> std::io::Result<(&'static str, Value)> {
+        Ok(match self {
+            // The server resolves paths against its own working directory
+            Command::Install { path } => ("install", json!({ "path": std::env::current_dir()?.join(path) })),
+            Command::Uninstall { plugin_id } => ("uninstall", json!({ "plugin_id": plugin_id })),
+            Command::Enable { plugin_id } => ("enable", json!({ "plugin_id": plugin_id })),
+            Command::Disable { plugin_id } => ("disable", json!({ "plugin_id": plugin_id })),
+            Command::List { .. } => ("list", Value::Null),
+            Command::Info { plugin_id } => ("info", json!({ "plugin_id": plugin_id })),
+            Command::Update { plugin_id, path } => {
+                let path = match path {
+                    Some(path) => Some(std::env::current_dir()?.join(path)),
+                    None => None,
+                };
+                ("update", json!({ "plugin_id": plugin_id, "path": path }))
+            },
+            Command::Execute { plugin_id, event, data } => {
+                ("execute", json!({ "plugin_id": plugin_id, "event": event, "data": data }))
+            },
+            Command::Search { query } => ("search", json!({ "query": query })),
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    
+    let (method, params) = match cli.command.to_request() {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("Failed to resolve path: {}", e);
+            return ExitCode::from(2);
+        },
+    };
+    
+    let response = match send_ipc_request(&cli.pipe, &RpcRequest::new(1, method, params)).await {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Failed to reach plugin manager on {}: {}", cli.pipe, e);
+            return ExitCode::from(2);
+        },
+    };
+    
+    if let Some(error) = response.error {
+        eprintln!("{}", json!({ "error": error }));
+        return ExitCode::FAILURE;
+    }
+    
+    let result = response.result.unwrap_or(Value::Null);
+    match cli.command {
+        Command::List { json: false } => {
+            for plugin in result.as_array().into_iter().flatten() {
+                // Error states serialize as objects; print those as JSON
+                let status = match plugin["status"].as_str() {
+                    Some(status) => status.to_owned(),
+                    None => plugin["status"].to_string(),
+                };
+                
+                println!(
+                    "{}\t{}\t{}",
+                    plugin["id"].as_str().unwrap_or_default(),
+                    plugin["version"].as_str().unwrap_or_default(),
+                    status,
+                );
+            }
+        },
+        _ => println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default()),
+    }
+    
+    ExitCode::SUCCESS
+}
@@ -4,11 +4,21 @@
 //! Ensures that plugins only access resources they are explicitly permitted to use.
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
 
+use crate::plugin_loader::PluginManifest;
+
+mod prompt_queue;
+mod risk;
+mod vendor;
+
+pub use prompt_queue::{PendingPrompt, PermissionPromptQueue, PromptPresenter};
+pub use risk::{PermissionCategory, RiskRule, RiskRules, RiskScore};
+pub use vendor::{VendorPolicy, VendorTrustLevel, VendorVerifier};
+
 /// Permission definition
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Permission {
@@ -23,6 +33,12 @@ pub enum Permission {
     
     /// System access permission
     System(SystemPermission),
+    
+    /// Access to the plugin's own key-value store
+    Storage,
+    
+    /// Ability to receive commands sent from the frontend
+    Command,
 }
 
 /// File system access permission
@@ -68,6 +84,76 @@ pub struct SystemPermission {
     pub read_system_info: bool,
 }
 
+/// Risk level of a permission, used to decide how prominently it is presented
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PermissionRiskLevel {
+    /// Cosmetic or easily reversible access
+    Low,
+    
+    /// Read access to user or system data
+    Medium,
+    
+    /// Write access or communication outside the host
+    High,
+    
+    /// Access that could compromise the system or exfiltrate user data
+    Critical,
+}
+
+impl PermissionRiskLevel {
+    /// Get the combined risk of a set of permissions under the built-in rules
+    pub fn for_permissions(permissions: &[Permission]) -> Self {
+        Self::for_permissions_with(permissions, RiskRules::builtin())
+    }
+    
+    /// Get the combined risk of a set of permissions under a rule table
+    ///
+    /// This is the highest individual risk, escalated to `Critical` when the set
+    /// pairs network access with access to local data (a potential exfiltration path).
+    pub fn for_permissions_with(permissions: &[Permission], rules: &RiskRules) -> Self {
+        let highest = permissions.iter()
+            .map(|permission| rules.score(permission).level)
+            .max()
+            .unwrap_or(PermissionRiskLevel::Low);
+        
+        let has_network = permissions.iter().any(|p| matches!(p, Permission::Network(_)));
+        let reads_local_data = permissions.iter().any(|p| match p {
+            Permission::FileSystem(fs_perm) => fs_perm.read,
+            Permission::System(sys_perm) => sys_perm.read_clipboard || sys_perm.read_system_info,
+            _ => false,
+        });
+        
+        if has_network && reads_local_data {
+            PermissionRiskLevel::Critical
+        } else {
+            highest
+        }
+    }
+}
+
+/// Presentation style for a permission prompt
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PromptStyle {
+    /// Regular informational prompt
+    Standard,
+    
+    /// Prompt highlighting elevated risk
+    Warning,
+    
+    /// Prompt requiring the user's full attention
+    Critical,
+}
+
+impl From<PermissionRiskLevel> for PromptStyle {
+    fn from(risk: PermissionRiskLevel) -> Self {
+        match risk {
+            PermissionRiskLevel::Low | PermissionRiskLevel::Medium => PromptStyle::Standard,
+            PermissionRiskLevel::High => PromptStyle::Warning,
+            PermissionRiskLevel::Critical => PromptStyle::Critical,
+        }
+    }
+}
+
 /// Permission grant status
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PermissionStatus {
@@ -92,9 +178,21 @@ pub enum PermissionError {
     #[error("Failed to save permission settings: {0}")]
     SaveFailed(#[from] std::io::Error),
     
+    /// Failed to read or parse stored permission settings
+    #[error("Failed to load permission settings: {0}")]
+    LoadFailed(String),
+    
     /// Failed to prompt for permissions
     #[error("Failed to prompt for permissions: {0}")]
     PromptFailed(String),
+    
+    /// Risk rule table could not be parsed
+    #[error("Invalid risk rules: {0}")]
+    InvalidRiskRules(String),
+    
+    /// The permission to revoke was never granted
+    #[error("Permission not granted: {0}")]
+    NotGranted(String),
 }
 
 /// Error during permission validation
@@ -155,6 +253,21 @@ pub struct PermissionSystem {
     
     /// Permission prompt handler
     prompt_handler: Option<Box<dyn PermissionPromptHandler>>,
+    
+    /// Queue serializing prompts answered asynchronously by the frontend
+    prompt_queue: Arc<PermissionPromptQueue>,
+    
+    /// Rules deciding the risk of requested permissions
+    risk_rules: Arc<RiskRules>,
+    
+    /// Permission policies keyed by vendor ID
+    vendor_policies: Mutex<HashMap<String, VendorPolicy>>,
+    
+    /// Authenticates plugin vendors before their policy's trust applies
+    vendor_verifier: Option<VendorVerifier>,
+    
+    /// File grants are saved to after being changed, if any
+    settings_path: Option<PathBuf>,
 }
 
 /// Permission prompt handler trait
@@ -175,6 +288,11 @@ impl PermissionSystem {
             default_permissions: Vec::new(),
             permissions: Arc::new(Mutex::new(HashMap::new())),
             prompt_handler: None,
+            prompt_queue: Arc::new(PermissionPromptQueue::new()),
+            risk_rules: Arc::new(RiskRules::default()),
+            vendor_policies: Mutex::new(HashMap::new()),
+            vendor_verifier: None,
+            settings_path: None,
         }
     }
     
@@ -183,20 +301,190 @@ impl PermissionSystem {
         self.prompt_handler = Some(Box::new(handler));
     }
     
+    /// Get the queue of prompts answered asynchronously by the frontend
+    ///
+    /// Once the queue has a presenter it takes precedence over the prompt handler.
+    pub fn prompt_queue(&self) -> Arc<PermissionPromptQueue> {
+        self.prompt_queue.clone()
+    }
+    
     /// Set default permissions
     pub fn set_default_permissions(&mut self, permissions: Vec<Permission>) {
         self.default_permissions = permissions;
     }
     
+    /// Set the rules deciding the risk of requested permissions
+    pub fn set_risk_rules(&mut self, risk_rules: Arc<RiskRules>) {
+        self.risk_rules = risk_rules;
+    }
+    
+    /// Replace the built-in risk rules with a TOML rule table, if it exists
+    pub fn load_risk_rules(&mut self, rules_path: &Path) -> Result<(), PermissionError> {
+        if rules_path.exists() {
+            self.risk_rules = Arc::new(RiskRules::load(rules_path)?);
+        }
+        
+        Ok(())
+    }
+    
+    /// Get the rules deciding the risk of requested permissions
+    pub fn risk_rules(&self) -> Arc<RiskRules> {
+        self.risk_rules.clone()
+    }
+    
+    /// Score each of a set of requested permissions
+    pub fn get_risk_scores(&self, permissions: &[Permission]) -> Vec<RiskScore> {
+        permissions.iter().map(|p| self.risk_rules.score(p)).collect()
+    }
+    
+    /// Get the combined risk of a set of requested permissions
+    pub fn get_risk_level(&self, permissions: &[Permission]) -> PermissionRiskLevel {
+        PermissionRiskLevel::for_permissions_with(permissions, &self.risk_rules)
+    }
+    
+    /// Register the permission policy for a vendor, replacing any existing one
+    pub fn register_vendor_policy(&self, policy: VendorPolicy) {
+        self.vendor_policies.lock().unwrap().insert(policy.vendor_id.clone(), policy);
+    }
+    
+    /// Remove the permission policy for a vendor
+    pub fn remove_vendor_policy(&self, vendor_id: &str) -> Option<VendorPolicy> {
+        self.vendor_policies.lock().unwrap().remove(vendor_id)
+    }
+    
+    /// Get the permission policy for a vendor
+    pub fn vendor_policy(&self, vendor_id: &str) -> Option<VendorPolicy> {
+        self.vendor_policies.lock().unwrap().get(vendor_id).cloned()
+    }
+    
+    /// Set the verifier authenticating plugin vendors
+    ///
+    /// Without one no vendor is verified, so no plugin is trusted.
+    pub fn set_vendor_verifier(&mut self, verifier: VendorVerifier) {
+        self.vendor_verifier = Some(verifier);
+    }
+    
+    /// Get the authenticated vendor of an installed plugin, if its package proves one
+    pub fn verified_vendor(&self, manifest: &PluginManifest, install_path: &Path) -> Option<String> {
+        self.vendor_verifier.as_ref().and_then(|verifier| verifier(manifest, install_path))
+    }
+    
+    /// Get how far an installed plugin's vendor is trusted
+    ///
+    /// Plugins whose vendor is not verified get `Standard` trust, or `Blocked`
+    /// if the vendor they claim to be is blocked.
+    pub fn vendor_trust(&self, manifest: &PluginManifest, install_path: &Path) -> VendorTrustLevel {
+        if self.claims_blocked_vendor(manifest) {
+            return VendorTrustLevel::Blocked;
+        }
+        
+        self.verified_vendor_policy(manifest, install_path)
+            .map(|policy| policy.trust_level)
+            .unwrap_or_default()
+    }
+    
+    /// Check whether a manifest names a blocked vendor as its author
+    fn claims_blocked_vendor(&self, manifest: &PluginManifest) -> bool {
+        self.vendor_policy(&manifest.author)
+            .is_some_and(|policy| policy.trust_level == VendorTrustLevel::Blocked)
+    }
+    
+    /// Get the policy of an installed plugin's authenticated vendor
+    fn verified_vendor_policy(&self, manifest: &PluginManifest, install_path: &Path) -> Option<VendorPolicy> {
+        self.verified_vendor(manifest, install_path).and_then(|vendor_id| self.vendor_policy(&vendor_id))
+    }
+    
+    /// Apply the policy of a plugin's vendor before prompting for its permissions
+    ///
+    /// For trusted vendors the verifier authenticated, the requested permissions
+    /// covered by the policy are granted without remembering the decision, so a
+    /// following `prompt_for_permissions` only asks for the rest. Plugins from
+    /// unverified vendors are prompted as usual. Plugins claiming or proven to
+    /// come from a blocked vendor are denied.
+    pub fn apply_vendor_policy(
+        &self,
+        plugin_id: &str,
+        manifest: &PluginManifest,
+        install_path: &Path,
+    ) -> Result<(), PermissionError> {
+        if self.claims_blocked_vendor(manifest) {
+            return Err(PermissionError::Denied(format!("Plugins from vendor '{}' are blocked", manifest.author)));
+        }
+        
+        let policy = match self.verified_vendor_policy(manifest, install_path) {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+        
+        if policy.trust_level == VendorTrustLevel::Blocked {
+            return Err(PermissionError::Denied(format!("Plugins from vendor '{}' are blocked", policy.vendor_id)));
+        }
+        
+        let auto_granted = policy.auto_granted(&manifest.permissions);
+        if auto_granted.is_empty() {
+            return Ok(());
+        }
+        
+        let mut permissions_lock = self.permissions.lock().unwrap();
+        let settings = permissions_lock.entry(plugin_id.to_owned())
+            .or_insert_with(|| PluginPermissionSettings {
+                plugin_id: plugin_id.to_owned(),
+                granted_permissions: Vec::new(),
+                remember: false,
+            });
+        
+        for permission in auto_granted {
+            if !settings.granted_permissions.contains(&permission) {
+                settings.granted_permissions.push(permission);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Load vendor policies from disk
+    pub fn load_vendor_policies(&self, policies_path: &Path) -> Result<(), PermissionError> {
+        if policies_path.exists() {
+            let contents = std::fs::read_to_string(policies_path)
+                .map_err(|e| PermissionError::LoadFailed(format!("Failed to read vendor policies: {}", e)))?;
+            let policies: Vec<VendorPolicy> = serde_json::from_str(&contents)
+                .map_err(|e| PermissionError::LoadFailed(format!("Failed to parse vendor policies: {}", e)))?;
+            
+            for policy in policies {
+                self.register_vendor_policy(policy);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Save vendor policies to disk
+    pub fn save_vendor_policies(&self, policies_path: &Path) -> Result<(), PermissionError> {
+        let mut policies: Vec<VendorPolicy> = self.vendor_policies.lock().unwrap().values().cloned().collect();
+        policies.sort_by(|a, b| a.vendor_id.cmp(&b.vendor_id));
+        
+        let contents = serde_json::to_string_pretty(&policies)
+            .map_err(|e| PermissionError::SaveFailed(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to serialize vendor policies: {}", e),
+            )))?;
+        
+        if let Some(parent) = policies_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        
+        std::fs::write(policies_path, contents)?;
+        
+        Ok(())
+    }
+    
     /// Load permission settings from disk
     pub fn load_permissions(&mut self, settings_path: &Path) -> Result<(), PermissionError> {
         if settings_path.exists() {
-            let contents = std::fs::read_to_string(settings_path)?;
+            let contents = std::fs::read_to_string(settings_path)
+                .map_err(|e| PermissionError::LoadFailed(format!("Failed to read permissions: {}", e)))?;
             let settings: Vec<PluginPermissionSettings> = serde_json::from_str(&contents)
-                .map_err(|e| PermissionError::SaveFailed(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    format!("Failed to parse permissions: {}", e),
-                )))?;
+                .map_err(|e| PermissionError::LoadFailed(format!("Failed to parse permissions: {}", e)))?;
             
             let mut permissions = self.permissions.lock().unwrap();
             for setting in settings {
@@ -207,6 +495,11 @@ impl PermissionSystem {
         Ok(())
     }
     
+    /// Set the file grants are saved to after being changed at runtime
+    pub fn set_settings_path(&mut self, settings_path: PathBuf) {
+        self.settings_path = Some(settings_path);
+    }
+    
     /// Save permission settings to disk
     pub fn save_permissions(&self, settings_path: &Path) -> Result<(), PermissionError> {
         let permissions = self.permissions.lock().unwrap();
@@ -266,7 +559,7 @@ impl PermissionSystem {
                         }
                     }
                 },
-                Permission::UI(_) | Permission::System(_) => {
+                Permission::UI(_) | Permission::System(_) | Permission::Storage | Permission::Command => {
                     // These are generally fine as-is
                 }
             }
@@ -343,8 +636,8 @@ impl PermissionSystem {
         }
         
         // Prompt the user
-        if let Some(handler) = &self.prompt_handler {
-            match handler.prompt_for_permissions(plugin_id, plugin_name, &permissions_to_request)? {
+        if let Some(prompt_result) = self.prompt_user(plugin_id, plugin_name, &permissions_to_request).await {
+            match prompt_result? {
                 PermissionPromptResult::Allowed(allowed) => {
                     // Combine with already granted permissions
                     let mut all_granted = already_granted.clone();
@@ -374,6 +667,73 @@ impl PermissionSystem {
         }
     }
     
+    /// Prompt the user for a permission a running plugin needs in addition to its grants
+    ///
+    /// On approval the permission is added to the plugin's grants, replacing any
+    /// grant it fully covers, and the updated grants are returned. Without a
+    /// prompt handler the request is denied.
+    pub async fn request_additional_permission(
+        &self,
+        plugin_id: &str,
+        plugin_name: &str,
+        permission: Permission,
+    ) -> Result<Vec<Permission>, PermissionError> {
+        let granted = self.get_granted_permissions(plugin_id);
+        if granted.contains(&permission) {
+            return Ok(granted);
+        }
+        
+        let prompt_result = self.prompt_user(plugin_id, plugin_name, std::slice::from_ref(&permission)).await
+            .ok_or_else(|| {
+                PermissionError::Denied(format!("No permission prompt handler to approve {}", permission))
+            })?;
+        
+        let allowed = match prompt_result? {
+            PermissionPromptResult::Allowed(allowed) => allowed,
+            PermissionPromptResult::Partial { allowed, .. } => allowed,
+            PermissionPromptResult::Denied(_) => Vec::new(),
+        };
+        
+        if !allowed.contains(&permission) {
+            return Err(PermissionError::Denied(format!("Permission denied: {}", permission)));
+        }
+        
+        let granted = {
+            let mut permissions_lock = self.permissions.lock().unwrap();
+            let settings = permissions_lock.entry(plugin_id.to_owned())
+                .or_insert_with(|| PluginPermissionSettings {
+                    plugin_id: plugin_id.to_owned(),
+                    granted_permissions: Vec::new(),
+                    remember: true,
+                });
+            
+            settings.granted_permissions.retain(|grant| grant.intersection(&permission).as_ref() != Some(grant));
+            settings.granted_permissions.push(permission);
+            settings.granted_permissions.clone()
+        };
+        
+        if let Some(settings_path) = &self.settings_path {
+            self.save_permissions(settings_path)?;
+        }
+        
+        Ok(granted)
+    }
+    
+    /// Ask the user through the prompt queue or handler, if either is available
+    async fn prompt_user(
+        &self,
+        plugin_id: &str,
+        plugin_name: &str,
+        permissions: &[Permission],
+    ) -> Option<Result<PermissionPromptResult, PermissionError>> {
+        if self.prompt_queue.has_presenter() {
+            return Some(self.prompt_queue.prompt(plugin_id, plugin_name, permissions).await);
+        }
+        
+        self.prompt_handler.as_ref()
+            .map(|handler| handler.prompt_for_permissions(plugin_id, plugin_name, permissions))
+    }
+    
     /// Get all granted permissions for a plugin
     pub fn get_granted_permissions(&self, plugin_id: &str) -> Vec<Permission> {
         let permissions_lock = self.permissions.lock().unwrap();
@@ -392,6 +752,174 @@ impl PermissionSystem {
         
         Ok(())
     }
+    
+    /// Revoke a plugin's grants of one category, keeping the others
+    ///
+    /// With a scope, only that path of a file system grant or host of a network
+    /// grant is revoked, and the grant is dropped once no scope is left. Returns
+    /// the remaining grants, which are saved when a settings path is set.
+    pub fn revoke_specific(
+        &self,
+        plugin_id: &str,
+        category: PermissionCategory,
+        scope: Option<&str>,
+    ) -> Result<Vec<Permission>, PermissionError> {
+        let remaining = {
+            let mut permissions_lock = self.permissions.lock().unwrap();
+            let settings = permissions_lock.get_mut(plugin_id).ok_or_else(|| {
+                PermissionError::NotGranted(format!("plugin '{}' holds no permissions", plugin_id))
+            })?;
+            
+            let mut revoked = false;
+            let mut remaining = Vec::with_capacity(settings.granted_permissions.len());
+            for permission in &settings.granted_permissions {
+                if permission.category() != category {
+                    remaining.push(permission.clone());
+                    continue;
+                }
+                
+                let scopes = match (scope, permission) {
+                    (None, _) => {
+                        revoked = true;
+                        continue;
+                    },
+                    (Some(_), Permission::FileSystem(fs_perm)) => &fs_perm.paths,
+                    (Some(_), Permission::Network(net_perm)) => &net_perm.allowed_hosts,
+                    (Some(_), _) => {
+                        remaining.push(permission.clone());
+                        continue;
+                    },
+                };
+                
+                let kept: Vec<String> = scopes.iter().filter(|s| Some(s.as_str()) != scope).cloned().collect();
+                revoked |= kept.len() != scopes.len();
+                
+                match permission {
+                    _ if kept.is_empty() => {},
+                    Permission::FileSystem(fs_perm) => remaining.push(Permission::FileSystem(FileSystemPermission {
+                        paths: kept,
+                        ..fs_perm.clone()
+                    })),
+                    _ => remaining.push(Permission::Network(NetworkPermission { allowed_hosts: kept })),
+                }
+            }
+            
+            if !revoked {
+                return Err(PermissionError::NotGranted(match scope {
+                    Some(scope) => format!("plugin '{}' holds no {:?} grant for '{}'", plugin_id, category, scope),
+                    None => format!("plugin '{}' holds no {:?} grant", plugin_id, category),
+                }));
+            }
+            
+            settings.granted_permissions = remaining.clone();
+            remaining
+        };
+        
+        if let Some(settings_path) = &self.settings_path {
+            self.save_permissions(settings_path)?;
+        }
+        
+        Ok(remaining)
+    }
+}
+
+impl Permission {
+    /// Get the risk level of this permission under the built-in rules
+    pub fn risk_level(&self) -> PermissionRiskLevel {
+        RiskRules::builtin().score(self).level
+    }
+    
+    /// Get the access this permission and another one both allow
+    ///
+    /// Paths are kept when they lie within one of the other permission's paths,
+    /// which paths with `..` components never do, and hosts when the other
+    /// permission lists them or allows any host (`*`). Returns `None` for
+    /// permissions of different kinds or with no access in common.
+    pub fn intersection(&self, other: &Permission) -> Option<Permission> {
+        let intersection = match (self, other) {
+            (Permission::FileSystem(a), Permission::FileSystem(b)) => {
+                let paths: Vec<String> = a.paths.iter()
+                    .filter(|path| b.paths.iter().any(|outer| is_within(path, outer)))
+                    .cloned()
+                    .collect();
+                let read = a.read && b.read;
+                let write = a.write && b.write;
+                
+                if paths.is_empty() || !(read || write) {
+                    return None;
+                }
+                Permission::FileSystem(FileSystemPermission { read, write, paths })
+            },
+            (Permission::Network(a), Permission::Network(b)) => {
+                let allowed_hosts: Vec<String> = a.allowed_hosts.iter()
+                    .filter(|host| b.allowed_hosts.iter().any(|other| other == "*" || other.eq_ignore_ascii_case(host)))
+                    .cloned()
+                    .collect();
+                
+                if allowed_hosts.is_empty() {
+                    return None;
+                }
+                Permission::Network(NetworkPermission { allowed_hosts })
+            },
+            (Permission::UI(a), Permission::UI(b)) => {
+                let show_notifications = a.show_notifications && b.show_notifications;
+                let create_windows = a.create_windows && b.create_windows;
+                
+                if !(show_notifications || create_windows) {
+                    return None;
+                }
+                Permission::UI(UIPermission { show_notifications, create_windows })
+            },
+            (Permission::System(a), Permission::System(b)) => {
+                let read_clipboard = a.read_clipboard && b.read_clipboard;
+                let write_clipboard = a.write_clipboard && b.write_clipboard;
+                let read_system_info = a.read_system_info && b.read_system_info;
+                
+                if !(read_clipboard || write_clipboard || read_system_info) {
+                    return None;
+                }
+                Permission::System(SystemPermission { read_clipboard, write_clipboard, read_system_info })
+            },
+            (Permission::Storage, Permission::Storage) => Permission::Storage,
+            (Permission::Command, Permission::Command) => Permission::Command,
+            _ => return None,
+        };
+        
+        Some(intersection)
+    }
+}
+
+/// Check whether a path is equal to or below another path
+///
+/// Paths containing `..` are never within another path.
+fn is_within(path: &str, outer: &str) -> bool {
+    match (path_components(path), path_components(outer)) {
+        (Some(path), Some(outer)) => path.starts_with(&outer),
+        _ => false,
+    }
+}
+
+/// Split a path into lowercase components for comparison
+///
+/// Repeated separators and `.` components are dropped. Returns `None` for paths
+/// with `..` components, which could otherwise escape the directory they name.
+fn path_components(path: &str) -> Option<Vec<String>> {
+    let mut components = Vec::new();
+    
+    // Keep a marker for rooted paths so they never match relative ones
+    if path.starts_with(['/', '\\']) {
+        components.push(String::new());
+    }
+    
+    for component in path.split(['/', '\\']) {
+        match component {
+            "" | "." => {},
+            ".." => return None,
+            component => components.push(component.to_lowercase()),
+        }
+    }
+    
+    Some(components)
 }
 
 impl std::fmt::Display for Permission {
@@ -438,6 +966,8 @@ impl std::fmt::Display for Permission {
                 
                 write!(f, "System access: {}", perms.join(", "))
             },
+            Permission::Storage => write!(f, "Plugin storage access"),
+            Permission::Command => write!(f, "Frontend command access"),
         }
     }
 }
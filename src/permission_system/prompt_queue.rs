@@ -0,0 +1,137 @@
+//! Asynchronous permission prompt queue
+//!
+//! Shows one permission dialog at a time. Each prompt waits for its turn
+//! without blocking the runtime, is handed to the presenter once the dialog
+//! before it has been resolved, and completes when the frontend resolves it
+//! by request ID. Prompts get their turn in the order they were queued.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use super::{Permission, PermissionError, PermissionPromptResult};
+
+/// A permission prompt waiting for the user's decision
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingPrompt {
+    /// ID the frontend resolves the prompt with
+    pub request_id: u64,
+    
+    /// ID of the plugin requesting the permissions
+    pub plugin_id: String,
+    
+    /// Name of the plugin requesting the permissions
+    pub plugin_name: String,
+    
+    /// Requested permissions
+    pub permissions: Vec<Permission>,
+}
+
+/// Displays a pending prompt to the user
+pub type PromptPresenter = Box<dyn Fn(&PendingPrompt) + Send + Sync>;
+
+/// Queue serializing permission prompts so only one dialog is shown at a time
+#[derive(Default)]
+pub struct PermissionPromptQueue {
+    /// Held by the prompt whose dialog is being shown
+    turn: tokio::sync::Mutex<()>,
+    
+    /// ID given to the next prompt
+    next_request_id: AtomicU64,
+    
+    /// Prompt being shown and the sender resolving it
+    current: Mutex<Option<(PendingPrompt, oneshot::Sender<PermissionPromptResult>)>>,
+    
+    /// Presenter displaying prompts, if one is configured
+    presenter: Mutex<Option<PromptPresenter>>,
+}
+
+impl PermissionPromptQueue {
+    /// Create an empty prompt queue without a presenter
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the presenter displaying prompts
+    pub fn set_presenter(&self, presenter: PromptPresenter) {
+        *self.presenter.lock().unwrap() = Some(presenter);
+    }
+    
+    /// Check whether a presenter is configured
+    pub fn has_presenter(&self) -> bool {
+        self.presenter.lock().unwrap().is_some()
+    }
+    
+    /// Get the prompt being shown, if any
+    pub fn current_prompt(&self) -> Option<PendingPrompt> {
+        self.current.lock().unwrap().as_ref().map(|(prompt, _)| prompt.clone())
+    }
+    
+    /// Queue a prompt and wait for the user's decision
+    ///
+    /// Fails if no presenter is configured or the prompt is cancelled.
+    pub async fn prompt(
+        &self,
+        plugin_id: &str,
+        plugin_name: &str,
+        permissions: &[Permission],
+    ) -> Result<PermissionPromptResult, PermissionError> {
+        // The turn lock is fair, so prompts are shown in the order they were queued
+        let _turn = self.turn.lock().await;
+        
+        let prompt = PendingPrompt {
+            request_id: self.next_request_id.fetch_add(1, Ordering::Relaxed),
+            plugin_id: plugin_id.to_owned(),
+            plugin_name: plugin_name.to_owned(),
+            permissions: permissions.to_vec(),
+        };
+        
+        let (sender, receiver) = oneshot::channel();
+        *self.current.lock().unwrap() = Some((prompt.clone(), sender));
+        
+        // Present without holding the current prompt, so the presenter may resolve the prompt itself
+        let presented = match self.presenter.lock().unwrap().as_ref() {
+            Some(presenter) => {
+                presenter(&prompt);
+                true
+            },
+            None => false,
+        };
+        
+        if !presented {
+            self.current.lock().unwrap().take();
+            return Err(PermissionError::PromptFailed("No permission prompt presenter set".into()));
+        }
+        
+        receiver.await.map_err(|_| {
+            PermissionError::PromptFailed(format!("Permission prompt {} was cancelled", prompt.request_id))
+        })
+    }
+    
+    /// Resolve the prompt being shown with the user's decision
+    pub fn resolve(&self, request_id: u64, result: PermissionPromptResult) -> Result<(), PermissionError> {
+        let sender = {
+            let mut current = self.current.lock().unwrap();
+            match current.take() {
+                Some((prompt, sender)) if prompt.request_id == request_id => sender,
+                other => {
+                    *current = other;
+                    return Err(PermissionError::PromptFailed(
+                        format!("No pending permission prompt {}", request_id)
+                    ));
+                },
+            }
+        };
+        
+        // The prompting task may have been dropped while waiting
+        let _ = sender.send(result);
+        
+        Ok(())
+    }
+    
+    /// Cancel the prompt being shown, failing it and moving on to the next one
+    pub fn cancel_current(&self) -> Option<PendingPrompt> {
+        self.current.lock().unwrap().take().map(|(prompt, _)| prompt)
+    }
+}
@@ -0,0 +1,210 @@
+//! Permission risk rules
+//!
+//! The risk level of a permission comes from an ordered rule table, which also
+//! explains what the permission allows and how to limit the exposure. The
+//! built-in rules live in `risk_rules.toml`; hosts can load their own table at
+//! startup to override them.
+
+use std::path::Path;
+use std::sync::OnceLock;
+use serde::{Serialize, Deserialize};
+
+use super::{is_within, Permission, PermissionError, PermissionRiskLevel};
+
+/// Built-in rule table
+const DEFAULT_RULES: &str = include_str!("risk_rules.toml");
+
+/// Broad kind of a permission, used to match risk rules
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionCategory {
+    /// File system access
+    FileSystem,
+    
+    /// Network access
+    Network,
+    
+    /// UI access
+    UI,
+    
+    /// System access
+    System,
+    
+    /// Plugin key-value storage
+    Storage,
+    
+    /// Commands sent from the frontend
+    Command,
+}
+
+/// Risk assessment of a single permission
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RiskScore {
+    /// Risk level of the permission
+    pub level: PermissionRiskLevel,
+    
+    /// What the permission allows, in user-facing terms
+    pub explanation: String,
+    
+    /// Steps the user can take to limit the risk
+    pub mitigations: Vec<String>,
+}
+
+/// A rule assigning a risk score to matching permissions
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RiskRule {
+    /// Category the permission must belong to
+    pub category: PermissionCategory,
+    
+    /// Access flag the permission must grant, such as `write` or `read_clipboard`
+    #[serde(default)]
+    pub access: Option<String>,
+    
+    /// Directory a path of the permission must be within, or host it must allow
+    #[serde(default)]
+    pub target: Option<String>,
+    
+    /// Risk level of matching permissions
+    pub level: PermissionRiskLevel,
+    
+    /// What matching permissions allow
+    pub explanation: String,
+    
+    /// Steps the user can take to limit the risk
+    #[serde(default)]
+    pub mitigations: Vec<String>,
+}
+
+impl RiskRule {
+    /// Check whether the rule applies to a permission
+    fn matches(&self, permission: &Permission) -> bool {
+        if permission.category() != self.category {
+            return false;
+        }
+        
+        if let Some(access) = &self.access {
+            if !permission.access_flags().contains(&access.as_str()) {
+                return false;
+            }
+        }
+        
+        match (&self.target, permission) {
+            (None, _) => true,
+            (Some(target), Permission::FileSystem(fs_perm)) => {
+                fs_perm.paths.iter().any(|path| is_within(path, target))
+            },
+            (Some(target), Permission::Network(net_perm)) => {
+                net_perm.allowed_hosts.iter().any(|host| host.eq_ignore_ascii_case(target))
+            },
+            (Some(_), _) => false,
+        }
+    }
+}
+
+/// On-disk layout of a rule table
+#[derive(Debug, Deserialize)]
+struct RiskRuleTable {
+    /// Rules in priority order
+    #[serde(default, rename = "rule")]
+    rules: Vec<RiskRule>,
+}
+
+/// Ordered rule table deciding the risk level of permissions
+#[derive(Debug, Clone)]
+pub struct RiskRules {
+    /// Rules in priority order
+    rules: Vec<RiskRule>,
+}
+
+impl RiskRules {
+    /// Create a rule table from rules in priority order
+    pub fn new(rules: Vec<RiskRule>) -> Self {
+        Self { rules }
+    }
+    
+    /// Parse a TOML rule table
+    pub fn from_toml(contents: &str) -> Result<Self, PermissionError> {
+        let table: RiskRuleTable = toml::from_str(contents)
+            .map_err(|e| PermissionError::InvalidRiskRules(e.to_string()))?;
+        
+        Ok(Self::new(table.rules))
+    }
+    
+    /// Load a TOML rule table from disk
+    pub fn load(path: &Path) -> Result<Self, PermissionError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+    
+    /// Get the built-in rule table
+    pub fn builtin() -> &'static RiskRules {
+        static BUILTIN: OnceLock<RiskRules> = OnceLock::new();
+        BUILTIN.get_or_init(|| Self::from_toml(DEFAULT_RULES).expect("Built-in risk rules are invalid"))
+    }
+    
+    /// Get the rules in priority order
+    pub fn rules(&self) -> &[RiskRule] {
+        &self.rules
+    }
+    
+    /// Score a permission with the first matching rule
+    ///
+    /// Permissions no rule matches are scored by the built-in rules.
+    pub fn score(&self, permission: &Permission) -> RiskScore {
+        let builtin = Self::builtin();
+        let rule = self.rules.iter().find(|rule| rule.matches(permission))
+            .or_else(|| builtin.rules.iter().find(|rule| rule.matches(permission)));
+        
+        match rule {
+            Some(rule) => RiskScore {
+                level: rule.level,
+                explanation: rule.explanation.clone(),
+                mitigations: rule.mitigations.clone(),
+            },
+            None => RiskScore {
+                level: PermissionRiskLevel::Low,
+                explanation: permission.to_string(),
+                mitigations: Vec::new(),
+            },
+        }
+    }
+}
+
+impl Default for RiskRules {
+    fn default() -> Self {
+        Self::builtin().clone()
+    }
+}
+
+impl Permission {
+    /// Get the category of this permission
+    pub fn category(&self) -> PermissionCategory {
+        match self {
+            Permission::FileSystem(_) => PermissionCategory::FileSystem,
+            Permission::Network(_) => PermissionCategory::Network,
+            Permission::UI(_) => PermissionCategory::UI,
+            Permission::System(_) => PermissionCategory::System,
+            Permission::Storage => PermissionCategory::Storage,
+            Permission::Command => PermissionCategory::Command,
+        }
+    }
+    
+    /// Get the names of the access flags this permission grants
+    fn access_flags(&self) -> Vec<&'static str> {
+        let flags = match self {
+            Permission::FileSystem(fs_perm) => vec![("read", fs_perm.read), ("write", fs_perm.write)],
+            Permission::Network(_) => Vec::new(),
+            Permission::UI(ui_perm) => vec![
+                ("show_notifications", ui_perm.show_notifications),
+                ("create_windows", ui_perm.create_windows),
+            ],
+            Permission::System(sys_perm) => vec![
+                ("read_clipboard", sys_perm.read_clipboard),
+                ("write_clipboard", sys_perm.write_clipboard),
+                ("read_system_info", sys_perm.read_system_info),
+            ],
+            Permission::Storage | Permission::Command => Vec::new(),
+        };
+        
+        flags.into_iter().filter(|(_, granted)| *granted).map(|(name, _)| name).collect()
+    }
+}
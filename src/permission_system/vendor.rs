@@ -0,0 +1,67 @@
+//! Vendor permission policies
+//!
+//! Lets hosts pre-approve the base permissions shared by a family of plugins
+//! from the same vendor, so users are only prompted for what goes beyond them.
+//!
+//! The manifest author is chosen by whoever built the package, so trust is only
+//! extended to vendors a `VendorVerifier` has authenticated, for example by
+//! checking the package signature.
+
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+
+use super::Permission;
+use crate::plugin_loader::PluginManifest;
+
+/// Authenticates the vendor of an installed plugin
+///
+/// Receives the plugin's manifest and installation directory, and returns the
+/// vendor ID the package proves it was published by, or `None` if it proves none.
+pub type VendorVerifier = Box<dyn Fn(&PluginManifest, &Path) -> Option<String> + Send + Sync>;
+
+/// How far the host trusts a vendor's plugins
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum VendorTrustLevel {
+    /// Permissions covered by the policy are granted without prompting once the vendor is verified
+    Trusted,
+    
+    /// Plugins are prompted for every permission as usual
+    #[default]
+    Standard,
+    
+    /// Plugins from the vendor are denied all permissions
+    Blocked,
+}
+
+/// Permission policy for all plugins published by one vendor
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VendorPolicy {
+    /// Vendor the policy applies to, matched against the authenticated vendor ID
+    pub vendor_id: String,
+    
+    /// Permissions granted without prompting to trusted vendors' plugins
+    pub auto_grant_permissions: Vec<Permission>,
+    
+    /// How far the vendor is trusted
+    pub trust_level: VendorTrustLevel,
+}
+
+impl VendorPolicy {
+    /// Get the requested permissions this policy grants without prompting
+    ///
+    /// A requested permission is granted only when one of the policy's
+    /// permissions allows everything it asks for.
+    pub fn auto_granted(&self, requested: &[Permission]) -> Vec<Permission> {
+        if self.trust_level != VendorTrustLevel::Trusted {
+            return Vec::new();
+        }
+        
+        requested.iter()
+            .filter(|request| {
+                self.auto_grant_permissions.iter()
+                    .any(|grant| request.intersection(grant).as_ref() == Some(*request))
+            })
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,124 @@
+//! Asynchronous event handlers
+//!
+//! An async callback starts handling an event and returns at once, keeping
+//! the `AsyncHandle` it was given. The plugin later reports the result by
+//! passing the handle to `PluginContext::complete_async`, which wakes whoever
+//! awaits the event. A callback that returns nonzero did not start the work;
+//! the host then keeps the handle and reports that code as the result.
+
+use std::ffi::{c_char, c_int};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use super::panics::catch_plugin_panic;
+use super::{PluginContext, PluginHostError};
+
+/// Callback function type for asynchronous event handling
+///
+/// The event data is only valid during the call; copy it to keep it.
+pub type AsyncCallbackFn = unsafe extern "C-unwind" fn(
+    context: *mut PluginContext,
+    event_data: *const c_char,
+    data_len: u32,
+    handle: *mut AsyncHandle,
+) -> c_int;
+
+/// Receives the result of a dispatched event
+pub(super) type ResultSender = oneshot::Sender<Result<i32, PluginHostError>>;
+
+/// Completion token of an event handled asynchronously
+///
+/// Opaque to plugins, which must complete each handle exactly once.
+pub struct AsyncHandle {
+    /// Receives the event's result; shared with the host until the callback returns
+    result_sender: Arc<Mutex<Option<ResultSender>>>,
+}
+
+/// Event handler registered by a plugin
+#[derive(Clone, Copy)]
+pub(super) enum EventCallback {
+    /// Handler returning the event's result
+    Sync(super::CallbackFn),
+    
+    /// Handler completing the event later through `complete_async`
+    Async(AsyncCallbackFn),
+}
+
+impl EventCallback {
+    /// Invoke the handler, sending its result once the event is handled
+    ///
+    /// A panic in the handler finishes the event with `PluginPanicked` and
+    /// its message is returned.
+    ///
+    /// # Safety
+    ///
+    /// `context` must point to the live context of the plugin owning the handler.
+    pub(super) unsafe fn invoke(
+        self,
+        context: *mut PluginContext,
+        event_data: *const c_char,
+        data_len: u32,
+        result_sender: ResultSender,
+    ) -> Result<(), String> {
+        match self {
+            EventCallback::Sync(callback) => {
+                match catch_plugin_panic(|| callback(context, event_data, data_len)) {
+                    Ok(result) => {
+                        let _ = result_sender.send(Ok(result));
+                        Ok(())
+                    },
+                    Err(message) => {
+                        let _ = result_sender.send(Err(PluginHostError::PluginPanicked(message.clone())));
+                        Err(message)
+                    },
+                }
+            },
+            EventCallback::Async(callback) => {
+                let result_sender = Arc::new(Mutex::new(Some(result_sender)));
+                let handle = Box::into_raw(Box::new(AsyncHandle { result_sender: result_sender.clone() }));
+                
+                match catch_plugin_panic(|| callback(context, event_data, data_len, handle)) {
+                    // The plugin did not take the handle, so the event finishes now
+                    Ok(result) if result != 0 => {
+                        let handle = Box::from_raw(handle);
+                        handle.send(Ok(result));
+                        Ok(())
+                    },
+                    Ok(_) => Ok(()),
+                    // The plugin may still hold the handle, so it is leaked rather than freed
+                    Err(message) => {
+                        if let Some(sender) = take_sender(&result_sender) {
+                            let _ = sender.send(Err(PluginHostError::PluginPanicked(message.clone())));
+                        }
+                        Err(message)
+                    },
+                }
+            },
+        }
+    }
+}
+
+impl AsyncHandle {
+    /// Send the event's result unless the event already finished
+    fn send(&self, result: Result<i32, PluginHostError>) {
+        if let Some(sender) = take_sender(&self.result_sender) {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+/// Take the result sender out of its shared slot
+fn take_sender(slot: &Mutex<Option<ResultSender>>) -> Option<ResultSender> {
+    slot.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+/// Complete an event handled asynchronously, releasing its handle
+pub(super) unsafe extern "C" fn complete_async_trampoline(handle: *mut AsyncHandle, result: c_int) {
+    if handle.is_null() {
+        return;
+    }
+    
+    // The handle was created by `EventCallback::invoke` and is completed only once
+    let handle = Box::from_raw(handle);
+    handle.send(Ok(result));
+}
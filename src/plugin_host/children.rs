@@ -0,0 +1,72 @@
+//! Child plugin registry
+//!
+//! Tracks plugins registered by other plugins at runtime, forming a tree of
+//! plugins under each top-level plugin.
+
+use std::collections::HashMap;
+
+use crate::permission_system::Permission;
+
+/// Parent/child relationships between plugins and the permissions children inherit
+#[derive(Debug, Default)]
+pub struct ChildPluginRegistry {
+    /// Child IDs of each parent, in registration order
+    children: HashMap<String, Vec<String>>,
+    
+    /// Parent ID of each child
+    parents: HashMap<String, String>,
+    
+    /// Permissions granted to each child
+    permissions: HashMap<String, Vec<Permission>>,
+}
+
+impl ChildPluginRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Record a child plugin and the permissions it inherited
+    pub fn register(&mut self, parent_id: &str, child_id: &str, permissions: Vec<Permission>) {
+        self.children.entry(parent_id.to_owned()).or_default().push(child_id.to_owned());
+        self.parents.insert(child_id.to_owned(), parent_id.to_owned());
+        self.permissions.insert(child_id.to_owned(), permissions);
+    }
+    
+    /// Forget a plugin, detaching it from its parent
+    ///
+    /// The plugin's own children stay recorded until they are removed themselves.
+    pub fn remove(&mut self, plugin_id: &str) {
+        if let Some(parent_id) = self.parents.remove(plugin_id) {
+            if let Some(siblings) = self.children.get_mut(&parent_id) {
+                siblings.retain(|id| id != plugin_id);
+                if siblings.is_empty() {
+                    self.children.remove(&parent_id);
+                }
+            }
+        }
+        self.permissions.remove(plugin_id);
+    }
+    
+    /// Get the IDs of a plugin's direct children
+    pub fn children_of(&self, plugin_id: &str) -> Vec<String> {
+        self.children.get(plugin_id).cloned().unwrap_or_default()
+    }
+    
+    /// Get the parent ID of a child plugin
+    pub fn parent_of(&self, plugin_id: &str) -> Option<&str> {
+        self.parents.get(plugin_id).map(String::as_str)
+    }
+    
+    /// Get the permissions a child plugin inherited
+    pub fn permissions_of(&self, plugin_id: &str) -> Option<&[Permission]> {
+        self.permissions.get(plugin_id).map(Vec::as_slice)
+    }
+}
+
+/// Restrict requested permissions to the access granted by a parent
+pub fn inherit_permissions(granted: &[Permission], requested: &[Permission]) -> Vec<Permission> {
+    requested.iter()
+        .flat_map(|request| granted.iter().filter_map(move |grant| request.intersection(grant)))
+        .collect()
+}
@@ -0,0 +1,63 @@
+//! JSON results of plugin commands
+//!
+//! A command callback returns a status code like any other callback. To hand
+//! data back to the caller it also calls `PluginContext::set_result` with a
+//! JSON document before returning, on the thread running the callback.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr};
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+use super::PluginContext;
+
+/// Return code of `set_result` outside of a command callback
+pub const COMMAND_RESULT_NOT_ACCEPTED: c_int = -10;
+
+/// Return code of `set_result` when the result is not valid UTF-8 JSON
+pub const COMMAND_RESULT_INVALID_JSON: c_int = -11;
+
+/// Receives the JSON result reported by a command, if any
+pub(super) type CommandResultSender = oneshot::Sender<Option<Value>>;
+
+/// Result slot of the command callback running on the current thread
+pub(super) struct CommandResultSlot {
+    /// Last result reported by the callback
+    result: Option<Value>,
+}
+
+thread_local! {
+    /// Slot for the result of the command running on this thread
+    static COMMAND_RESULT: RefCell<Option<CommandResultSlot>> = const { RefCell::new(None) };
+}
+
+/// Run a command callback, collecting the JSON result it reports
+pub(super) fn collect_command_result<T>(invoke: impl FnOnce() -> T) -> (T, Option<Value>) {
+    COMMAND_RESULT.with(|slot| *slot.borrow_mut() = Some(CommandResultSlot { result: None }));
+    let output = invoke();
+    let result = COMMAND_RESULT.with(|slot| slot.borrow_mut().take()).and_then(|slot| slot.result);
+    
+    (output, result)
+}
+
+/// `set_result` function handed to plugins
+///
+/// Replaces any result reported earlier by the same callback.
+pub(super) unsafe extern "C" fn set_result_trampoline(_context: *mut PluginContext, json: *const c_char) -> c_int {
+    if json.is_null() {
+        return COMMAND_RESULT_INVALID_JSON;
+    }
+    
+    let result = match CStr::from_ptr(json).to_str().map(serde_json::from_str::<Value>) {
+        Ok(Ok(result)) => result,
+        _ => return COMMAND_RESULT_INVALID_JSON,
+    };
+    
+    COMMAND_RESULT.with(|slot| match slot.borrow_mut().as_mut() {
+        Some(slot) => {
+            slot.result = Some(result);
+            0
+        },
+        None => COMMAND_RESULT_NOT_ACCEPTED,
+    })
+}
@@ -5,21 +5,96 @@
 
 use std::ffi::{c_char, c_int, c_void, CStr, CString};
 use std::ptr;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::task::{Context, Poll};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use log::{debug, info, warn, error};
 use thiserror::Error;
+use tokio::sync::oneshot;
 
+use crate::permission_system::Permission;
 use crate::plugin_loader::{LoadedPlugin, PluginLoadError};
 
+mod async_events;
+mod children;
+mod command_results;
+mod panics;
+mod pool;
+mod replay;
+mod storage;
+mod streaming;
+mod trace;
+
+pub use async_events::{AsyncCallbackFn, AsyncHandle};
+pub use children::{inherit_permissions, ChildPluginRegistry};
+pub use command_results::{COMMAND_RESULT_INVALID_JSON, COMMAND_RESULT_NOT_ACCEPTED};
+pub use panics::{PanicListener, PluginPanic};
+pub use pool::{PluginContextPool, PooledContext, DEFAULT_CONTEXT_POOL_SIZE, EXPECTED_CONCURRENT_PLUGINS};
+pub use replay::{EventRecorder, EventReplayer, MockClock, ReplayClock, ReplayEntry, ReplayLog, ReplayResult, SystemClock};
+pub use storage::{
+    KvLimits, KvStore, KvStoreError, DEFAULT_KV_QUOTA_BYTES, DEFAULT_MAX_KEY_BYTES, DEFAULT_MAX_VALUE_BYTES,
+    KV_IO_ERROR, KV_LIMIT_EXCEEDED, KV_NOT_FOUND, KV_NO_PERMISSION, KV_STORE_FILE,
+};
+pub use streaming::{EventStream, STREAM_CHANNEL_CAPACITY};
+pub use trace::{TraceContext, TraceSampler, TRACE_FLAG_SAMPLED};
+
+use async_events::{complete_async_trampoline, EventCallback, ResultSender};
+use command_results::{collect_command_result, set_result_trampoline, CommandResultSender};
+use panics::{install_panic_hook, PanicReporter};
+use streaming::{chunk_trampoline, ChunkSink, CHUNK_SINK};
+
 /// Log levels for plugin logging
 pub const LOG_DEBUG: u32 = 0;
 pub const LOG_INFO: u32 = 1;
 pub const LOG_WARN: u32 = 2;
 pub const LOG_ERROR: u32 = 3;
 
+/// Return code reported for an event skipped as a duplicate
+pub const DEDUP_SKIPPED_CODE: i32 = i32::MIN;
+
+/// Default window during which repeated idempotency keys are treated as duplicates
+pub const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(30);
+
+/// Default time a plugin command may run before it is interrupted
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Consecutive command timeouts after which a plugin is considered unhealthy by default
+pub const DEFAULT_UNHEALTHY_TIMEOUT_THRESHOLD: u32 = 3;
+
+/// Number of commands waiting for the command worker of a synchronous plugin
+const COMMAND_QUEUE_CAPACITY: usize = 16;
+
+/// Event asking a plugin to suspend its work
+pub const PAUSE_EVENT: &str = "pause_requested";
+
+/// Event asking a paused plugin to continue its work
+pub const RESUME_EVENT: &str = "resume_requested";
+
+/// Event telling a plugin its feature flags, as `{"flags": {...}, "changed": name}`
+pub const FEATURE_FLAGS_EVENT: &str = "feature_flags_changed";
+
+/// Default time teardown waits for a plugin's running callbacks to finish
+pub const DEFAULT_TEARDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time a plugin is given to react to a graceful shutdown request
+const GRACEFUL_SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+
+/// Interval at which teardown checks whether a callback worker has stopped
+const WORKER_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Number of time buckets kept per deduplication window
+const DEDUP_BUCKETS_PER_WINDOW: u32 = 4;
+
 /// Callback function type for event handling
-pub type CallbackFn = unsafe extern "C" fn(
+///
+/// Uses the `C-unwind` ABI so that a panicking callback can be isolated.
+pub type CallbackFn = unsafe extern "C-unwind" fn(
     context: *mut PluginContext,
     event_data: *const c_char,
     data_len: u32,
@@ -50,6 +125,71 @@ pub struct PluginContext {
     pub log: Option<
         unsafe extern "C" fn(context: *mut PluginContext, level: u32, message: *const c_char),
     >,
+    
+    /// Function to read a value from the plugin's key-value store
+    ///
+    /// Returns the value's length in bytes, copying it NUL-terminated into the
+    /// buffer when it fits. Pass a null buffer to query the length.
+    pub kv_get: Option<
+        unsafe extern "C" fn(
+            context: *mut PluginContext,
+            key: *const c_char,
+            buffer: *mut c_char,
+            buffer_len: u32,
+        ) -> c_int,
+    >,
+    
+    /// Function to store a value in the plugin's key-value store
+    pub kv_set: Option<
+        unsafe extern "C" fn(context: *mut PluginContext, key: *const c_char, value: *const c_char) -> c_int,
+    >,
+    
+    /// Function to delete a value from the plugin's key-value store
+    pub kv_delete: Option<
+        unsafe extern "C" fn(context: *mut PluginContext, key: *const c_char) -> c_int,
+    >,
+    
+    /// Trace context of the event being handled, for propagation as a `traceparent` header
+    ///
+    /// Set by the host before each callback; all zeros outside of a callback.
+    pub trace_context: TraceContext,
+    
+    /// Function to register asynchronous callbacks for events
+    pub register_async_callback: Option<
+        unsafe extern "C" fn(
+            context: *mut PluginContext,
+            event_name: *const c_char,
+            callback: Option<AsyncCallbackFn>,
+        ) -> c_int,
+    >,
+    
+    /// Function to report the result of an event handled asynchronously
+    pub complete_async: Option<unsafe extern "C" fn(handle: *mut AsyncHandle, result: c_int)>,
+    
+    /// Set to 1 by the host when the running callback should stop early
+    ///
+    /// Cleared before each callback. The host cannot interrupt a blocked callback,
+    /// so long-running callbacks should poll this with an atomic load.
+    pub shutdown_requested: AtomicU32,
+    
+    /// Function to report the JSON result of the running command
+    ///
+    /// Only accepted from the thread running a command callback, before it returns.
+    pub set_result: Option<
+        unsafe extern "C" fn(context: *mut PluginContext, json: *const c_char) -> c_int,
+    >,
+}
+
+impl PluginContext {
+    /// Prepare a context for the next callback invocation
+    ///
+    /// # Safety
+    ///
+    /// `context` must point to a live plugin context.
+    unsafe fn begin_callback(context: *mut Self, trace_context: TraceContext) {
+        (*context).trace_context = trace_context;
+        (*context).shutdown_requested.store(0, Ordering::SeqCst);
+    }
 }
 
 /// Host-specific data associated with a plugin
@@ -59,6 +199,10 @@ pub struct HostData {
     pub plugin_id: String,
     /// Registered callbacks for events
     pub callbacks: HashMap<String, CallbackFn>,
+    /// Registered asynchronous callbacks for events
+    pub async_callbacks: HashMap<String, AsyncCallbackFn>,
+    /// Key-value store, present when the plugin holds the `Storage` permission
+    pub storage: Option<Arc<KvStore>>,
 }
 
 /// Error type for plugin host operations
@@ -87,23 +231,539 @@ pub enum PluginHostError {
     /// Failed to communicate with plugin
     #[error("Plugin communication error: {0}")]
     CommunicationError(String),
+    
+    /// The plugin's event queue is full
+    #[error("Event queue full for plugin: {0}")]
+    QueueFull(String),
+    
+    /// A plugin command did not finish in time
+    #[error("Plugin {plugin_id} did not handle event '{event_name}' within {timeout_ms} ms")]
+    CommandTimedOut {
+        /// ID of the plugin
+        plugin_id: String,
+        /// Name of the event that timed out
+        event_name: String,
+        /// Timeout in milliseconds
+        timeout_ms: u64,
+    },
+    
+    /// A plugin callback panicked
+    #[error("Plugin panicked: {0}")]
+    PluginPanicked(String),
+    
+    /// A plugin with the same ID is already loaded
+    #[error("Plugin already loaded: {0}")]
+    AlreadyLoaded(String),
+    
+    /// A plugin's callbacks were still running when its teardown gave up waiting
+    #[error("Plugin {0} was still running a callback when torn down")]
+    TeardownTimedOut(String),
+}
+
+/// An event payload together with its delivery metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventEnvelope {
+    /// Event payload passed to the plugin
+    pub data: String,
+    
+    /// Key identifying repeated deliveries of the same logical event
+    pub idempotency_key: Option<String>,
+    
+    /// Trace the event belongs to; a new trace is started when absent
+    pub trace_context: Option<TraceContext>,
+}
+
+impl EventEnvelope {
+    /// Create an envelope without an idempotency key
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            idempotency_key: None,
+            trace_context: None,
+        }
+    }
+    
+    /// Attach an idempotency key to the envelope
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+    
+    /// Continue an existing trace when delivering the event
+    pub fn with_trace_context(mut self, trace_context: TraceContext) -> Self {
+        self.trace_context = Some(trace_context);
+        self
+    }
+}
+
+impl From<&str> for EventEnvelope {
+    fn from(data: &str) -> Self {
+        Self::new(data)
+    }
+}
+
+impl From<String> for EventEnvelope {
+    fn from(data: String) -> Self {
+        Self::new(data)
+    }
+}
+
+/// Event deduplication statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeduplicatorStats {
+    /// Number of events skipped as duplicates
+    pub duplicates_caught: u64,
+    
+    /// Number of distinct events seen
+    pub unique_events: u64,
+}
+
+/// Keys seen within one time bucket
+struct DedupBucket {
+    /// When the bucket was opened
+    started_at: Instant,
+    /// Keys recorded in the bucket
+    keys: HashSet<String>,
+}
+
+/// Mutable deduplicator state
+#[derive(Default)]
+struct DeduplicatorState {
+    /// Buckets ordered from oldest to newest
+    buckets: VecDeque<DedupBucket>,
+    /// Running statistics
+    stats: DeduplicatorStats,
+}
+
+/// Filter detecting events delivered more than once within a time window
+pub struct EventDeduplicator {
+    /// How long a key is remembered
+    dedup_window: Duration,
+    /// Bucketed keys and statistics
+    state: Mutex<DeduplicatorState>,
+}
+
+impl EventDeduplicator {
+    /// Create a deduplicator remembering keys for `dedup_window`
+    pub fn new(dedup_window: Duration) -> Self {
+        Self {
+            dedup_window,
+            state: Mutex::new(DeduplicatorState::default()),
+        }
+    }
+    
+    /// Get the deduplication window
+    pub fn dedup_window(&self) -> Duration {
+        self.dedup_window
+    }
+    
+    /// Check whether an event was already seen, recording it if not
+    pub fn is_duplicate(&self, plugin_id: &str, event_name: &str, idempotency_key: &str) -> bool {
+        let key = format!("{}\0{}\0{}", plugin_id, event_name, idempotency_key);
+        let now = Instant::now();
+        let bucket_width = self.dedup_window / DEDUP_BUCKETS_PER_WINDOW;
+        
+        let mut state = self.state.lock().unwrap();
+        
+        // Expire buckets that fall entirely outside the window
+        while let Some(bucket) = state.buckets.front() {
+            if now.duration_since(bucket.started_at) > self.dedup_window + bucket_width {
+                state.buckets.pop_front();
+            } else {
+                break;
+            }
+        }
+        
+        if state.buckets.iter().any(|bucket| bucket.keys.contains(&key)) {
+            state.stats.duplicates_caught += 1;
+            return true;
+        }
+        
+        let needs_new_bucket = match state.buckets.back() {
+            Some(bucket) => now.duration_since(bucket.started_at) >= bucket_width,
+            None => true,
+        };
+        if needs_new_bucket {
+            state.buckets.push_back(DedupBucket {
+                started_at: now,
+                keys: HashSet::new(),
+            });
+        }
+        
+        if let Some(bucket) = state.buckets.back_mut() {
+            bucket.keys.insert(key);
+        }
+        state.stats.unique_events += 1;
+        
+        false
+    }
+    
+    /// Get deduplication statistics
+    pub fn stats(&self) -> DeduplicatorStats {
+        self.state.lock().unwrap().stats
+    }
+}
+
+impl Default for EventDeduplicator {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_WINDOW)
+    }
+}
+
+/// Thread model used to invoke plugin event callbacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CallbackThreadModel {
+    /// Invoke callbacks on the caller's thread
+    #[default]
+    Synchronous,
+    
+    /// Invoke callbacks on a dedicated per-plugin worker thread
+    WorkerThread {
+        /// Maximum number of events waiting to be dispatched
+        queue_capacity: usize,
+    },
+}
+
+/// Handle to the result of a dispatched plugin event
+pub struct PendingEvent {
+    /// Receives the callback's return value
+    receiver: oneshot::Receiver<Result<i32, PluginHostError>>,
+}
+
+impl PendingEvent {
+    /// Create a handle for a callback that has already returned
+    fn ready(result: i32) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        let _ = sender.send(Ok(result));
+        Self { receiver }
+    }
+    
+    /// Block the current thread until the callback has returned
+    pub fn wait(self) -> Result<i32, PluginHostError> {
+        futures::executor::block_on(self)
+    }
+}
+
+impl Future for PendingEvent {
+    type Output = Result<i32, PluginHostError>;
+    
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx).map(|result| {
+            result.unwrap_or_else(|_| Err(
+                PluginHostError::CommunicationError("Plugin stopped before handling the event".into())
+            ))
+        })
+    }
+}
+
+/// Outcome of a plugin command
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandOutput {
+    /// Status code returned by the callback
+    pub code: i32,
+    
+    /// JSON result reported through `set_result`, if any
+    pub result: Option<serde_json::Value>,
+}
+
+/// Handle to a dispatched event that must complete within a timeout
+///
+/// Holds no reference to the host, so it can be awaited after releasing the host lock.
+pub struct TimedEvent {
+    /// Status code of the callback
+    pending: PendingEvent,
+    /// JSON result reported by the callback
+    result: oneshot::Receiver<Option<serde_json::Value>>,
+    /// ID of the plugin handling the event
+    plugin_id: String,
+    /// Name of the event
+    event_name: String,
+    /// Time the plugin has to handle the event
+    timeout: Duration,
+}
+
+impl TimedEvent {
+    /// Wait for the callback's output, or `None` once the timeout has elapsed
+    pub async fn wait(&mut self) -> Option<Result<CommandOutput, PluginHostError>> {
+        let code = match tokio::time::timeout(self.timeout, &mut self.pending).await.ok()? {
+            Ok(code) => code,
+            Err(e) => return Some(Err(e)),
+        };
+        
+        // The worker reports the result right after the status code; skipped events have none
+        let result = (&mut self.result).await.unwrap_or(None);
+        Some(Ok(CommandOutput { code, result }))
+    }
+    
+    /// Give a timed out callback a grace period to stop, returning the timeout error
+    ///
+    /// Call after `PluginHost::record_timeout` has asked the plugin to stop.
+    pub async fn finish_timed_out(mut self) -> PluginHostError {
+        if tokio::time::timeout(GRACEFUL_SHUTDOWN_GRACE, &mut self.pending).await.is_err() {
+            error!("Plugin {} is still blocked handling event '{}'", self.plugin_id, self.event_name);
+        }
+        
+        PluginHostError::CommandTimedOut {
+            plugin_id: self.plugin_id,
+            event_name: self.event_name,
+            timeout_ms: self.timeout.as_millis() as u64,
+        }
+    }
 }
 
 /// Plugin host responsible for managing plugin execution
 pub struct PluginHost {
     /// Loaded plugins managed by this host
     plugins: HashMap<String, PluginInstance>,
+    
+    /// Thread model for plugins initialized by this host
+    thread_model: CallbackThreadModel,
+    
+    /// Filter for events delivered more than once
+    deduplicator: EventDeduplicator,
+    
+    /// Timeout applied to commands when the caller does not choose one
+    default_command_timeout: Duration,
+    
+    /// Timeouts of individual commands, overriding the default
+    command_timeouts: HashMap<String, Duration>,
+    
+    /// Consecutive command timeouts per plugin
+    consecutive_timeouts: Mutex<HashMap<String, u32>>,
+    
+    /// Consecutive command timeouts after which a plugin is unhealthy
+    unhealthy_timeout_threshold: u32,
+    
+    /// Time teardown waits for a plugin's running callbacks
+    teardown_timeout: Duration,
+    
+    /// Recorder of triggered events
+    recorder: EventRecorder,
+    
+    /// Plugins registered by other plugins
+    child_registry: ChildPluginRegistry,
+    
+    /// Reusable plugin contexts
+    context_pool: Arc<PluginContextPool>,
+    
+    /// Sampling decision for event traces; every event is sampled when unset
+    trace_sampler: Option<TraceSampler>,
+    
+    /// Receiver of panics raised inside plugin callbacks
+    panic_reporter: PanicReporter,
 }
 
 /// A running plugin instance
+///
+/// Fields drop in declaration order: the worker stops before the context it
+/// uses is freed, and the plugin library is unloaded last.
 struct PluginInstance {
-    /// The loaded plugin
-    loaded_plugin: LoadedPlugin,
+    /// Callback worker, present when the plugin uses the worker thread model
+    worker: Option<CallbackWorker>,
+    /// Worker running the timed commands of a plugin using the synchronous model
+    command_worker: OnceLock<CallbackWorker>,
+    /// Held while any of the plugin's callbacks runs, so they never share the context at once
+    callback_lock: Arc<Mutex<()>>,
+    /// Context handed to the plugin for C ABI calls
+    context: OwnedContext,
     /// Host data for this plugin
     host_data: Arc<Mutex<HostData>>,
-    /// Raw pointer for FFI (not shared between threads directly)
-    /// This is used only for C ABI calls and is managed by the context above
-    context_ptr: *mut PluginContext,
+    /// The loaded plugin
+    loaded_plugin: LoadedPlugin,
+}
+
+/// Plugin context taken from the host's pool and returned exactly once when dropped
+struct OwnedContext {
+    /// Context passed to the plugin
+    context: PooledContext,
+    /// Host data reference stored in the context, kept in case the plugin overwrites it
+    host_data_ptr: *const Mutex<HostData>,
+}
+
+impl OwnedContext {
+    /// Acquire a context holding a reference to the plugin's host data
+    fn new(host_data: &Arc<Mutex<HostData>>, pool: &Arc<PluginContextPool>) -> Self {
+        let host_data_ptr = Arc::into_raw(host_data.clone());
+        
+        let mut context = pool.acquire();
+        *context = PluginContext {
+            api_version: 1,
+            host_data: host_data_ptr as *mut c_void,
+            plugin_data: ptr::null_mut(),
+            register_callback: Some(PluginHost::register_callback_trampoline),
+            log: Some(PluginHost::log_trampoline),
+            kv_get: Some(PluginHost::kv_get_trampoline),
+            kv_set: Some(PluginHost::kv_set_trampoline),
+            kv_delete: Some(PluginHost::kv_delete_trampoline),
+            trace_context: TraceContext::default(),
+            register_async_callback: Some(PluginHost::register_async_callback_trampoline),
+            complete_async: Some(complete_async_trampoline),
+            shutdown_requested: AtomicU32::new(0),
+            set_result: Some(set_result_trampoline),
+        };
+        
+        Self {
+            context,
+            host_data_ptr,
+        }
+    }
+    
+    /// Get the raw context pointer
+    fn as_ptr(&self) -> *mut PluginContext {
+        self.context.as_ptr()
+    }
+}
+
+impl Drop for OwnedContext {
+    fn drop(&mut self) {
+        // The host data pointer was created by `OwnedContext::new` and is released only here;
+        // the context itself goes back to its pool when the field is dropped
+        unsafe {
+            drop(Arc::from_raw(self.host_data_ptr));
+        }
+    }
+}
+
+/// A queued callback invocation
+struct CallbackJob {
+    /// Callback to invoke
+    callback: EventCallback,
+    /// Event payload passed to the callback
+    event_data: CString,
+    /// Length of the event payload in bytes
+    data_len: u32,
+    /// Trace context set on the plugin context for the callback
+    trace_context: TraceContext,
+    /// Receives the callback's return value
+    result_sender: ResultSender,
+    /// Receives the JSON result reported by a command callback
+    command_result: Option<CommandResultSender>,
+}
+
+/// Context pointer handed over to a callback worker thread
+struct WorkerContext(*mut PluginContext);
+
+// Callbacks on the context run under the plugin's callback lock;
+// the host stops the worker before tearing the plugin down
+unsafe impl Send for WorkerContext {}
+
+impl WorkerContext {
+    /// Get the raw context pointer
+    fn as_ptr(&self) -> *mut PluginContext {
+        self.0
+    }
+}
+
+/// Dedicated thread invoking a single plugin's callbacks in order
+struct CallbackWorker {
+    /// Sending half of the bounded event queue
+    sender: Option<SyncSender<CallbackJob>>,
+    /// Worker thread handle
+    handle: Option<JoinHandle<()>>,
+}
+
+impl CallbackWorker {
+    /// Spawn a worker thread for a plugin context
+    fn spawn(
+        plugin_id: &str,
+        context_ptr: *mut PluginContext,
+        queue_capacity: usize,
+        callback_lock: Arc<Mutex<()>>,
+        panic_reporter: PanicReporter,
+    ) -> Result<Self, PluginHostError> {
+        let (sender, receiver) = mpsc::sync_channel::<CallbackJob>(queue_capacity.max(1));
+        let context = WorkerContext(context_ptr);
+        let worker_plugin_id = plugin_id.to_owned();
+        
+        let handle = thread::Builder::new()
+            .name(format!("plugin-{}", plugin_id))
+            .spawn(move || {
+                for job in receiver {
+                    let callback_guard = callback_lock.lock().unwrap();
+                    let invoke = || unsafe {
+                        PluginContext::begin_callback(context.as_ptr(), job.trace_context);
+                        job.callback.invoke(context.as_ptr(), job.event_data.as_ptr(), job.data_len, job.result_sender)
+                    };
+                    
+                    let result = match job.command_result {
+                        Some(command_result) => {
+                            let (result, command_output) = collect_command_result(invoke);
+                            let _ = command_result.send(command_output);
+                            result
+                        },
+                        None => invoke(),
+                    };
+                    drop(callback_guard);
+                    
+                    // The worker keeps serving the plugin after a panic
+                    if let Err(message) = result {
+                        panic_reporter.report(&worker_plugin_id, message);
+                    }
+                }
+            })
+            .map_err(|e| {
+                PluginHostError::CommunicationError(format!("Failed to spawn callback worker: {}", e))
+            })?;
+        
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+        })
+    }
+    
+    /// Queue a callback invocation without waiting for it
+    fn submit(&self, plugin_id: &str, job: CallbackJob) -> Result<(), PluginHostError> {
+        let sender = self.sender.as_ref().ok_or_else(|| {
+            PluginHostError::CommunicationError(format!("Callback worker stopped for plugin: {}", plugin_id))
+        })?;
+        
+        match sender.try_send(job) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(PluginHostError::QueueFull(plugin_id.to_owned())),
+            Err(TrySendError::Disconnected(_)) => Err(PluginHostError::CommunicationError(
+                format!("Callback worker stopped for plugin: {}", plugin_id)
+            )),
+        }
+    }
+    
+    /// Stop accepting events and wait up to `timeout` for queued callbacks to finish
+    ///
+    /// Returns false, detaching the worker thread, if a callback is still running.
+    fn shutdown_within(&mut self, timeout: Duration) -> bool {
+        self.sender.take();
+        
+        let deadline = Instant::now() + timeout;
+        while let Some(handle) = &self.handle {
+            if handle.is_finished() {
+                break;
+            }
+            if Instant::now() >= deadline {
+                self.handle.take();
+                return false;
+            }
+            thread::sleep(WORKER_POLL_INTERVAL);
+        }
+        
+        self.shutdown();
+        true
+    }
+    
+    /// Stop accepting events and wait for queued callbacks to finish
+    fn shutdown(&mut self) {
+        self.sender.take();
+        
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                error!("Plugin callback worker panicked");
+            }
+        }
+    }
+}
+
+impl Drop for CallbackWorker {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
 }
 
 // Implementing Send and Sync explicitly for PluginInstance
@@ -115,82 +775,487 @@ unsafe impl Sync for PluginInstance {}
 impl PluginHost {
     /// Create a new plugin host
     pub fn new() -> Self {
+        Self::with_thread_model(CallbackThreadModel::default())
+    }
+    
+    /// Create a new plugin host using the given callback thread model
+    pub fn with_thread_model(thread_model: CallbackThreadModel) -> Self {
+        install_panic_hook();
+        
         Self {
             plugins: HashMap::new(),
+            thread_model,
+            deduplicator: EventDeduplicator::default(),
+            default_command_timeout: DEFAULT_COMMAND_TIMEOUT,
+            command_timeouts: HashMap::new(),
+            consecutive_timeouts: Mutex::new(HashMap::new()),
+            unhealthy_timeout_threshold: DEFAULT_UNHEALTHY_TIMEOUT_THRESHOLD,
+            teardown_timeout: DEFAULT_TEARDOWN_TIMEOUT,
+            recorder: EventRecorder::new(),
+            child_registry: ChildPluginRegistry::new(),
+            context_pool: Arc::new(PluginContextPool::default()),
+            trace_sampler: None,
+            panic_reporter: PanicReporter::default(),
         }
     }
     
+    /// Set the number of plugin contexts kept for reuse
+    ///
+    /// Replaces the pool; contexts of running plugins are freed when they are torn down.
+    pub fn with_context_pool_size(mut self, pool_size: usize) -> Self {
+        self.context_pool = Arc::new(PluginContextPool::new(pool_size));
+        self
+    }
+    
+    /// Set which plugin events are sampled for tracing
+    ///
+    /// The sampler receives the plugin ID and event name. Unsampled events still
+    /// carry a trace context, with the sampled flag cleared.
+    pub fn set_trace_sampler(&mut self, sampler: TraceSampler) {
+        self.trace_sampler = Some(sampler);
+    }
+    
+    /// Set the listener receiving panics raised inside plugin callbacks
+    ///
+    /// A panicking callback finishes its event with `PluginPanicked`; the
+    /// listener may be called from plugin worker threads.
+    pub fn set_panic_listener(&self, listener: PanicListener) {
+        self.panic_reporter.set_listener(listener);
+    }
+    
+    /// Get the trace context for delivering an event, continuing `parent` if given
+    fn event_trace_context(&self, plugin_id: &str, event_name: &str, parent: Option<&TraceContext>) -> TraceContext {
+        let sampled = self.trace_sampler.as_ref().is_none_or(|sampler| sampler(plugin_id, event_name));
+        
+        match parent.filter(|parent| parent.is_valid()) {
+            Some(parent) => parent.child(sampled),
+            None => TraceContext::new_root(sampled),
+        }
+    }
+    
+    /// Get the pool of reusable plugin contexts
+    pub fn context_pool(&self) -> &PluginContextPool {
+        &self.context_pool
+    }
+    
+    /// Set the timeout applied to commands when the caller does not choose one
+    pub fn with_default_command_timeout(mut self, timeout: Duration) -> Self {
+        self.default_command_timeout = timeout;
+        self
+    }
+    
+    /// Get the timeout applied to commands when the caller does not choose one
+    pub fn default_command_timeout(&self) -> Duration {
+        self.default_command_timeout
+    }
+    
+    /// Set the timeout of a command, overriding the default for it
+    pub fn set_command_timeout(&mut self, command: &str, timeout: Duration) {
+        self.command_timeouts.insert(command.to_owned(), timeout);
+    }
+    
+    /// Get the timeout applied to a command
+    pub fn command_timeout(&self, command: &str) -> Duration {
+        self.command_timeouts.get(command).copied().unwrap_or(self.default_command_timeout)
+    }
+    
+    /// Set how many consecutive command timeouts make a plugin unhealthy
+    pub fn with_unhealthy_timeout_threshold(mut self, threshold: u32) -> Self {
+        self.unhealthy_timeout_threshold = threshold.max(1);
+        self
+    }
+    
+    /// Set how long teardown waits for a plugin's running callbacks
+    ///
+    /// A plugin still blocked after that is unloaded without calling its
+    /// teardown function, and its context and library are leaked.
+    pub fn with_teardown_timeout(mut self, timeout: Duration) -> Self {
+        self.teardown_timeout = timeout;
+        self
+    }
+    
+    /// Get the number of commands in a row that timed out for a plugin
+    pub fn consecutive_timeouts(&self, plugin_id: &str) -> u32 {
+        self.consecutive_timeouts.lock().unwrap().get(plugin_id).copied().unwrap_or(0)
+    }
+    
+    /// Check whether a plugin's recent commands finished in time
+    pub fn is_plugin_healthy(&self, plugin_id: &str) -> bool {
+        self.consecutive_timeouts(plugin_id) < self.unhealthy_timeout_threshold
+    }
+    
+    /// Get the callback thread model
+    pub fn thread_model(&self) -> CallbackThreadModel {
+        self.thread_model
+    }
+    
+    /// Set the callback thread model
+    ///
+    /// Only applies to plugins initialized after the call.
+    pub fn set_thread_model(&mut self, thread_model: CallbackThreadModel) {
+        self.thread_model = thread_model;
+    }
+    
+    /// Get the recorder of triggered events
+    pub fn recorder(&self) -> &EventRecorder {
+        &self.recorder
+    }
+    
+    /// Get the event deduplicator
+    pub fn deduplicator(&self) -> &EventDeduplicator {
+        &self.deduplicator
+    }
+    
+    /// Set how long idempotency keys are remembered
+    ///
+    /// Replaces the deduplicator, forgetting previously seen keys.
+    pub fn set_dedup_window(&mut self, dedup_window: Duration) {
+        self.deduplicator = EventDeduplicator::new(dedup_window);
+    }
+    
     /// Initialize a plugin
     pub fn init_plugin(&mut self, plugin_id: String, loaded_plugin: LoadedPlugin) -> Result<(), PluginHostError> {
+        self.init_plugin_with_storage(plugin_id, loaded_plugin, None)
+    }
+    
+    /// Initialize a plugin with access to a key-value store
+    ///
+    /// Without a store, the plugin's `kv_*` context functions return `KV_NO_PERMISSION`.
+    pub fn init_plugin_with_storage(
+        &mut self,
+        plugin_id: String,
+        loaded_plugin: LoadedPlugin,
+        storage: Option<Arc<KvStore>>,
+    ) -> Result<(), PluginHostError> {
+        // Loading a plugin again would replace the running instance without tearing it down
+        if self.plugins.contains_key(&plugin_id) {
+            return Err(PluginHostError::AlreadyLoaded(plugin_id));
+        }
+        
         // Create host data
         let host_data = Arc::new(Mutex::new(HostData {
             plugin_id: plugin_id.clone(),
             callbacks: HashMap::new(),
+            async_callbacks: HashMap::new(),
+            storage,
         }));
         
-        // Create plugin context
-        let context = Box::new(PluginContext {
-            api_version: 1,
-            host_data: Arc::into_raw(host_data.clone()) as *mut c_void,
-            plugin_data: ptr::null_mut(),
-            register_callback: Some(Self::register_callback_trampoline),
-            log: Some(Self::log_trampoline),
-        });
+        // Create plugin context; it is freed when dropped, including on the error paths below
+        let context = OwnedContext::new(&host_data, &self.context_pool);
         
-        // Convert to raw pointer for C interface
-        let context_ptr = Box::into_raw(context);
+        // Start the callback worker, if any; it stays idle until events are queued
+        let callback_lock = Arc::new(Mutex::new(()));
+        let worker = match self.thread_model {
+            CallbackThreadModel::Synchronous => None,
+            CallbackThreadModel::WorkerThread { queue_capacity } => Some(CallbackWorker::spawn(
+                &plugin_id,
+                context.as_ptr(),
+                queue_capacity,
+                callback_lock.clone(),
+                self.panic_reporter.clone(),
+            )?),
+        };
         
         // Call plugin_init
         unsafe {
-            let init_fn = loaded_plugin.get_init_fn()?;
-            let result = init_fn(context_ptr);
+            let init_fn = loaded_plugin.get_init_fn();
+            let result = init_fn(context.as_ptr());
             
             if result != 0 {
-                // Cleanup on failure
-                let _ = Box::from_raw(context_ptr);
                 return Err(PluginHostError::InitializationFailed(result));
             }
+            
+            // Let the plugin register its asynchronous handlers
+            if let Some(register_async_handler) = loaded_plugin.get_register_async_handler_fn() {
+                let result = register_async_handler(context.as_ptr());
+                if result != 0 {
+                    let teardown_fn = loaded_plugin.get_teardown_fn();
+                    teardown_fn(context.as_ptr());
+                    return Err(PluginHostError::InitializationFailed(result));
+                }
+            }
         }
         
-        // Store plugin instance with the raw pointer for FFI calls
-        // The context_ptr is managed by the plugin instance lifecycle
+        // Store plugin instance; the context lives as long as the instance
         self.plugins.insert(plugin_id.clone(), PluginInstance {
-            loaded_plugin,
-            context_ptr,
+            worker,
+            command_worker: OnceLock::new(),
+            callback_lock,
+            context,
             host_data,
+            loaded_plugin,
         });
         
         info!("Plugin {} initialized successfully", plugin_id);
         Ok(())
     }
     
+    /// Register a plugin spawned by an initialized plugin and initialize it
+    ///
+    /// The child is granted only the part of its requested permissions that its
+    /// parent holds. Returns the child's ID, `<parent_id>/<child name>`.
+    pub fn register_child_plugin(&mut self, parent_id: &str, child_plugin: LoadedPlugin) -> Result<String, PluginHostError> {
+        if !self.plugins.contains_key(parent_id) {
+            return Err(PluginHostError::CommunicationError(format!("Plugin not found: {}", parent_id)));
+        }
+        
+        let manifest = &child_plugin.metadata().manifest;
+        let child_id = format!("{}/{}", parent_id, manifest.name);
+        if self.plugins.contains_key(&child_id) {
+            return Err(PluginHostError::CommunicationError(format!("Plugin already loaded: {}", child_id)));
+        }
+        
+        let permissions = inherit_permissions(&self.effective_permissions(parent_id), &manifest.permissions);
+        
+        self.init_plugin(child_id.clone(), child_plugin)?;
+        self.child_registry.register(parent_id, &child_id, permissions);
+        
+        info!("Plugin {} registered child plugin {}", parent_id, child_id);
+        Ok(child_id)
+    }
+    
+    /// Get the IDs of a plugin's direct children
+    pub fn list_children(&self, plugin_id: &str) -> Vec<String> {
+        self.child_registry.children_of(plugin_id)
+    }
+    
+    /// Get the permissions a loaded plugin holds
+    ///
+    /// Top-level plugins hold the permissions in their manifest; child plugins
+    /// hold those they inherited from their parent.
+    pub fn effective_permissions(&self, plugin_id: &str) -> Vec<Permission> {
+        if let Some(permissions) = self.child_registry.permissions_of(plugin_id) {
+            return permissions.to_vec();
+        }
+        
+        self.plugins.get(plugin_id)
+            .map(|plugin| plugin.loaded_plugin.metadata().manifest.permissions.clone())
+            .unwrap_or_default()
+    }
+    
     /// Teardown a plugin
+    ///
+    /// Child plugins are torn down first, deepest descendants before their parents.
     pub fn teardown_plugin(&mut self, plugin_id: &str) -> Result<(), PluginHostError> {
+        if !self.plugins.contains_key(plugin_id) {
+            return Err(PluginHostError::CommunicationError(format!("Plugin not found: {}", plugin_id)));
+        }
+        
+        // Teardown children, continuing past failures so none is leaked
+        let mut child_error = None;
+        for child_id in self.child_registry.children_of(plugin_id) {
+            if let Err(e) = self.teardown_plugin(&child_id) {
+                error!("Failed to teardown child plugin {}: {}", child_id, e);
+                child_error.get_or_insert(e);
+            }
+        }
+        
+        self.child_registry.remove(plugin_id);
+        self.teardown_single_plugin(plugin_id)?;
+        
+        match child_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+    
+    /// Teardown a plugin without its children
+    fn teardown_single_plugin(&mut self, plugin_id: &str) -> Result<(), PluginHostError> {
         // Find the plugin
-        let plugin = self.plugins.remove(plugin_id).ok_or_else(|| {
+        let mut plugin = self.plugins.remove(plugin_id).ok_or_else(|| {
             PluginHostError::CommunicationError(format!("Plugin not found: {}", plugin_id))
         })?;
         
+        // Drain queued callbacks so the context is no longer used by the workers
+        let deadline = Instant::now() + self.teardown_timeout;
+        let mut workers_stopped = true;
+        if let Some(mut worker) = plugin.worker.take() {
+            workers_stopped &= worker.shutdown_within(deadline.saturating_duration_since(Instant::now()));
+        }
+        if let Some(mut worker) = plugin.command_worker.take() {
+            workers_stopped &= worker.shutdown_within(deadline.saturating_duration_since(Instant::now()));
+        }
+        
+        // A blocked callback still uses the context and library, so they are leaked
+        if !workers_stopped {
+            error!("Plugin {} is still running a callback, unloading it without teardown", plugin_id);
+            std::mem::forget(plugin);
+            return Err(PluginHostError::TeardownTimedOut(plugin_id.to_owned()));
+        }
+        
         // Call plugin_teardown
-        unsafe {
-            let teardown_fn = plugin.loaded_plugin.get_teardown_fn()?;
-            let result = teardown_fn(plugin.context_ptr);
-            
-            // Clean up resources
-            let _ = Box::from_raw(plugin.context_ptr);
-            // We don't need to call Arc::from_raw since we're using normal Arc
-            
-            if result != 0 {
-                return Err(PluginHostError::TeardownFailed(result));
-            }
+        let result = unsafe {
+            let teardown_fn = plugin.loaded_plugin.get_teardown_fn();
+            teardown_fn(plugin.context.as_ptr())
+        };
+        
+        // Free the context whether or not teardown succeeded
+        drop(plugin);
+        
+        if result != 0 {
+            return Err(PluginHostError::TeardownFailed(result));
         }
         
         info!("Plugin {} torn down successfully", plugin_id);
         Ok(())
     }
     
-    /// Trigger an event on a plugin
-    pub fn trigger_event(&self, plugin_id: &str, event_name: &str, event_data: &str) -> Result<i32, PluginHostError> {
+    /// Trigger an event on a plugin and wait for the callback's result
+    ///
+    /// Returns `DEDUP_SKIPPED_CODE` without invoking the callback when the event
+    /// carries an idempotency key already seen within the deduplication window.
+    pub fn trigger_event(
+        &self,
+        plugin_id: &str,
+        event_name: &str,
+        event: impl Into<EventEnvelope>,
+    ) -> Result<i32, PluginHostError> {
+        self.dispatch_event(plugin_id, event_name, event)?.wait()
+    }
+    
+    /// Execute a plugin command, giving up once the command's timeout has elapsed
+    pub async fn execute_command(
+        &self,
+        plugin_id: &str,
+        command: &str,
+        event: impl Into<EventEnvelope>,
+    ) -> Result<i32, PluginHostError> {
+        self.trigger_event_with_timeout(plugin_id, command, event, self.command_timeout(command)).await
+    }
+    
+    /// Trigger an event on a plugin, giving up once the timeout has elapsed
+    ///
+    /// The callback always runs on a worker thread; plugins using the synchronous
+    /// model get a command worker on their first timed event. When the timeout
+    /// fires the host sets the context's `shutdown_requested` flag and gives the
+    /// plugin 500 ms to unblock. Plugins run inside the host process, so a callback that
+    /// is still blocked after that cannot be forcibly terminated: its worker
+    /// thread leaks if the callback never returns, and tearing the plugin down
+    /// waits for it. Repeated timeouts make the plugin unhealthy.
+    pub async fn trigger_event_with_timeout(
+        &self,
+        plugin_id: &str,
+        event_name: &str,
+        event: impl Into<EventEnvelope>,
+        timeout: Duration,
+    ) -> Result<i32, PluginHostError> {
+        let mut timed_event = self.dispatch_with_timeout(plugin_id, event_name, event, timeout)?;
+        
+        if let Some(output) = timed_event.wait().await {
+            self.record_completion(plugin_id);
+            return output.map(|output| output.code);
+        }
+        
+        self.record_timeout(plugin_id, event_name);
+        Err(timed_event.finish_timed_out().await)
+    }
+    
+    /// Dispatch a plugin command without waiting for it, subject to the command's timeout
+    ///
+    /// Callers holding the host lock can release it before waiting on the returned handle.
+    pub fn dispatch_command(
+        &self,
+        plugin_id: &str,
+        command: &str,
+        event: impl Into<EventEnvelope>,
+    ) -> Result<TimedEvent, PluginHostError> {
+        self.dispatch_with_timeout(plugin_id, command, event, self.command_timeout(command))
+    }
+    
+    /// Dispatch an event to a worker thread without waiting for it, subject to a timeout
+    ///
+    /// The handle also yields the JSON result the callback reports through `set_result`.
+    pub fn dispatch_with_timeout(
+        &self,
+        plugin_id: &str,
+        event_name: &str,
+        event: impl Into<EventEnvelope>,
+        timeout: Duration,
+    ) -> Result<TimedEvent, PluginHostError> {
+        let (result_sender, result) = oneshot::channel();
+        let pending = self.dispatch(plugin_id, event_name, event.into(), false, Some(result_sender))?;
+        
+        Ok(TimedEvent {
+            pending,
+            result,
+            plugin_id: plugin_id.to_owned(),
+            event_name: event_name.to_owned(),
+            timeout,
+        })
+    }
+    
+    /// Record that a timed event completed in time, resetting the plugin's timeout count
+    pub fn record_completion(&self, plugin_id: &str) {
+        self.consecutive_timeouts.lock().unwrap().remove(plugin_id);
+    }
+    
+    /// Record a timed out event and ask the plugin to stop the running callback
+    ///
+    /// Returns the plugin's number of consecutive timeouts.
+    pub fn record_timeout(&self, plugin_id: &str, event_name: &str) -> u32 {
+        let timeouts = {
+            let mut consecutive_timeouts = self.consecutive_timeouts.lock().unwrap();
+            let timeouts = consecutive_timeouts.entry(plugin_id.to_owned()).or_insert(0);
+            *timeouts += 1;
+            *timeouts
+        };
+        if timeouts == self.unhealthy_timeout_threshold {
+            error!("Plugin {} is unhealthy after {} consecutive command timeouts", plugin_id, timeouts);
+        }
+        
+        warn!("Plugin {} timed out handling event '{}', requesting graceful shutdown", plugin_id, event_name);
+        
+        // Ask the plugin to interrupt the running command; an event would queue behind it
+        match self.plugins.get(plugin_id) {
+            Some(plugin) => unsafe {
+                (*plugin.context.as_ptr()).shutdown_requested.store(1, Ordering::SeqCst);
+            },
+            None => warn!("Failed to request graceful shutdown of unloaded plugin {}", plugin_id),
+        }
+        
+        timeouts
+    }
+    
+    /// Dispatch an event to a plugin, returning a handle to the callback's result
+    ///
+    /// With the synchronous thread model the callback runs before this returns;
+    /// with the worker thread model it is queued and this returns immediately.
+    pub fn dispatch_event(
+        &self,
+        plugin_id: &str,
+        event_name: &str,
+        event: impl Into<EventEnvelope>,
+    ) -> Result<PendingEvent, PluginHostError> {
+        self.dispatch(plugin_id, event_name, event.into(), false, None)
+    }
+    
+    /// Trigger an event on a plugin, preferring its asynchronous handler
+    ///
+    /// The returned handle resolves once the plugin completes the event through
+    /// `complete_async`. Events without an asynchronous handler are dispatched
+    /// to the plugin's regular callback.
+    pub fn trigger_event_async(
+        &self,
+        plugin_id: &str,
+        event_name: &str,
+        event: impl Into<EventEnvelope>,
+    ) -> Result<PendingEvent, PluginHostError> {
+        self.dispatch(plugin_id, event_name, event.into(), true, None)
+    }
+    
+    /// Dispatch an event, optionally to an asynchronous handler or off the caller's thread
+    fn dispatch(
+        &self,
+        plugin_id: &str,
+        event_name: &str,
+        event: EventEnvelope,
+        allow_async: bool,
+        command_result: Option<CommandResultSender>,
+    ) -> Result<PendingEvent, PluginHostError> {
+        let event_data = event.data.as_str();
+        
+        self.recorder.record(plugin_id, event_name, event_data);
+        
         // Find the plugin
         let plugin = self.plugins.get(plugin_id).ok_or_else(|| {
             PluginHostError::CommunicationError(format!("Plugin not found: {}", plugin_id))
@@ -199,28 +1264,185 @@ impl PluginHost {
         // Get the callback
         let callback = {
             let host_data = plugin.host_data.lock().unwrap();
-            host_data.callbacks.get(event_name).copied()
+            let async_callback = host_data.async_callbacks.get(event_name).filter(|_| allow_async);
+            
+            async_callback.map(|callback| EventCallback::Async(*callback))
+                .or_else(|| host_data.callbacks.get(event_name).map(|callback| EventCallback::Sync(*callback)))
         };
         
-        // Call the callback if registered
-        if let Some(callback_fn) = callback {
-            let c_data = CString::new(event_data).map_err(|e| {
-                PluginHostError::CommunicationError(format!("Invalid event data: {}", e))
-            })?;
-            
-            unsafe {
-                // Use the raw pointer for FFI calls instead of the thread-safe wrapper
-                let result = callback_fn(
-                    plugin.context_ptr,
-                    c_data.as_ptr(),
-                    event_data.len() as u32,
-                );
-                
-                Ok(result)
+        let callback_fn = callback.ok_or_else(|| {
+            PluginHostError::InvalidEventName(format!("No callback registered for event: {}", event_name))
+        })?;
+        
+        let c_data = CString::new(event_data).map_err(|e| {
+            PluginHostError::CommunicationError(format!("Invalid event data: {}", e))
+        })?;
+        let data_len = event_data.len() as u32;
+        
+        // Skip events that were already delivered
+        if let Some(key) = &event.idempotency_key {
+            if self.deduplicator.is_duplicate(plugin_id, event_name, key) {
+                debug!("Skipping duplicate event '{}' for plugin {}", event_name, plugin_id);
+                return Ok(PendingEvent::ready(DEDUP_SKIPPED_CODE));
             }
-        } else {
-            Err(PluginHostError::InvalidEventName(format!("No callback registered for event: {}", event_name)))
         }
+        
+        let trace_context = self.event_trace_context(plugin_id, event_name, event.trace_context.as_ref());
+        
+        let worker = match &plugin.worker {
+            Some(worker) => Some(worker),
+            None if command_result.is_some() => Some(self.command_worker(plugin_id, plugin)?),
+            None => None,
+        };
+        
+        match worker {
+            Some(worker) => {
+                let (result_sender, receiver) = oneshot::channel();
+                worker.submit(plugin_id, CallbackJob {
+                    callback: callback_fn,
+                    event_data: c_data,
+                    data_len,
+                    trace_context,
+                    result_sender,
+                    command_result,
+                })?;
+                
+                Ok(PendingEvent { receiver })
+            },
+            None => {
+                let (result_sender, receiver) = oneshot::channel();
+                
+                // Wait for a callback running on the command worker, then use the raw pointer for FFI calls
+                let result = {
+                    let _callback_guard = plugin.callback_lock.lock().unwrap();
+                    unsafe {
+                        PluginContext::begin_callback(plugin.context.as_ptr(), trace_context);
+                        callback_fn.invoke(plugin.context.as_ptr(), c_data.as_ptr(), data_len, result_sender)
+                    }
+                };
+                
+                if let Err(message) = result {
+                    self.panic_reporter.report(plugin_id, message);
+                }
+                
+                Ok(PendingEvent { receiver })
+            },
+        }
+    }
+    
+    /// Get the command worker of a plugin using the synchronous model, starting it if needed
+    fn command_worker<'a>(&self, plugin_id: &str, plugin: &'a PluginInstance) -> Result<&'a CallbackWorker, PluginHostError> {
+        if let Some(worker) = plugin.command_worker.get() {
+            return Ok(worker);
+        }
+        
+        let worker = CallbackWorker::spawn(
+            plugin_id,
+            plugin.context.as_ptr(),
+            COMMAND_QUEUE_CAPACITY,
+            plugin.callback_lock.clone(),
+            self.panic_reporter.clone(),
+        )?;
+        
+        // A worker started concurrently wins; the idle loser exits once dropped
+        let _ = plugin.command_worker.set(worker);
+        Ok(plugin.command_worker.get().expect("command worker was just set"))
+    }
+    
+    /// Trigger an event on a plugin, receiving its result as a stream of chunks
+    ///
+    /// Plugins exporting `plugin_execute_streaming` handle the event on a
+    /// dedicated thread and report chunks of at most `max_chunk_size` bytes,
+    /// with up to `STREAM_CHANNEL_CAPACITY` chunks buffered. Other plugins handle
+    /// it through their registered callback, and the stream yields the return code.
+    pub fn trigger_event_streaming(
+        &self,
+        plugin_id: &str,
+        event_name: &str,
+        event_data: &str,
+        max_chunk_size: usize,
+    ) -> Result<EventStream<'_>, PluginHostError> {
+        // Find the plugin
+        let plugin = self.plugins.get(plugin_id).ok_or_else(|| {
+            PluginHostError::CommunicationError(format!("Plugin not found: {}", plugin_id))
+        })?;
+        
+        let execute_streaming = match plugin.loaded_plugin.get_execute_streaming_fn() {
+            Some(execute_streaming) => execute_streaming,
+            None => return Ok(EventStream::single(self.dispatch_event(plugin_id, event_name, event_data)?)),
+        };
+        
+        self.recorder.record(plugin_id, event_name, event_data);
+        
+        let c_event_name = CString::new(event_name).map_err(|e| {
+            PluginHostError::InvalidEventName(format!("Invalid event name: {}", e))
+        })?;
+        let c_data = CString::new(event_data).map_err(|e| {
+            PluginHostError::CommunicationError(format!("Invalid event data: {}", e))
+        })?;
+        
+        let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_CHANNEL_CAPACITY);
+        let context = WorkerContext(plugin.context.as_ptr());
+        let callback_lock = plugin.callback_lock.clone();
+        let trace_context = self.event_trace_context(plugin_id, event_name, None);
+        let max_chunk_size = max_chunk_size.max(1);
+        let error_message = format!("Plugin {} failed to stream event '{}'", plugin_id, event_name);
+        
+        let producer = thread::Builder::new()
+            .name(format!("plugin-{}-stream", plugin_id))
+            .spawn(move || {
+                let result_sender = sender.clone();
+                CHUNK_SINK.with(|sink| *sink.borrow_mut() = Some(ChunkSink { sender, max_chunk_size }));
+                
+                let result = {
+                    let _callback_guard = callback_lock.lock().unwrap();
+                    unsafe {
+                        PluginContext::begin_callback(context.as_ptr(), trace_context);
+                        execute_streaming(context.as_ptr(), c_event_name.as_ptr(), c_data.as_ptr(), chunk_trampoline)
+                    }
+                };
+                
+                CHUNK_SINK.with(|sink| sink.borrow_mut().take());
+                
+                if result != 0 {
+                    let error = PluginHostError::CommunicationError(format!("{} with code: {}", error_message, result));
+                    let _ = result_sender.blocking_send(Err(error));
+                }
+            })
+            .map_err(|e| {
+                PluginHostError::CommunicationError(format!("Failed to spawn streaming thread: {}", e))
+            })?;
+        
+        Ok(EventStream::chunks(receiver, producer))
+    }
+    
+    /// Give a loaded plugin access to a key-value store
+    ///
+    /// Used when the storage permission is granted while the plugin is running.
+    pub fn attach_storage(&self, plugin_id: &str, storage: Arc<KvStore>) -> Result<(), PluginHostError> {
+        let plugin = self.plugins.get(plugin_id).ok_or_else(|| {
+            PluginHostError::CommunicationError(format!("Plugin not found: {}", plugin_id))
+        })?;
+        
+        plugin.host_data.lock().unwrap().storage = Some(storage);
+        Ok(())
+    }
+    
+    /// Stop a loaded plugin from using its key-value store
+    ///
+    /// Later storage calls from the plugin fail with `KV_NO_PERMISSION`.
+    pub fn detach_storage(&self, plugin_id: &str) -> Result<(), PluginHostError> {
+        let plugin = self.plugins.get(plugin_id).ok_or_else(|| {
+            PluginHostError::CommunicationError(format!("Plugin not found: {}", plugin_id))
+        })?;
+        
+        plugin.host_data.lock().unwrap().storage = None;
+        Ok(())
+    }
+    
+    /// Get the IDs of all loaded plugins
+    pub fn loaded_plugins(&self) -> Vec<String> {
+        self.plugins.keys().cloned().collect()
     }
     
     /// Check if a plugin is loaded
@@ -271,6 +1493,41 @@ impl PluginHost {
         0 // Success
     }
     
+    /// Register asynchronous callback trampoline function
+    unsafe extern "C" fn register_async_callback_trampoline(
+        context: *mut PluginContext,
+        event_name: *const c_char,
+        callback: Option<AsyncCallbackFn>,
+    ) -> c_int {
+        if context.is_null() || event_name.is_null() {
+            return -1;
+        }
+        
+        let context_ref = &*context;
+        if context_ref.host_data.is_null() {
+            return -2;
+        }
+        
+        let event_name_str = match CStr::from_ptr(event_name).to_str() {
+            Ok(s) => s,
+            Err(_) => return -3,
+        };
+        
+        let callback_fn = match callback {
+            Some(cb) => cb,
+            None => return -4,
+        };
+        
+        let host_data = &*(context_ref.host_data as *const Mutex<HostData>);
+        match host_data.lock() {
+            Ok(mut host_data) => {
+                host_data.async_callbacks.insert(event_name_str.to_owned(), callback_fn);
+                0
+            },
+            Err(_) => -5,
+        }
+    }
+    
     /// Log trampoline function
     unsafe extern "C" fn log_trampoline(
         context: *mut PluginContext,
@@ -312,6 +1569,111 @@ impl PluginHost {
             _ => info!("[Plugin {}] {}", plugin_id, message_str),
         }
     }
+    
+    /// Get the key-value store of the plugin owning a context
+    unsafe fn context_storage(context: *mut PluginContext) -> Result<Arc<KvStore>, c_int> {
+        if context.is_null() {
+            return Err(-1);
+        }
+        
+        let context_ref = &*context;
+        
+        if context_ref.host_data.is_null() {
+            return Err(-2);
+        }
+        
+        let host_data = &*(context_ref.host_data as *const Mutex<HostData>);
+        let storage = match host_data.lock() {
+            Ok(lock) => lock.storage.clone(),
+            Err(_) => return Err(-5),
+        };
+        
+        storage.ok_or(KV_NO_PERMISSION)
+    }
+    
+    /// Convert a string argument passed by a plugin
+    unsafe fn c_str_arg<'a>(value: *const c_char) -> Result<&'a str, c_int> {
+        if value.is_null() {
+            return Err(-1);
+        }
+        
+        CStr::from_ptr(value).to_str().map_err(|_| -3)
+    }
+    
+    /// Key-value read trampoline function
+    unsafe extern "C" fn kv_get_trampoline(
+        context: *mut PluginContext,
+        key: *const c_char,
+        buffer: *mut c_char,
+        buffer_len: u32,
+    ) -> c_int {
+        let storage = match Self::context_storage(context) {
+            Ok(storage) => storage,
+            Err(code) => return code,
+        };
+        let key = match Self::c_str_arg(key) {
+            Ok(key) => key,
+            Err(code) => return code,
+        };
+        
+        let value = match storage.get(key) {
+            Some(value) => value,
+            None => return KV_NOT_FOUND,
+        };
+        
+        // Copy the value only when it fits together with its terminator
+        if !buffer.is_null() && value.len() < buffer_len as usize {
+            ptr::copy_nonoverlapping(value.as_ptr() as *const c_char, buffer, value.len());
+            *buffer.add(value.len()) = 0;
+        }
+        
+        value.len() as c_int
+    }
+    
+    /// Key-value write trampoline function
+    unsafe extern "C" fn kv_set_trampoline(
+        context: *mut PluginContext,
+        key: *const c_char,
+        value: *const c_char,
+    ) -> c_int {
+        let storage = match Self::context_storage(context) {
+            Ok(storage) => storage,
+            Err(code) => return code,
+        };
+        let (key, value) = match (Self::c_str_arg(key), Self::c_str_arg(value)) {
+            (Ok(key), Ok(value)) => (key, value),
+            (Err(code), _) | (_, Err(code)) => return code,
+        };
+        
+        match storage.set(key, value) {
+            Ok(()) => 0,
+            Err(e) => {
+                warn!("Plugin key-value write failed: {}", e);
+                e.code()
+            },
+        }
+    }
+    
+    /// Key-value delete trampoline function
+    unsafe extern "C" fn kv_delete_trampoline(context: *mut PluginContext, key: *const c_char) -> c_int {
+        let storage = match Self::context_storage(context) {
+            Ok(storage) => storage,
+            Err(code) => return code,
+        };
+        let key = match Self::c_str_arg(key) {
+            Ok(key) => key,
+            Err(code) => return code,
+        };
+        
+        match storage.delete(key) {
+            Ok(true) => 0,
+            Ok(false) => KV_NOT_FOUND,
+            Err(e) => {
+                warn!("Plugin key-value delete failed: {}", e);
+                e.code()
+            },
+        }
+    }
 }
 
 impl Drop for PluginHost {
@@ -320,6 +1682,11 @@ impl Drop for PluginHost {
         let plugin_ids: Vec<String> = self.plugins.keys().cloned().collect();
         
         for plugin_id in plugin_ids {
+            // Children are torn down along with their parent
+            if !self.plugins.contains_key(&plugin_id) {
+                continue;
+            }
+            
             if let Err(e) = self.teardown_plugin(&plugin_id) {
                 error!("Failed to teardown plugin {}: {}", plugin_id, e);
             }
@@ -0,0 +1,109 @@
+//! Plugin panic isolation
+//!
+//! Event callbacks use the `C-unwind` ABI, so a panic raised inside one
+//! unwinds back into the host, which catches it at the call site instead of
+//! letting it take down the thread. A panic hook records where such panics
+//! were raised without printing them, and the host reports each caught panic
+//! to its panic listener.
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, Once};
+use log::error;
+
+/// A panic raised inside a plugin callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginPanic {
+    /// ID of the plugin that panicked
+    pub plugin_id: String,
+    
+    /// Panic message, followed by where the panic was raised when known
+    pub message: String,
+}
+
+/// Receiver of the panics raised inside plugin callbacks
+pub type PanicListener = Box<dyn Fn(&PluginPanic) + Send + Sync>;
+
+thread_local! {
+    /// Number of plugin callbacks running on this thread
+    static CALLBACK_DEPTH: Cell<u32> = const { Cell::new(0) };
+    
+    /// Where the last panic inside a plugin callback on this thread was raised
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Shared, replaceable handle to the configured panic listener
+#[derive(Clone, Default)]
+pub(super) struct PanicReporter {
+    /// Listener receiving panics, if one is configured
+    listener: Arc<Mutex<Option<PanicListener>>>,
+}
+
+impl PanicReporter {
+    /// Replace the configured listener
+    pub(super) fn set_listener(&self, listener: PanicListener) {
+        *self.listener.lock().unwrap_or_else(|e| e.into_inner()) = Some(listener);
+    }
+    
+    /// Log a plugin panic and pass it to the listener
+    pub(super) fn report(&self, plugin_id: &str, message: String) {
+        error!("Plugin {} panicked: {}", plugin_id, message);
+        
+        let plugin_panic = PluginPanic {
+            plugin_id: plugin_id.to_owned(),
+            message,
+        };
+        
+        if let Some(listener) = self.listener.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            listener(&plugin_panic);
+        }
+    }
+}
+
+/// Install the hook recording panics raised inside plugin callbacks
+///
+/// Installed once per process; panics elsewhere go to the previous hook.
+pub(super) fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        
+        panic::set_hook(Box::new(move |info| {
+            if CALLBACK_DEPTH.with(Cell::get) == 0 {
+                previous(info);
+                return;
+            }
+            
+            let location = info.location().map(ToString::to_string);
+            PANIC_LOCATION.with(|last| *last.borrow_mut() = location);
+        }));
+    });
+}
+
+/// Run a plugin callback, returning the panic message if it panicked
+pub(super) fn catch_plugin_panic<T>(callback: impl FnOnce() -> T) -> Result<T, String> {
+    CALLBACK_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let result = panic::catch_unwind(AssertUnwindSafe(callback));
+    CALLBACK_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    
+    // Panics the plugin caught itself leave a location behind too
+    let location = PANIC_LOCATION.with(|last| last.borrow_mut().take());
+    
+    result.map_err(|payload| match location {
+        Some(location) => format!("{} at {}", panic_message(payload.as_ref()), location),
+        None => panic_message(payload.as_ref()),
+    })
+}
+
+/// Get the message of a panic payload
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_owned()
+    }
+}
@@ -0,0 +1,164 @@
+//! Plugin context pooling
+//!
+//! Every initialized plugin needs a heap-allocated `PluginContext` whose
+//! address stays fixed while the plugin runs. The pool keeps released contexts
+//! for reuse so that rapid plugin cycling does not allocate each time.
+//!
+//! The pool holds at most `capacity` idle contexts. A larger pool absorbs
+//! bigger bursts of plugin cycling without allocating, at the cost of keeping
+//! that many contexts alive while idle; once the pool is empty, further
+//! contexts are allocated and freed as usual.
+
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use crossbeam_queue::ArrayQueue;
+
+use super::{PluginContext, TraceContext};
+
+/// Number of plugins expected to run at the same time
+pub const EXPECTED_CONCURRENT_PLUGINS: usize = 16;
+
+/// Default number of contexts kept by a pool
+pub const DEFAULT_CONTEXT_POOL_SIZE: usize = EXPECTED_CONCURRENT_PLUGINS * 2;
+
+/// Pool of reusable plugin contexts
+pub struct PluginContextPool {
+    /// Idle contexts
+    contexts: ArrayQueue<Box<PluginContext>>,
+    
+    /// Maximum number of idle contexts
+    pool_size: usize,
+    
+    /// Number of contexts allocated because the pool was empty
+    overflow_allocations: AtomicU64,
+}
+
+// Idle contexts are blank and referenced by no plugin, so they may move between threads
+unsafe impl Send for PluginContextPool {}
+unsafe impl Sync for PluginContextPool {}
+
+impl PluginContextPool {
+    /// Create a pool preallocated with `pool_size` contexts
+    pub fn new(pool_size: usize) -> Self {
+        let contexts = ArrayQueue::new(pool_size.max(1));
+        for _ in 0..pool_size {
+            let _ = contexts.push(Box::new(blank_context()));
+        }
+        
+        Self {
+            contexts,
+            pool_size,
+            overflow_allocations: AtomicU64::new(0),
+        }
+    }
+    
+    /// Take a context from the pool, allocating one if the pool is empty
+    pub fn acquire(self: &Arc<Self>) -> PooledContext {
+        let context = self.contexts.pop().unwrap_or_else(|| {
+            self.overflow_allocations.fetch_add(1, Ordering::Relaxed);
+            Box::new(blank_context())
+        });
+        
+        PooledContext {
+            context_ptr: Box::into_raw(context),
+            pool: self.clone(),
+        }
+    }
+    
+    /// Get the maximum number of idle contexts
+    pub fn capacity(&self) -> usize {
+        self.pool_size
+    }
+    
+    /// Get the number of idle contexts
+    pub fn available(&self) -> usize {
+        self.contexts.len()
+    }
+    
+    /// Get the number of contexts allocated because the pool was empty
+    pub fn overflow_allocations(&self) -> u64 {
+        self.overflow_allocations.load(Ordering::Relaxed)
+    }
+    
+    /// Return a context to the pool, freeing it if the pool is full
+    fn put_back(&self, mut context: Box<PluginContext>) {
+        // Clear pointers left by the previous plugin
+        *context = blank_context();
+        
+        // The queue needs a nonzero capacity, so an empty pool is enforced here
+        if self.contexts.len() < self.pool_size {
+            let _ = self.contexts.push(context);
+        }
+    }
+}
+
+impl Default for PluginContextPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONTEXT_POOL_SIZE)
+    }
+}
+
+/// A plugin context borrowed from a pool and returned to it when dropped
+pub struct PooledContext {
+    /// Context owned by this handle; its address stays fixed until release
+    context_ptr: *mut PluginContext,
+    
+    /// Pool the context is returned to
+    pool: Arc<PluginContextPool>,
+}
+
+impl PooledContext {
+    /// Get the raw context pointer
+    pub fn as_ptr(&self) -> *mut PluginContext {
+        self.context_ptr
+    }
+    
+    /// Return the context to its pool
+    pub fn release(self) {
+        drop(self);
+    }
+}
+
+impl Deref for PooledContext {
+    type Target = PluginContext;
+    
+    fn deref(&self) -> &PluginContext {
+        // The pointer comes from `Box::into_raw` and is valid until the handle drops
+        unsafe { &*self.context_ptr }
+    }
+}
+
+impl DerefMut for PooledContext {
+    fn deref_mut(&mut self) -> &mut PluginContext {
+        unsafe { &mut *self.context_ptr }
+    }
+}
+
+impl Drop for PooledContext {
+    fn drop(&mut self) {
+        // The pointer was created by `PluginContextPool::acquire` and is released only here
+        let context = unsafe { Box::from_raw(self.context_ptr) };
+        self.pool.put_back(context);
+    }
+}
+
+/// Create a context with no host data or functions
+fn blank_context() -> PluginContext {
+    PluginContext {
+        api_version: 0,
+        host_data: ptr::null_mut(),
+        plugin_data: ptr::null_mut(),
+        register_callback: None,
+        log: None,
+        kv_get: None,
+        kv_set: None,
+        kv_delete: None,
+        trace_context: TraceContext::default(),
+        register_async_callback: None,
+        complete_async: None,
+        shutdown_requested: AtomicU32::new(0),
+        set_result: None,
+    }
+}
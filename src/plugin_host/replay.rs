@@ -0,0 +1,234 @@
+//! Event recording and replay
+//!
+//! Records the events delivered to plugins so that a reported issue can be
+//! reproduced by replaying the same sequence against a fresh host. Replays
+//! can run against a mock clock, making their timing and timestamps
+//! independent of the machine they run on.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Serialize, Deserialize};
+
+use super::PluginHost;
+
+/// A single recorded event delivery
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    /// Time the event was triggered
+    pub timestamp: DateTime<Utc>,
+    
+    /// ID of the plugin the event was sent to
+    pub plugin_id: String,
+    
+    /// Name of the event
+    pub event_name: String,
+    
+    /// Event payload
+    pub data: String,
+}
+
+/// A recorded sequence of event deliveries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayLog {
+    /// Recorded events in delivery order
+    pub entries: Vec<ReplayEntry>,
+}
+
+impl ReplayLog {
+    /// Write the log to disk as JSON
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        // Create parent directories if they don't exist
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+    
+    /// Read a log written by `save`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Records the events triggered on a plugin host
+#[derive(Debug, Default)]
+pub struct EventRecorder {
+    /// Events recorded so far, or `None` while not recording
+    entries: Mutex<Option<Vec<ReplayEntry>>>,
+}
+
+impl EventRecorder {
+    /// Create a recorder that is not recording
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Start recording, discarding any events recorded before
+    pub fn start(&self) {
+        *self.entries.lock().unwrap() = Some(Vec::new());
+        info!("Started recording plugin events");
+    }
+    
+    /// Stop recording and return the recorded events
+    pub fn stop(&self) -> ReplayLog {
+        let entries = self.entries.lock().unwrap().take().unwrap_or_default();
+        info!("Stopped recording plugin events, {} recorded", entries.len());
+        
+        ReplayLog { entries }
+    }
+    
+    /// Check whether events are being recorded
+    pub fn is_recording(&self) -> bool {
+        self.entries.lock().unwrap().is_some()
+    }
+    
+    /// Write the events recorded so far to disk without stopping
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let entries = self.entries.lock().unwrap().clone().unwrap_or_default();
+        ReplayLog { entries }.save(path)
+    }
+    
+    /// Record an event delivery if recording
+    pub(super) fn record(&self, plugin_id: &str, event_name: &str, data: &str) {
+        if let Some(entries) = self.entries.lock().unwrap().as_mut() {
+            entries.push(ReplayEntry {
+                timestamp: Utc::now(),
+                plugin_id: plugin_id.to_owned(),
+                event_name: event_name.to_owned(),
+                data: data.to_owned(),
+            });
+        }
+    }
+}
+
+/// Source of time for replays
+pub trait ReplayClock: Send + Sync {
+    /// Get the current time
+    fn now(&self) -> DateTime<Utc>;
+    
+    /// Wait for a duration
+    fn sleep(&self, duration: Duration);
+}
+
+/// Clock using the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl ReplayClock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+    
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Clock whose time only moves when it is slept on
+///
+/// Sleeping advances the clock immediately, so replays finish without waiting
+/// and report the same timestamps on every run.
+#[derive(Debug)]
+pub struct MockClock {
+    /// Current time
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// Create a clock starting at the given time
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(start) }
+    }
+}
+
+impl ReplayClock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+    
+    fn sleep(&self, duration: Duration) {
+        if let Ok(duration) = chrono::Duration::from_std(duration) {
+            *self.now.lock().unwrap() += duration;
+        }
+    }
+}
+
+/// Outcome of replaying a single event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayResult {
+    /// The replayed event
+    pub entry: ReplayEntry,
+    
+    /// Time the event was replayed, according to the replay clock
+    pub replayed_at: DateTime<Utc>,
+    
+    /// Value returned by the plugin callback
+    pub return_code: Option<i32>,
+    
+    /// Error raised while delivering the event
+    pub error: Option<String>,
+}
+
+/// Replays recorded events against a plugin host
+pub struct EventReplayer {
+    /// Clock used to pace the replay
+    clock: Box<dyn ReplayClock>,
+}
+
+impl EventReplayer {
+    /// Create a replayer paced by the system clock
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+    
+    /// Create a replayer paced by a custom clock
+    pub fn with_clock<C: ReplayClock + 'static>(clock: C) -> Self {
+        Self { clock: Box::new(clock) }
+    }
+    
+    /// Replay a log, waiting between events as long as during recording
+    ///
+    /// Delays are divided by `speed`; a speed of zero or less replays the events
+    /// back to back. Every event is delivered even if an earlier one failed.
+    pub fn replay(&self, log: &ReplayLog, host: &PluginHost, speed: f64) -> Vec<ReplayResult> {
+        let mut results = Vec::with_capacity(log.entries.len());
+        let mut previous: Option<DateTime<Utc>> = None;
+        
+        for entry in &log.entries {
+            if let Some(previous) = previous {
+                let delay = (entry.timestamp - previous).to_std().unwrap_or_default();
+                if speed > 0.0 && !delay.is_zero() {
+                    self.clock.sleep(delay.div_f64(speed));
+                }
+            }
+            previous = Some(entry.timestamp);
+            
+            let replayed_at = self.clock.now();
+            let (return_code, error) = match host.trigger_event(&entry.plugin_id, &entry.event_name, entry.data.as_str()) {
+                Ok(code) => (Some(code), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            
+            results.push(ReplayResult {
+                entry: entry.clone(),
+                replayed_at,
+                return_code,
+                error,
+            });
+        }
+        
+        results
+    }
+}
+
+impl Default for EventReplayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
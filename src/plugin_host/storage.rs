@@ -0,0 +1,199 @@
+//! Plugin key-value storage
+//!
+//! A small persistent key-value store the host exposes to plugins holding the
+//! `Storage` permission. Each plugin gets its own store file inside its data
+//! directory, so one plugin can never see another plugin's keys.
+
+use std::collections::BTreeMap;
+use std::ffi::c_int;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// Name of the store file inside a plugin's data directory
+pub const KV_STORE_FILE: &str = "kv_store.json";
+
+/// Default maximum key length in bytes
+pub const DEFAULT_MAX_KEY_BYTES: usize = 256;
+
+/// Default maximum value length in bytes
+pub const DEFAULT_MAX_VALUE_BYTES: usize = 64 * 1024;
+
+/// Default total size of a plugin's keys and values in bytes
+pub const DEFAULT_KV_QUOTA_BYTES: usize = 1024 * 1024;
+
+/// Return code of `kv_get` and `kv_delete` when the key does not exist
+pub const KV_NOT_FOUND: c_int = -6;
+
+/// Return code when the plugin does not hold the `Storage` permission
+pub const KV_NO_PERMISSION: c_int = -7;
+
+/// Return code when a key, a value, or the quota exceeds its limit
+pub const KV_LIMIT_EXCEEDED: c_int = -8;
+
+/// Return code when the store could not be written
+pub const KV_IO_ERROR: c_int = -9;
+
+/// Size limits of a key-value store
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KvLimits {
+    /// Maximum key length in bytes
+    pub max_key_bytes: usize,
+    
+    /// Maximum value length in bytes
+    pub max_value_bytes: usize,
+    
+    /// Maximum total size of all keys and values in bytes
+    pub quota_bytes: usize,
+}
+
+impl Default for KvLimits {
+    fn default() -> Self {
+        Self {
+            max_key_bytes: DEFAULT_MAX_KEY_BYTES,
+            max_value_bytes: DEFAULT_MAX_VALUE_BYTES,
+            quota_bytes: DEFAULT_KV_QUOTA_BYTES,
+        }
+    }
+}
+
+/// Error type for key-value store operations
+#[derive(Error, Debug)]
+pub enum KvStoreError {
+    /// The key is empty or longer than allowed
+    #[error("Invalid key length: {0} bytes")]
+    InvalidKey(usize),
+    
+    /// The value is longer than allowed
+    #[error("Value too large: {0} bytes")]
+    ValueTooLarge(usize),
+    
+    /// Storing the entry would exceed the store's quota
+    #[error("Storage quota exceeded: {used} of {quota} bytes")]
+    QuotaExceeded {
+        /// Bytes the store would use
+        used: usize,
+        /// Quota in bytes
+        quota: usize,
+    },
+    
+    /// The store could not be read or written
+    #[error("Storage I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl KvStoreError {
+    /// Get the return code reported to plugins for the error
+    pub fn code(&self) -> c_int {
+        match self {
+            KvStoreError::Io(_) => KV_IO_ERROR,
+            _ => KV_LIMIT_EXCEEDED,
+        }
+    }
+}
+
+/// Persistent key-value store of a single plugin
+pub struct KvStore {
+    /// Path of the store file
+    path: PathBuf,
+    
+    /// Size limits
+    limits: KvLimits,
+    
+    /// Stored entries
+    entries: Mutex<BTreeMap<String, String>>,
+}
+
+impl KvStore {
+    /// Open the store in a plugin's data directory, creating it if needed
+    pub fn open(data_dir: &Path, limits: KvLimits) -> io::Result<Self> {
+        let path = data_dir.join(KV_STORE_FILE);
+        let entries = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        
+        Ok(Self {
+            path,
+            limits,
+            entries: Mutex::new(entries),
+        })
+    }
+    
+    /// Get the size limits
+    pub fn limits(&self) -> KvLimits {
+        self.limits
+    }
+    
+    /// Get a value
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+    
+    /// Store a value, replacing any previous value
+    pub fn set(&self, key: &str, value: &str) -> Result<(), KvStoreError> {
+        if key.is_empty() || key.len() > self.limits.max_key_bytes {
+            return Err(KvStoreError::InvalidKey(key.len()));
+        }
+        if value.len() > self.limits.max_value_bytes {
+            return Err(KvStoreError::ValueTooLarge(value.len()));
+        }
+        
+        let mut entries = self.entries.lock().unwrap();
+        let replaced = entries.get(key).map(|old| key.len() + old.len()).unwrap_or(0);
+        let used = used_bytes(&entries) - replaced + key.len() + value.len();
+        if used > self.limits.quota_bytes {
+            return Err(KvStoreError::QuotaExceeded { used, quota: self.limits.quota_bytes });
+        }
+        
+        let previous = entries.insert(key.to_owned(), value.to_owned());
+        if let Err(e) = self.save(&entries) {
+            // Keep memory consistent with what is on disk
+            match previous {
+                Some(previous) => entries.insert(key.to_owned(), previous),
+                None => entries.remove(key),
+            };
+            return Err(e.into());
+        }
+        
+        Ok(())
+    }
+    
+    /// Delete a value, returning whether it existed
+    pub fn delete(&self, key: &str) -> Result<bool, KvStoreError> {
+        let mut entries = self.entries.lock().unwrap();
+        let previous = match entries.remove(key) {
+            Some(previous) => previous,
+            None => return Ok(false),
+        };
+        
+        if let Err(e) = self.save(&entries) {
+            entries.insert(key.to_owned(), previous);
+            return Err(e.into());
+        }
+        
+        Ok(true)
+    }
+    
+    /// Get the total size of all keys and values in bytes
+    pub fn used_bytes(&self) -> usize {
+        used_bytes(&self.entries.lock().unwrap())
+    }
+    
+    /// Write the entries to the store file
+    fn save(&self, entries: &BTreeMap<String, String>) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        fs::write(&self.path, serde_json::to_string(entries)?)
+    }
+}
+
+/// Get the total size of the keys and values in bytes
+fn used_bytes(entries: &BTreeMap<String, String>) -> usize {
+    entries.iter().map(|(key, value)| key.len() + value.len()).sum()
+}
@@ -0,0 +1,140 @@
+//! Streamed event results
+//!
+//! Lets plugins return large results in chunks through the optional
+//! `plugin_execute_streaming` export instead of building them in one buffer.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread::JoinHandle;
+use bytes::Bytes;
+use futures::Stream;
+use log::error;
+use tokio::sync::mpsc;
+
+use super::{PendingEvent, PluginContext, PluginHost, PluginHostError};
+
+/// Number of chunks buffered between a plugin and the stream consumer
+pub const STREAM_CHANNEL_CAPACITY: usize = 16;
+
+/// Destination of the chunks produced on the current thread
+pub(super) struct ChunkSink {
+    /// Sends chunks to the stream
+    pub(super) sender: mpsc::Sender<Result<Bytes, PluginHostError>>,
+    /// Largest chunk passed to the stream
+    pub(super) max_chunk_size: usize,
+}
+
+thread_local! {
+    /// Sink for chunks reported by the plugin call running on this thread
+    pub(super) static CHUNK_SINK: RefCell<Option<ChunkSink>> = const { RefCell::new(None) };
+}
+
+/// Chunk callback handed to `plugin_execute_streaming`
+///
+/// Only chunks reported from the thread running the streaming call are
+/// delivered; chunks are dropped once the stream has been dropped.
+pub(super) unsafe extern "C" fn chunk_trampoline(_context: *mut PluginContext, data: *const u8, len: usize) {
+    if data.is_null() || len == 0 {
+        return;
+    }
+    
+    let data = std::slice::from_raw_parts(data, len);
+    
+    CHUNK_SINK.with(|sink| {
+        if let Some(sink) = sink.borrow().as_ref() {
+            for chunk in data.chunks(sink.max_chunk_size) {
+                if sink.sender.blocking_send(Ok(Bytes::copy_from_slice(chunk))).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Where the items of an event stream come from
+enum StreamSource {
+    /// Chunks produced by a streaming plugin call on a dedicated thread
+    Chunks {
+        /// Receives chunks from the plugin call
+        receiver: mpsc::Receiver<Result<Bytes, PluginHostError>>,
+        /// Thread running the plugin call
+        producer: Option<JoinHandle<()>>,
+    },
+    
+    /// Result of a plain event, yielded as a single item
+    Single(Option<PendingEvent>),
+}
+
+/// Stream of result chunks from a plugin event
+///
+/// Borrows the host so the plugin cannot be torn down while its streaming
+/// call is running. Dropping the stream waits for the plugin call to return.
+pub struct EventStream<'a> {
+    /// Source of the stream's items
+    source: StreamSource,
+    /// Host the plugin belongs to
+    _host: PhantomData<&'a PluginHost>,
+}
+
+impl EventStream<'_> {
+    /// Create a stream fed by a streaming plugin call
+    pub(super) fn chunks(receiver: mpsc::Receiver<Result<Bytes, PluginHostError>>, producer: JoinHandle<()>) -> Self {
+        Self {
+            source: StreamSource::Chunks {
+                receiver,
+                producer: Some(producer),
+            },
+            _host: PhantomData,
+        }
+    }
+    
+    /// Create a stream yielding the result of a plain event
+    ///
+    /// The callback's return code is yielded as its decimal text.
+    pub(super) fn single(pending: PendingEvent) -> Self {
+        Self {
+            source: StreamSource::Single(Some(pending)),
+            _host: PhantomData,
+        }
+    }
+}
+
+impl Stream for EventStream<'_> {
+    type Item = Result<Bytes, PluginHostError>;
+    
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match &mut self.source {
+            StreamSource::Chunks { receiver, .. } => receiver.poll_recv(cx),
+            StreamSource::Single(pending) => {
+                let result = match pending.as_mut() {
+                    Some(pending) => match Pin::new(pending).poll(cx) {
+                        Poll::Ready(result) => result,
+                        Poll::Pending => return Poll::Pending,
+                    },
+                    None => return Poll::Ready(None),
+                };
+                
+                *pending = None;
+                Poll::Ready(Some(result.map(|code| Bytes::from(code.to_string()))))
+            },
+        }
+    }
+}
+
+impl Drop for EventStream<'_> {
+    fn drop(&mut self) {
+        if let StreamSource::Chunks { receiver, producer } = &mut self.source {
+            // Make further chunks fail fast, then wait for the plugin call to return
+            receiver.close();
+            
+            if let Some(producer) = producer.take() {
+                if producer.join().is_err() {
+                    error!("Plugin streaming call panicked");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Distributed trace context
+//!
+//! Each event delivered to a plugin runs in its own span of a W3C trace. The
+//! host writes the trace context into the plugin context before invoking the
+//! callback, so a plugin can forward it as a `traceparent` header on outbound
+//! calls, and a caller can pass one in through the event envelope to continue
+//! an existing trace.
+
+use std::collections::hash_map::RandomState;
+use std::fmt::Write;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Version of the `traceparent` format produced by the host
+const TRACEPARENT_VERSION: &str = "00";
+
+/// Trace flag marking a trace as sampled
+pub const TRACE_FLAG_SAMPLED: u8 = 0x01;
+
+/// Decides whether events are sampled, given a plugin ID and event name
+pub type TraceSampler = Box<dyn Fn(&str, &str) -> bool + Send + Sync>;
+
+/// W3C trace context passed to plugins
+///
+/// An all-zero context means no trace is active.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    /// ID of the whole trace
+    pub trace_id: [u8; 16],
+    
+    /// ID of the span the plugin's work is a child of
+    pub parent_span_id: [u8; 8],
+    
+    /// Trace flags, see `TRACE_FLAG_SAMPLED`
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Start a new trace
+    pub fn new_root(sampled: bool) -> Self {
+        let mut trace_id = [0u8; 16];
+        trace_id[..8].copy_from_slice(&random_id());
+        trace_id[8..].copy_from_slice(&random_id());
+        
+        Self {
+            trace_id,
+            parent_span_id: random_id(),
+            flags: if sampled { TRACE_FLAG_SAMPLED } else { 0 },
+        }
+    }
+    
+    /// Start a new span within this trace, with this span as its parent
+    pub fn child(&self, sampled: bool) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            parent_span_id: random_id(),
+            flags: if sampled { self.flags | TRACE_FLAG_SAMPLED } else { self.flags & !TRACE_FLAG_SAMPLED },
+        }
+    }
+    
+    /// Check whether the context identifies a trace and span
+    pub fn is_valid(&self) -> bool {
+        self.trace_id != [0; 16] && self.parent_span_id != [0; 8]
+    }
+    
+    /// Check whether the trace is sampled
+    pub fn is_sampled(&self) -> bool {
+        self.flags & TRACE_FLAG_SAMPLED != 0
+    }
+    
+    /// Format the context as a `traceparent` header value
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "{}-{}-{}-{:02x}",
+            TRACEPARENT_VERSION,
+            hex(&self.trace_id),
+            hex(&self.parent_span_id),
+            self.flags
+        )
+    }
+    
+    /// Parse a `traceparent` header value
+    ///
+    /// Returns `None` for malformed values and for all-zero trace or span IDs.
+    pub fn parse_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_span_id = parts.next()?;
+        let flags = parts.next()?;
+        
+        // Version 00 has exactly four fields; later versions may append more
+        if version.len() != 2 || version == "ff" || (version == TRACEPARENT_VERSION && parts.next().is_some()) {
+            return None;
+        }
+        u8::from_str_radix(version, 16).ok()?;
+        
+        let mut context = Self::default();
+        unhex(trace_id, &mut context.trace_id)?;
+        unhex(parent_span_id, &mut context.parent_span_id)?;
+        let mut flags_byte = [0u8; 1];
+        unhex(flags, &mut flags_byte)?;
+        context.flags = flags_byte[0];
+        
+        context.is_valid().then_some(context)
+    }
+}
+
+/// Generate a nonzero random 8-byte ID
+fn random_id() -> [u8; 8] {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    
+    loop {
+        // Each RandomState is seeded randomly; the counter keeps IDs distinct within a process
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        let id = hasher.finish();
+        if id != 0 {
+            return id.to_be_bytes();
+        }
+    }
+}
+
+/// Encode bytes as lowercase hex
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut text, byte| {
+        let _ = write!(text, "{:02x}", byte);
+        text
+    })
+}
+
+/// Decode lowercase hex into a buffer of exactly matching length
+fn unhex(text: &str, out: &mut [u8]) -> Option<()> {
+    if text.len() != out.len() * 2 || text.bytes().any(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+    
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(text.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(())
+}
@@ -0,0 +1,90 @@
+//! Plugin ABI coverage analysis
+//!
+//! Inspects the PE export and import tables of a plugin DLL to report which
+//! parts of the host ABI the plugin implements and which foreign libraries it
+//! depends on. Context functions such as `send_message` are called through the
+//! `PluginContext` function pointers and never appear in the import table, so
+//! coverage is measured over the exports the host resolves.
+
+use std::fs;
+use std::path::Path;
+use goblin::pe::PE;
+use serde::{Deserialize, Serialize};
+use super::PluginLoadError;
+
+/// Exports the host resolves from a plugin DLL
+pub const HOST_ABI_EXPORTS: &[&str] = &[
+    "plugin_init", "plugin_teardown", "plugin_execute_streaming", "plugin_register_async_handler",
+];
+
+/// Library name prefixes of Windows system and C runtime DLLs
+const SYSTEM_LIBRARY_PREFIXES: &[&str] = &[
+    "api-ms-win-", "ext-ms-win-", "kernel32", "kernelbase", "ntdll", "user32", "gdi32",
+    "advapi32", "bcrypt", "crypt32", "ncrypt", "secur32", "ws2_32", "ole32", "oleaut32",
+    "shell32", "shlwapi", "userenv", "dbghelp", "msvcrt", "msvcp", "vcruntime", "ucrtbase",
+];
+
+/// ABI coverage of a plugin DLL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbiCoverageReport {
+    /// Host ABI exports implemented by the plugin
+    pub used_features: Vec<String>,
+    
+    /// Host ABI exports the plugin does not implement
+    pub unused_features: Vec<String>,
+    
+    /// Imports from non-system libraries, as `library!symbol`
+    pub unknown_imports: Vec<String>,
+    
+    /// Percentage of the host ABI exports implemented by the plugin
+    pub coverage_percent: f32,
+}
+
+/// Analyzes the ABI surface used by plugin DLLs
+pub struct AbiCoverageAnalyzer;
+
+impl AbiCoverageAnalyzer {
+    /// Analyze a plugin DLL on disk
+    pub fn analyze(dll_path: &Path) -> Result<AbiCoverageReport, PluginLoadError> {
+        let bytes = fs::read(dll_path).map_err(|e| {
+            PluginLoadError::DllLoadFailed(format!("Failed to read {}: {}", dll_path.display(), e))
+        })?;
+        
+        Self::analyze_bytes(&bytes)
+    }
+    
+    /// Analyze a plugin DLL image held in memory
+    pub fn analyze_bytes(bytes: &[u8]) -> Result<AbiCoverageReport, PluginLoadError> {
+        let pe = PE::parse(bytes)
+            .map_err(|e| PluginLoadError::DllLoadFailed(format!("Invalid PE image: {}", e)))?;
+        
+        let exported = |feature: &str| pe.exports.iter().any(|export| export.name == Some(feature));
+        let (used_features, unused_features): (Vec<String>, Vec<String>) = HOST_ABI_EXPORTS
+            .iter()
+            .map(|feature| feature.to_string())
+            .partition(|feature| exported(feature));
+        
+        let mut unknown_imports: Vec<String> = pe.imports
+            .iter()
+            .filter(|import| !is_system_library(import.dll))
+            .map(|import| format!("{}!{}", import.dll, import.name))
+            .collect();
+        unknown_imports.sort();
+        unknown_imports.dedup();
+        
+        let coverage_percent = used_features.len() as f32 / HOST_ABI_EXPORTS.len() as f32 * 100.0;
+        
+        Ok(AbiCoverageReport {
+            used_features,
+            unused_features,
+            unknown_imports,
+            coverage_percent,
+        })
+    }
+}
+
+/// Check whether a library is part of Windows or the C runtime
+fn is_system_library(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SYSTEM_LIBRARY_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
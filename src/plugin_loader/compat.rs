@@ -0,0 +1,118 @@
+//! Plugin API compatibility
+//!
+//! A plugin runs on any host providing the same major API version with at
+//! least the minor version it was built against. The report explains which of
+//! these rules a plugin breaks so the UI can tell the user what is needed.
+//! Plugins may also require host features, which are checked when enabling them.
+
+use std::fmt;
+use semver::Version;
+use serde::{Serialize, Deserialize};
+
+use super::{PluginManifest, SUPPORTED_API_VERSION};
+
+/// Cargo features this host was built with
+const HOST_FEATURES: &[&str] = &[
+    #[cfg(feature = "dynamic-loading")]
+    "dynamic-loading",
+];
+
+/// Get the cargo features this host was built with
+pub fn host_features() -> &'static [&'static str] {
+    HOST_FEATURES
+}
+
+/// Get the host features a plugin requires that this host was built without
+pub fn missing_host_features(manifest: &PluginManifest) -> Vec<String> {
+    manifest.required_host_features.iter()
+        .filter(|feature| !HOST_FEATURES.contains(&feature.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// Why a plugin's API version is not supported by the host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompatibilityIssue {
+    /// The plugin's API version is not a valid semantic version
+    InvalidVersion(String),
+    
+    /// The plugin targets a different major API version
+    MajorMismatch,
+    
+    /// The plugin needs API features from a newer minor version
+    MinorTooNew,
+}
+
+/// Comparison of a plugin's required API version with the host's
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompatibilityReport {
+    /// API version the plugin was built against
+    pub required_api_version: String,
+    
+    /// API version provided by the host
+    pub host_api_version: String,
+    
+    /// Reason the plugin is incompatible, if it is
+    pub issue: Option<CompatibilityIssue>,
+}
+
+impl CompatibilityReport {
+    /// Check a required API version against the host's API version
+    pub fn check(required_api_version: &str) -> Self {
+        Self::check_against(required_api_version, SUPPORTED_API_VERSION)
+    }
+    
+    /// Check a required API version against a given host API version
+    pub fn check_against(required_api_version: &str, host_api_version: &str) -> Self {
+        let issue = match (Version::parse(required_api_version), Version::parse(host_api_version)) {
+            (Err(e), _) => Some(CompatibilityIssue::InvalidVersion(e.to_string())),
+            (_, Err(e)) => Some(CompatibilityIssue::InvalidVersion(format!("host: {}", e))),
+            (Ok(required), Ok(host)) => {
+                if required.major != host.major {
+                    Some(CompatibilityIssue::MajorMismatch)
+                } else if required.minor > host.minor {
+                    Some(CompatibilityIssue::MinorTooNew)
+                } else {
+                    None
+                }
+            },
+        };
+        
+        Self {
+            required_api_version: required_api_version.to_owned(),
+            host_api_version: host_api_version.to_owned(),
+            issue,
+        }
+    }
+    
+    /// Check whether the plugin can run on the host
+    pub fn is_compatible(&self) -> bool {
+        self.issue.is_none()
+    }
+    
+    /// Get a user-facing explanation of the incompatibility
+    pub fn reason(&self) -> Option<String> {
+        self.issue.as_ref().map(|_| self.to_string())
+    }
+}
+
+impl fmt::Display for CompatibilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.issue {
+            None => write!(
+                f, "plugin API {} is supported by host API {}", self.required_api_version, self.host_api_version
+            ),
+            Some(CompatibilityIssue::InvalidVersion(e)) => write!(
+                f, "invalid API version '{}': {}", self.required_api_version, e
+            ),
+            Some(CompatibilityIssue::MajorMismatch) => write!(
+                f, "major API version mismatch: plugin requires API {}, host provides API {}",
+                self.required_api_version, self.host_api_version
+            ),
+            Some(CompatibilityIssue::MinorTooNew) => write!(
+                f, "plugin requires newer API minor version {}, host provides API {}; update the host",
+                self.required_api_version, self.host_api_version
+            ),
+        }
+    }
+}
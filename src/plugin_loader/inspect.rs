@@ -0,0 +1,90 @@
+//! Plugin package inspection
+//!
+//! Reads what a store preview needs from a package without extracting it:
+//! the manifest, the archive's central directory and a hash of the file.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+
+use super::{PluginLoadError, PluginLoader, PluginManifest};
+
+/// Name of the archive entry a signed package carries its signature in
+pub const SIGNATURE_FILE_NAME: &str = "plugin.sig";
+
+/// Summary of a plugin package read without extracting it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageInspection {
+    /// Manifest read from the package's `plugin.json`
+    pub manifest: PluginManifest,
+    
+    /// Number of files in the package, not counting directories
+    pub file_count: usize,
+    
+    /// Total compressed size of the files in bytes
+    pub total_compressed_size: u64,
+    
+    /// Total uncompressed size of the files in bytes
+    pub total_uncompressed_size: u64,
+    
+    /// Hex-encoded SHA-256 hash of the package file
+    pub package_hash: String,
+    
+    /// Whether the package contains a signature entry
+    pub has_signature: bool,
+}
+
+impl PluginLoader {
+    /// Inspect a plugin package without extracting it
+    ///
+    /// Only the manifest entry is decompressed; sizes come from the central
+    /// directory. The manifest is parsed but not checked for compatibility.
+    pub fn inspect_package(package_path: &Path) -> Result<PackageInspection, PluginLoadError> {
+        let mut file = File::open(package_path)?;
+        let package_hash = hash_reader(&mut file)?;
+        file.seek(SeekFrom::Start(0))?;
+        
+        let mut archive = ZipArchive::new(file)?;
+        
+        let mut file_count = 0;
+        let mut total_compressed_size = 0;
+        let mut total_uncompressed_size = 0;
+        let mut has_signature = false;
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i)?;
+            if entry.is_dir() {
+                continue;
+            }
+            
+            file_count += 1;
+            total_compressed_size += entry.compressed_size();
+            total_uncompressed_size += entry.size();
+            has_signature |= entry.name() == SIGNATURE_FILE_NAME;
+        }
+        
+        let mut contents = Vec::new();
+        archive.by_name("plugin.json")
+            .map_err(|e| PluginLoadError::ManifestError(format!("Failed to open manifest: {}", e)))?
+            .read_to_end(&mut contents)
+            .map_err(|e| PluginLoadError::ManifestError(format!("Failed to read manifest: {}", e)))?;
+        
+        Ok(PackageInspection {
+            manifest: Self::parse_manifest(&contents)?,
+            file_count,
+            total_compressed_size,
+            total_uncompressed_size,
+            package_hash,
+            has_signature,
+        })
+    }
+}
+
+/// Compute the hex-encoded SHA-256 hash of everything left in a reader
+fn hash_reader(reader: &mut impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
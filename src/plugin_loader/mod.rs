@@ -3,10 +3,13 @@
 //! Responsible for loading plugin packages, extracting them, and validating their manifests.
 //! Handles dynamic loading of plugin DLLs and manages the plugin lifecycle.
 
+use std::ffi::{c_char, c_int};
 use std::path::{Path, PathBuf};
 use std::fs::{self, File};
-use std::io::{self, Read};
+use std::io::{self, Cursor, Read, Seek};
+use std::sync::atomic::{AtomicU64, Ordering};
 use libloading::{Library, Symbol};
+use log::warn;
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
 use zip::ZipArchive;
@@ -15,6 +18,27 @@ use thiserror::Error;
 use crate::permission_system::Permission;
 use crate::plugin_host::PluginContext;
 
+mod abi;
+mod compat;
+mod inspect;
+mod pe;
+mod version_req;
+
+pub use abi::{AbiCoverageAnalyzer, AbiCoverageReport, HOST_ABI_EXPORTS};
+pub use compat::{host_features, missing_host_features, CompatibilityIssue, CompatibilityReport};
+pub use inspect::{PackageInspection, SIGNATURE_FILE_NAME};
+pub use pe::DllArchitecture;
+pub use version_req::ExtendedVersionReq;
+
+/// Plugin API version supported by this host
+pub const SUPPORTED_API_VERSION: &str = "1.0.0";
+
+/// Percentage by which extracted files may differ from the manifest's declared size
+pub const EXTRACTED_SIZE_TOLERANCE_PERCENT: u64 = 1;
+
+/// Smallest difference from the declared size that is always tolerated, in bytes
+pub const EXTRACTED_SIZE_TOLERANCE_BYTES: u64 = 1024;
+
 /// Metadata about a loaded plugin
 #[derive(Debug, Clone)]
 pub struct PluginMetadata {
@@ -48,6 +72,110 @@ pub struct PluginManifest {
     /// Homepage URL of the plugin
     #[serde(default)]
     pub homepage: Option<String>,
+    /// Suggested resource limits for the plugin
+    #[serde(default)]
+    pub resource_limits: Option<ResourceLimits>,
+    /// Capabilities the plugin provides, such as `clipboard-manager`
+    #[serde(default)]
+    pub provides_capabilities: Vec<String>,
+    /// Cargo features the host must be built with, such as `dynamic-loading`
+    #[serde(default)]
+    pub required_host_features: Vec<String>,
+    /// Expected total size of the extracted package files in bytes
+    #[serde(default)]
+    pub total_size_bytes: Option<u64>,
+    /// Expected number of files in the package
+    #[serde(default)]
+    pub file_count: Option<u64>,
+}
+
+/// Resource limits applied to a running plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Maximum CPU usage in percent
+    pub max_cpu_percent: u32,
+    /// Maximum memory usage in megabytes
+    pub max_memory_mb: u64,
+    /// Maximum size of the plugin's data directory in bytes, if limited
+    #[serde(default)]
+    pub data_dir_quota_bytes: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Limits for plugins the host has no reason to trust
+    pub const fn strict() -> Self {
+        Self {
+            max_cpu_percent: 25,
+            max_memory_mb: 256,
+            data_dir_quota_bytes: None,
+        }
+    }
+    
+    /// Limits for plugins from verified vendors with a trusted policy
+    pub const fn trusted() -> Self {
+        Self {
+            max_cpu_percent: 100,
+            max_memory_mb: 1024,
+            data_dir_quota_bytes: None,
+        }
+    }
+    
+    /// Check whether any limit is above the corresponding maximum
+    pub fn exceeds(&self, maxima: &ResourceLimits) -> bool {
+        let quota_exceeds = match (self.data_dir_quota_bytes, maxima.data_dir_quota_bytes) {
+            (Some(quota), Some(max_quota)) => quota > max_quota,
+            _ => false,
+        };
+        
+        self.max_cpu_percent > maxima.max_cpu_percent || self.max_memory_mb > maxima.max_memory_mb || quota_exceeds
+    }
+    
+    /// Lower each limit to at most the corresponding maximum
+    pub fn clamp_to(&self, maxima: &ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            max_cpu_percent: self.max_cpu_percent.min(maxima.max_cpu_percent),
+            max_memory_mb: self.max_memory_mb.min(maxima.max_memory_mb),
+            data_dir_quota_bytes: match (self.data_dir_quota_bytes, maxima.data_dir_quota_bytes) {
+                (Some(quota), Some(max_quota)) => Some(quota.min(max_quota)),
+                (quota, max_quota) => quota.or(max_quota),
+            },
+        }
+    }
+}
+
+impl Default for ResourceLimits {
+    /// Limits for plugins from verified vendors without special trust
+    fn default() -> Self {
+        Self {
+            max_cpu_percent: 50,
+            max_memory_mb: 512,
+            data_dir_quota_bytes: None,
+        }
+    }
+}
+
+/// Named set of resource limits chosen by how far a plugin is trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResourceProfile {
+    /// `ResourceLimits::strict`
+    Strict,
+    
+    /// `ResourceLimits::default`
+    Standard,
+    
+    /// `ResourceLimits::trusted`
+    Trusted,
+}
+
+impl ResourceProfile {
+    /// Get the limits of the profile
+    pub fn limits(&self) -> ResourceLimits {
+        match self {
+            ResourceProfile::Strict => ResourceLimits::strict(),
+            ResourceProfile::Standard => ResourceLimits::default(),
+            ResourceProfile::Trusted => ResourceLimits::trusted(),
+        }
+    }
 }
 
 /// Error type for plugin loading operations
@@ -77,6 +205,10 @@ pub enum PluginLoadError {
     #[error("Plugin initialization failed with code: {0}")]
     InitializationFailed(i32),
     
+    /// The extracted files do not match the size or count declared in the manifest
+    #[error("Package integrity mismatch: {0}")]
+    IntegrityMismatch(String),
+    
     /// ZIP extraction error
     #[error("ZIP extraction error: {0}")]
     ZipError(#[from] zip::result::ZipError),
@@ -84,6 +216,10 @@ pub enum PluginLoadError {
     /// JSON parsing error
     #[error("JSON parsing error: {0}")]
     JsonError(#[from] serde_json::Error),
+    
+    /// Version requirement could not be parsed
+    #[error("Invalid version requirement: {0}")]
+    InvalidVersionReq(String),
 }
 
 /// Function type for plugin initialization
@@ -92,25 +228,90 @@ pub type PluginInitFn = unsafe extern "C" fn(context: *mut PluginContext) -> i32
 /// Function type for plugin teardown
 pub type PluginTeardownFn = unsafe extern "C" fn(context: *mut PluginContext) -> i32;
 
+/// Function type receiving one chunk of a streamed result
+pub type PluginChunkCallback = unsafe extern "C" fn(context: *mut PluginContext, data: *const u8, len: usize);
+
+/// Function type for the optional streaming event handler
+pub type PluginExecuteStreamingFn = unsafe extern "C" fn(
+    context: *mut PluginContext,
+    event_name: *const c_char,
+    event_data: *const c_char,
+    chunk_cb: PluginChunkCallback,
+) -> c_int;
+
+/// Function type for the optional export registering asynchronous event handlers
+pub type PluginRegisterAsyncHandlerFn = unsafe extern "C" fn(context: *mut PluginContext) -> c_int;
+
 /// Represents a loaded plugin DLL
 pub struct LoadedPlugin {
-    /// The library handle
-    library: Library,
+    /// The library handle, kept loaded while the entry points are in use
+    /// (absent for statically linked plugins)
+    _library: Option<Library>,
+    /// Plugin initialization entry point
+    init_fn: PluginInitFn,
+    /// Plugin teardown entry point
+    teardown_fn: PluginTeardownFn,
+    /// Streaming event handler, if the plugin exports one
+    execute_streaming_fn: Option<PluginExecuteStreamingFn>,
+    /// Asynchronous handler registration, if the plugin exports one
+    register_async_handler_fn: Option<PluginRegisterAsyncHandlerFn>,
     /// Plugin metadata
     metadata: PluginMetadata,
 }
 
 impl LoadedPlugin {
-    /// Get the init function from the plugin DLL
-    pub unsafe fn get_init_fn(&self) -> Result<Symbol<PluginInitFn>, PluginLoadError> {
-        self.library.get(b"plugin_init")
-            .map_err(|e| PluginLoadError::MissingExport(format!("plugin_init: {}", e)))
+    /// Create a loaded plugin from entry points linked into the host binary
+    ///
+    /// For the crate's tests and benchmarks, which exercise the host without a
+    /// DLL on disk; not part of the supported API.
+    #[doc(hidden)]
+    pub fn from_static(
+        metadata: PluginMetadata,
+        init_fn: PluginInitFn,
+        teardown_fn: PluginTeardownFn,
+    ) -> Self {
+        Self {
+            _library: None,
+            init_fn,
+            teardown_fn,
+            execute_streaming_fn: None,
+            register_async_handler_fn: None,
+            metadata,
+        }
     }
     
-    /// Get the teardown function from the plugin DLL
-    pub unsafe fn get_teardown_fn(&self) -> Result<Symbol<PluginTeardownFn>, PluginLoadError> {
-        self.library.get(b"plugin_teardown")
-            .map_err(|e| PluginLoadError::MissingExport(format!("plugin_teardown: {}", e)))
+    /// Set the streaming event handler of a statically linked plugin
+    #[doc(hidden)]
+    pub fn with_execute_streaming_fn(mut self, execute_streaming_fn: PluginExecuteStreamingFn) -> Self {
+        self.execute_streaming_fn = Some(execute_streaming_fn);
+        self
+    }
+    
+    /// Set the asynchronous handler registration of a statically linked plugin
+    #[doc(hidden)]
+    pub fn with_register_async_handler_fn(mut self, register_async_handler_fn: PluginRegisterAsyncHandlerFn) -> Self {
+        self.register_async_handler_fn = Some(register_async_handler_fn);
+        self
+    }
+    
+    /// Get the plugin's init function
+    pub fn get_init_fn(&self) -> PluginInitFn {
+        self.init_fn
+    }
+    
+    /// Get the plugin's teardown function
+    pub fn get_teardown_fn(&self) -> PluginTeardownFn {
+        self.teardown_fn
+    }
+    
+    /// Get the streaming event handler, if the plugin exports `plugin_execute_streaming`
+    pub fn get_execute_streaming_fn(&self) -> Option<PluginExecuteStreamingFn> {
+        self.execute_streaming_fn
+    }
+    
+    /// Get the asynchronous handler registration, if the plugin exports `plugin_register_async_handler`
+    pub fn get_register_async_handler_fn(&self) -> Option<PluginRegisterAsyncHandlerFn> {
+        self.register_async_handler_fn
     }
     
     /// Get the plugin metadata
@@ -133,29 +334,71 @@ impl PluginLoader {
     
     /// Load a plugin package from a path
     pub async fn load_plugin_package(&self, package_path: &Path) -> Result<PluginMetadata, PluginLoadError> {
+        self.load_archive(File::open(package_path)?)
+    }
+    
+    /// Load a plugin package held in memory
+    pub fn load_plugin_bytes(&self, package: &[u8]) -> Result<PluginMetadata, PluginLoadError> {
+        self.load_archive(Cursor::new(package))
+    }
+    
+    /// Extract and validate a plugin package
+    ///
+    /// The extracted files are removed again if the package is rejected.
+    fn load_archive<R: Read + Seek>(&self, reader: R) -> Result<PluginMetadata, PluginLoadError> {
         // Extract ZIP package
-        let extract_dir = self.extract_plugin_package(package_path)?;
+        let extract_dir = ExtractDirGuard::create(&self.extract_base_dir)?;
+        self.extract_archive(reader, extract_dir.path())?;
         
         // Read and validate manifest
-        let manifest_path = extract_dir.join("plugin.json");
-        let manifest = self.read_and_validate_manifest(&manifest_path)?;
+        let manifest = self.read_and_validate_manifest(&extract_dir.path().join("plugin.json"))?;
+        
+        // Catch truncated or padded packages
+        verify_extracted_contents(&manifest, extract_dir.path())?;
         
         // Check permissions and compatibility
         self.validate_plugin_compatibility(&manifest)?;
         
         // Create plugin metadata
-        let plugin_metadata = PluginMetadata {
+        let extract_dir = extract_dir.keep();
+        Ok(PluginMetadata {
             manifest,
-            install_path: extract_dir.clone(),
             dll_path: extract_dir.join("plugin.dll"),
+            install_path: extract_dir,
             installed_at: Utc::now(),
-        };
+        })
+    }
+    
+    /// Load an unpacked plugin directory in place, without extracting it
+    pub fn load_plugin_directory(&self, plugin_dir: &Path) -> Result<PluginMetadata, PluginLoadError> {
+        // Read and validate manifest
+        let manifest = self.read_and_validate_manifest(&plugin_dir.join("plugin.json"))?;
+        
+        // Check permissions and compatibility
+        self.validate_plugin_compatibility(&manifest)?;
         
-        Ok(plugin_metadata)
+        Ok(PluginMetadata {
+            manifest,
+            install_path: plugin_dir.to_path_buf(),
+            dll_path: plugin_dir.join("plugin.dll"),
+            installed_at: Utc::now(),
+        })
     }
     
     /// Load a plugin DLL
     pub fn load_plugin_dll(&self, metadata: &PluginMetadata) -> Result<LoadedPlugin, PluginLoadError> {
+        // Reject DLLs built for another architecture before the loader sees them
+        let plugin_arch = DllArchitecture::of_file(&metadata.dll_path).map_err(|e| {
+            PluginLoadError::DllLoadFailed(format!("Failed to read DLL: {}", e))
+        })?;
+        if let (Some(plugin_arch), Some(host_arch)) = (plugin_arch, DllArchitecture::host()) {
+            if plugin_arch != host_arch {
+                return Err(PluginLoadError::Incompatible(format!(
+                    "architecture mismatch: plugin is {}, host is {}", plugin_arch, host_arch
+                )));
+            }
+        }
+        
         // Load the DLL
         let library = unsafe {
             Library::new(&metadata.dll_path).map_err(|e| {
@@ -163,33 +406,39 @@ impl PluginLoader {
             })?
         };
         
-        // Check required exports
-        unsafe {
-            let _: Symbol<PluginInitFn> = library.get(b"plugin_init")
+        // Resolve required exports
+        let (init_fn, teardown_fn) = unsafe {
+            let init_fn: Symbol<PluginInitFn> = library.get(b"plugin_init")
                 .map_err(|e| PluginLoadError::MissingExport(format!("plugin_init: {}", e)))?;
             
-            let _: Symbol<PluginTeardownFn> = library.get(b"plugin_teardown")
+            let teardown_fn: Symbol<PluginTeardownFn> = library.get(b"plugin_teardown")
                 .map_err(|e| PluginLoadError::MissingExport(format!("plugin_teardown: {}", e)))?;
-        }
+            
+            (*init_fn, *teardown_fn)
+        };
+        
+        // Resolve optional exports
+        let execute_streaming_fn = unsafe {
+            library.get::<PluginExecuteStreamingFn>(b"plugin_execute_streaming").ok().map(|f| *f)
+        };
+        let register_async_handler_fn = unsafe {
+            library.get::<PluginRegisterAsyncHandlerFn>(b"plugin_register_async_handler").ok().map(|f| *f)
+        };
         
         Ok(LoadedPlugin {
-            library,
+            _library: Some(library),
+            init_fn,
+            teardown_fn,
+            execute_streaming_fn,
+            register_async_handler_fn,
             metadata: metadata.clone(),
         })
     }
     
-    /// Extract a plugin package to a temporary directory
-    fn extract_plugin_package(&self, package_path: &Path) -> Result<PathBuf, PluginLoadError> {
-        // Create a unique directory for extraction
-        let extract_dir = self.extract_base_dir.join(format!(
-            "plugin_{}", 
-            chrono::Utc::now().timestamp_millis()
-        ));
-        fs::create_dir_all(&extract_dir)?;
-        
-        // Open the ZIP file
-        let file = File::open(package_path)?;
-        let mut archive = ZipArchive::new(file)?;
+    /// Extract a ZIP archive into a directory
+    fn extract_archive<R: Read + Seek>(&self, reader: R, extract_dir: &Path) -> Result<(), PluginLoadError> {
+        // Open the ZIP archive
+        let mut archive = ZipArchive::new(reader)?;
         
         // Extract all files
         for i in 0..archive.len() {
@@ -212,7 +461,7 @@ impl PluginLoader {
             }
         }
         
-        Ok(extract_dir)
+        Ok(())
     }
     
     /// Read and validate the plugin manifest
@@ -221,12 +470,22 @@ impl PluginLoader {
         let mut file = File::open(manifest_path)
             .map_err(|e| PluginLoadError::ManifestError(format!("Failed to open manifest: {}", e)))?;
         
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .map_err(|e| PluginLoadError::ManifestError(format!("Failed to read manifest: {}", e)))?;
+        
+        Self::parse_manifest(&contents)
+    }
+    
+    /// Parse and validate the contents of a plugin manifest
+    ///
+    /// Never panics on malformed input; every problem is reported as an error.
+    pub fn parse_manifest(contents: &[u8]) -> Result<PluginManifest, PluginLoadError> {
+        let contents = std::str::from_utf8(contents)
             .map_err(|e| PluginLoadError::ManifestError(format!("Failed to read manifest: {}", e)))?;
         
         // Parse the manifest
-        let manifest: PluginManifest = serde_json::from_str(&contents)?;
+        let manifest: PluginManifest = serde_json::from_str(contents)?;
         
         // Basic validation
         if manifest.name.is_empty() {
@@ -248,16 +507,102 @@ impl PluginLoader {
         Ok(manifest)
     }
     
+    /// Compare a plugin's required API version with the host's
+    pub fn compatibility_report(manifest: &PluginManifest) -> CompatibilityReport {
+        CompatibilityReport::check(&manifest.api_version)
+    }
+    
     /// Validate plugin compatibility
     fn validate_plugin_compatibility(&self, manifest: &PluginManifest) -> Result<(), PluginLoadError> {
         // Check API version compatibility
-        // For now, we only support API version 1.0.0
-        if manifest.api_version != "1.0.0" {
-            return Err(PluginLoadError::Incompatible(
-                format!("Unsupported API version: {}", manifest.api_version)
-            ));
+        match Self::compatibility_report(manifest).reason() {
+            Some(reason) => Err(PluginLoadError::Incompatible(reason)),
+            None => Ok(()),
         }
+    }
+}
+
+/// Check the extracted files against the size and count declared in the manifest
+///
+/// The size may differ by `EXTRACTED_SIZE_TOLERANCE_PERCENT`, with a floor of
+/// `EXTRACTED_SIZE_TOLERANCE_BYTES`, since the declaration is part of the
+/// manifest it measures. The file count must match exactly.
+fn verify_extracted_contents(manifest: &PluginManifest, extract_dir: &Path) -> Result<(), PluginLoadError> {
+    if manifest.total_size_bytes.is_none() && manifest.file_count.is_none() {
+        return Ok(());
+    }
+    
+    let mut total_size_bytes = 0;
+    let mut file_count = 0;
+    for entry in walkdir::WalkDir::new(extract_dir) {
+        let entry = entry.map_err(io::Error::from)?;
+        if entry.file_type().is_file() {
+            total_size_bytes += entry.metadata().map_err(io::Error::from)?.len();
+            file_count += 1;
+        }
+    }
+    
+    if let Some(expected) = manifest.file_count {
+        if file_count != expected {
+            return Err(PluginLoadError::IntegrityMismatch(format!(
+                "manifest declares {} files, package contains {}", expected, file_count
+            )));
+        }
+    }
+    
+    if let Some(expected) = manifest.total_size_bytes {
+        let tolerance = (expected * EXTRACTED_SIZE_TOLERANCE_PERCENT / 100).max(EXTRACTED_SIZE_TOLERANCE_BYTES);
+        if total_size_bytes.abs_diff(expected) > tolerance {
+            return Err(PluginLoadError::IntegrityMismatch(format!(
+                "manifest declares {} bytes, package contains {}", expected, total_size_bytes
+            )));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Extraction directory removed when dropped unless kept
+///
+/// Ensures a package that fails partway through extraction or validation
+/// leaves nothing behind in the extract base directory.
+struct ExtractDirGuard {
+    /// Directory being extracted into; `None` once kept
+    path: Option<PathBuf>,
+}
+
+impl ExtractDirGuard {
+    /// Create a uniquely named directory below the extract base directory
+    fn create(extract_base_dir: &Path) -> Result<Self, PluginLoadError> {
+        static EXTRACT_COUNTER: AtomicU64 = AtomicU64::new(0);
         
-        Ok(())
+        let path = extract_base_dir.join(format!(
+            "plugin_{}_{}",
+            Utc::now().timestamp_millis(),
+            EXTRACT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path)?;
+        
+        Ok(Self { path: Some(path) })
+    }
+    
+    /// Get the directory path
+    fn path(&self) -> &Path {
+        self.path.as_deref().expect("extract directory already kept")
+    }
+    
+    /// Keep the directory, returning its path
+    fn keep(mut self) -> PathBuf {
+        self.path.take().expect("extract directory already kept")
+    }
+}
+
+impl Drop for ExtractDirGuard {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            if let Err(e) = fs::remove_dir_all(&path) {
+                warn!("Failed to remove partially extracted package {}: {}", path.display(), e);
+            }
+        }
     }
 }
@@ -0,0 +1,96 @@
+//! PE image inspection
+//!
+//! Reads the machine type from a DLL's PE header so plugins built for another
+//! architecture can be rejected with a clear error before they are loaded.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Offset of the PE header pointer in the DOS header
+const PE_POINTER_OFFSET: u64 = 0x3C;
+
+/// Processor architecture a DLL is built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DllArchitecture {
+    /// 32-bit x86
+    X86,
+    
+    /// 64-bit x86
+    X64,
+    
+    /// 32-bit ARM
+    Arm,
+    
+    /// 64-bit ARM
+    Arm64,
+    
+    /// Any other machine type
+    Other(u16),
+}
+
+impl DllArchitecture {
+    /// Get the architecture for a PE machine type
+    pub fn from_machine(machine: u16) -> Self {
+        match machine {
+            0x014C => DllArchitecture::X86,
+            0x8664 => DllArchitecture::X64,
+            0x01C0 | 0x01C4 => DllArchitecture::Arm,
+            0xAA64 => DllArchitecture::Arm64,
+            other => DllArchitecture::Other(other),
+        }
+    }
+    
+    /// Get the architecture of the host process, if it can load DLLs
+    pub fn host() -> Option<Self> {
+        if cfg!(target_arch = "x86_64") {
+            Some(DllArchitecture::X64)
+        } else if cfg!(target_arch = "x86") {
+            Some(DllArchitecture::X86)
+        } else if cfg!(target_arch = "aarch64") {
+            Some(DllArchitecture::Arm64)
+        } else if cfg!(target_arch = "arm") {
+            Some(DllArchitecture::Arm)
+        } else {
+            None
+        }
+    }
+    
+    /// Read the architecture of a DLL, or `None` if the file is not a PE image
+    pub fn of_file(path: &Path) -> io::Result<Option<Self>> {
+        let mut file = File::open(path)?;
+        
+        let mut dos_magic = [0u8; 2];
+        if file.read_exact(&mut dos_magic).is_err() || &dos_magic != b"MZ" {
+            return Ok(None);
+        }
+        
+        let mut pe_offset = [0u8; 4];
+        file.seek(SeekFrom::Start(PE_POINTER_OFFSET))?;
+        if file.read_exact(&mut pe_offset).is_err() {
+            return Ok(None);
+        }
+        
+        // The PE signature is followed directly by the machine type
+        let mut header = [0u8; 6];
+        file.seek(SeekFrom::Start(u32::from_le_bytes(pe_offset) as u64))?;
+        if file.read_exact(&mut header).is_err() || &header[..4] != b"PE\0\0" {
+            return Ok(None);
+        }
+        
+        Ok(Some(Self::from_machine(u16::from_le_bytes([header[4], header[5]]))))
+    }
+}
+
+impl fmt::Display for DllArchitecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DllArchitecture::X86 => write!(f, "x86"),
+            DllArchitecture::X64 => write!(f, "x64"),
+            DllArchitecture::Arm => write!(f, "arm"),
+            DllArchitecture::Arm64 => write!(f, "arm64"),
+            DllArchitecture::Other(machine) => write!(f, "unknown machine type {:#06x}", machine),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Extended version requirements
+//!
+//! Semver requirements only express the intersection of their comparators. An
+//! extended requirement joins several of them with `||` and is satisfied when
+//! any one of them is.
+
+use std::fmt;
+use std::str::FromStr;
+use semver::{Version, VersionReq};
+
+use super::PluginLoadError;
+
+/// Separator between alternative requirement clauses
+const OR_SEPARATOR: &str = "||";
+
+/// A version requirement made of `||`-separated semver requirements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedVersionReq {
+    /// Alternative requirements, any of which may match
+    clauses: Vec<VersionReq>,
+}
+
+impl ExtendedVersionReq {
+    /// Parse a requirement such as `>=1.0, <2.0 || >=3.0`
+    pub fn parse(text: &str) -> Result<Self, PluginLoadError> {
+        let mut clauses = Vec::new();
+        let mut rest = text;
+        
+        loop {
+            let (clause, next) = match rest.find(OR_SEPARATOR) {
+                Some(index) => (&rest[..index], Some(&rest[index + OR_SEPARATOR.len()..])),
+                None => (rest, None),
+            };
+            
+            let clause = clause.trim();
+            if clause.is_empty() {
+                return Err(PluginLoadError::InvalidVersionReq(
+                    format!("'{}': empty requirement clause", text)
+                ));
+            }
+            
+            let req = VersionReq::parse(clause).map_err(|e| {
+                PluginLoadError::InvalidVersionReq(format!("'{}': {}", text, e))
+            })?;
+            clauses.push(req);
+            
+            match next {
+                Some(next) => rest = next,
+                None => break,
+            }
+        }
+        
+        Ok(Self { clauses })
+    }
+    
+    /// Check whether a version satisfies any of the clauses
+    pub fn matches(&self, version: &Version) -> bool {
+        self.clauses.iter().any(|req| req.matches(version))
+    }
+    
+    /// Get the alternative requirements
+    pub fn clauses(&self) -> &[VersionReq] {
+        &self.clauses
+    }
+}
+
+impl FromStr for ExtendedVersionReq {
+    type Err = PluginLoadError;
+    
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for ExtendedVersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, req) in self.clauses.iter().enumerate() {
+            if index > 0 {
+                write!(f, " {} ", OR_SEPARATOR)?;
+            }
+            write!(f, "{}", req)?;
+        }
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! Capability conflict detection
+//!
+//! Plugins declare the capabilities they provide in their manifest. When two
+//! plugins provide the same capability they may fight over shared state, such
+//! as the clipboard, so the manager reports them together with the configured
+//! way of resolving the conflict.
+
+use std::collections::{BTreeMap, HashMap};
+use serde::{Serialize, Deserialize};
+
+use super::PluginInfo;
+
+/// How a capability conflict should be resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ConflictResolution {
+    /// Ask the user to choose between the plugins
+    #[default]
+    Manual,
+    
+    /// Keep the plugin that was installed first
+    PreferFirst,
+    
+    /// Keep the plugin with the highest version
+    PreferHigherVersion,
+    
+    /// Disable all conflicting plugins
+    Disable,
+}
+
+/// Several plugins providing the same capability
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityConflict {
+    /// The contested capability
+    pub capability_id: String,
+    
+    /// IDs of the plugins providing it, in installation order
+    pub conflicting_plugins: Vec<String>,
+    
+    /// Configured resolution for the capability
+    pub resolution: ConflictResolution,
+}
+
+/// Finds capabilities provided by more than one plugin
+#[derive(Debug, Clone, Default)]
+pub struct CapabilityConflictDetector {
+    /// Resolution per capability; unlisted capabilities are resolved manually
+    policies: HashMap<String, ConflictResolution>,
+}
+
+impl CapabilityConflictDetector {
+    /// Create a detector resolving every conflict manually
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set how conflicts over a capability should be resolved
+    pub fn set_policy(&mut self, capability_id: &str, resolution: ConflictResolution) {
+        self.policies.insert(capability_id.to_owned(), resolution);
+    }
+    
+    /// Get how conflicts over a capability should be resolved
+    pub fn policy(&self, capability_id: &str) -> ConflictResolution {
+        self.policies.get(capability_id).copied().unwrap_or_default()
+    }
+    
+    /// Find the capabilities provided by more than one of the given plugins
+    ///
+    /// Conflicts are ordered by capability ID.
+    pub fn detect(&self, plugins: &[PluginInfo]) -> Vec<CapabilityConflict> {
+        let mut plugins: Vec<&PluginInfo> = plugins.iter().collect();
+        plugins.sort_by(|a, b| a.installed_at.cmp(&b.installed_at).then_with(|| a.id.cmp(&b.id)));
+        
+        let mut providers: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for plugin in plugins {
+            for capability in &plugin.provides_capabilities {
+                let ids = providers.entry(capability.as_str()).or_default();
+                if !ids.contains(&plugin.id) {
+                    ids.push(plugin.id.clone());
+                }
+            }
+        }
+        
+        providers.into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|(capability_id, conflicting_plugins)| CapabilityConflict {
+                capability_id: capability_id.to_owned(),
+                conflicting_plugins,
+                resolution: self.policy(capability_id),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,134 @@
+//! Diagnostics bundles
+//!
+//! Collects a snapshot of the plugin system into a zip archive that users can
+//! attach to bug reports. Secrets and file paths outside the application
+//! directory are redacted before anything is written.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Replacement for redacted secret values
+pub const REDACTED: &str = "<redacted>";
+
+/// Replacement for redacted file paths
+pub const REDACTED_PATH: &str = "<redacted path>";
+
+/// Object keys whose values are always redacted
+const SECRET_KEYS: &[&str] = &["token", "secret", "password", "api_key", "apikey", "authorization", "credential"];
+
+/// Removes secrets and private file paths from diagnostics
+pub struct DiagnosticsRedactor {
+    /// Directory whose paths may appear in diagnostics
+    app_dir: PathBuf,
+}
+
+impl DiagnosticsRedactor {
+    /// Create a redactor keeping paths inside the application directory
+    pub fn new(app_dir: impl Into<PathBuf>) -> Self {
+        Self { app_dir: app_dir.into() }
+    }
+    
+    /// Redact a JSON value in place
+    pub fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if is_secret_key(key) {
+                        *value = Value::String(REDACTED.to_owned());
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            },
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            Value::String(text) => {
+                if text.starts_with("Bearer ") {
+                    *text = REDACTED.to_owned();
+                } else if is_absolute_path(text) && !self.is_app_path(text) {
+                    *text = REDACTED_PATH.to_owned();
+                }
+            },
+            _ => {},
+        }
+    }
+    
+    /// Check whether a path lies inside the application directory
+    fn is_app_path(&self, path: &str) -> bool {
+        let normalize = |path: &str| path.replace('\\', "/").trim_end_matches('/').to_lowercase();
+        let app_dir = normalize(&self.app_dir.to_string_lossy());
+        let path = normalize(path);
+        
+        path == app_dir || path.starts_with(&format!("{}/", app_dir))
+    }
+}
+
+/// Check whether an object key names a secret
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_KEYS.iter().any(|secret| key.contains(secret))
+}
+
+/// Check whether text looks like an absolute Windows or Unix path
+fn is_absolute_path(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let drive_path = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+    
+    drive_path || text.starts_with("\\\\") || (text.starts_with('/') && !text.starts_with("//"))
+}
+
+/// A set of named JSON sections written to a zip archive
+#[derive(Default)]
+pub struct DiagnosticsBundle {
+    /// Archive entry names and their contents
+    sections: Vec<(String, Value)>,
+}
+
+impl DiagnosticsBundle {
+    /// Create an empty bundle
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a section stored under the given archive entry name
+    pub fn add_section(&mut self, name: impl Into<String>, contents: Value) {
+        self.sections.push((name.into(), contents));
+    }
+    
+    /// Get the archive entry names of the sections
+    pub fn section_names(&self) -> Vec<&str> {
+        self.sections.iter().map(|(name, _)| name.as_str()).collect()
+    }
+    
+    /// Redact every section
+    pub fn redact(&mut self, redactor: &DiagnosticsRedactor) {
+        for (_, contents) in &mut self.sections {
+            redactor.redact(contents);
+        }
+    }
+    
+    /// Write the bundle as a zip archive
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        let mut zip = ZipWriter::new(File::create(path)?);
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        
+        for (name, contents) in &self.sections {
+            zip.start_file(name.as_str(), options)?;
+            serde_json::to_writer_pretty(&mut zip, contents)?;
+            zip.write_all(b"\n")?;
+        }
+        
+        zip.finish()?;
+        Ok(())
+    }
+}
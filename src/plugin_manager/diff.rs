@@ -0,0 +1,70 @@
+//! Manifest diffs
+//!
+//! Summarizes what changes between two versions of a plugin manifest, so that
+//! an update can be reviewed before it is applied.
+
+use serde::{Serialize, Deserialize};
+
+use crate::permission_system::Permission;
+use crate::plugin_loader::PluginManifest;
+
+/// Changes between two versions of a plugin manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestDiff {
+    /// Permissions requested only by the new manifest
+    pub added_permissions: Vec<Permission>,
+    
+    /// Permissions requested only by the old manifest
+    pub removed_permissions: Vec<Permission>,
+    
+    /// Old and new version, if the version changed
+    pub changed_version: Option<(String, String)>,
+    
+    /// Old and new description, if the description changed
+    pub changed_description: Option<(String, String)>,
+    
+    /// Old and new API version, if the API version changed
+    pub api_version_changed: Option<(String, String)>,
+}
+
+impl ManifestDiff {
+    /// Compute the changes from an old manifest to a new one
+    pub fn compute(old: &PluginManifest, new: &PluginManifest) -> Self {
+        Self {
+            added_permissions: new.permissions.iter()
+                .filter(|p| !old.permissions.contains(p))
+                .cloned()
+                .collect(),
+            removed_permissions: old.permissions.iter()
+                .filter(|p| !new.permissions.contains(p))
+                .cloned()
+                .collect(),
+            changed_version: changed(&old.version, &new.version),
+            changed_description: changed(&old.description, &new.description),
+            api_version_changed: changed(&old.api_version, &new.api_version),
+        }
+    }
+    
+    /// Check whether the manifests are identical in every compared field
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+    
+    /// Check whether the update gains system access or file system write access
+    pub fn has_security_implications(&self) -> bool {
+        self.added_permissions.iter().any(|p| match p {
+            Permission::System(_) => true,
+            Permission::FileSystem(fs_perm) => fs_perm.write,
+            _ => false,
+        })
+    }
+}
+
+/// Pair an old and new value if they differ
+fn changed(old: &str, new: &str) -> Option<(String, String)> {
+    if old == new {
+        None
+    } else {
+        Some((old.to_owned(), new.to_owned()))
+    }
+}
@@ -0,0 +1,229 @@
+//! Bounded event queue
+//!
+//! Buffers plugin system events between the plugin manager and a slow
+//! consumer such as the frontend. The queue never grows beyond its capacity:
+//! when it is full the oldest event is dropped, and events with a coalescing
+//! policy replace their queued predecessor instead of piling up.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use log::error;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use super::events::PluginEventEmitter;
+use super::lock;
+
+/// Default number of events buffered before events are dropped
+pub const DEFAULT_EVENT_QUEUE_CAPACITY: usize = 256;
+
+/// How an event is queued when it competes with other events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OverflowPolicy {
+    /// Queue every event, dropping the oldest queued event when full
+    #[default]
+    DropOldest,
+    
+    /// Replace a queued event with the same name and plugin ID
+    Coalesce,
+}
+
+/// Event queue counters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventQueueStats {
+    /// Maximum number of queued events
+    pub capacity: usize,
+    
+    /// Number of events currently queued
+    pub queued: usize,
+    
+    /// Number of events dropped because the queue was full
+    pub dropped: u64,
+    
+    /// Number of events replaced by a newer event
+    pub coalesced: u64,
+    
+    /// Number of dropped events per event name
+    pub dropped_by_event: BTreeMap<String, u64>,
+}
+
+/// An event waiting to be delivered
+struct QueuedEvent {
+    /// Event name
+    name: String,
+    /// ID of the plugin the event is about, used for coalescing
+    plugin_id: Option<String>,
+    /// Event payload
+    payload: Value,
+}
+
+/// Mutable queue state
+#[derive(Default)]
+struct QueueState {
+    /// Events in delivery order
+    events: VecDeque<QueuedEvent>,
+    /// Queueing policy per event name
+    policies: HashMap<String, OverflowPolicy>,
+    /// Running counters
+    stats: EventQueueStats,
+    /// Whether the queue accepts no more events
+    closed: bool,
+}
+
+/// Fixed-capacity queue of events awaiting delivery
+pub struct BoundedEventQueue {
+    /// Queued events and counters
+    state: Mutex<QueueState>,
+    /// Signalled when an event is queued or the queue is closed
+    available: Condvar,
+}
+
+impl BoundedEventQueue {
+    /// Create a queue holding at most `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        let mut state = QueueState::default();
+        state.stats.capacity = capacity.max(1);
+        
+        Self {
+            state: Mutex::new(state),
+            available: Condvar::new(),
+        }
+    }
+    
+    /// Set the queueing policy of an event
+    pub fn set_policy(&self, event: &str, policy: OverflowPolicy) {
+        lock(&self.state).policies.insert(event.to_owned(), policy);
+    }
+    
+    /// Queue an event
+    pub fn push(&self, event: &str, payload: Value) {
+        let mut state = lock(&self.state);
+        if state.closed {
+            return;
+        }
+        
+        let plugin_id = payload.get("plugin_id").and_then(Value::as_str).map(str::to_owned);
+        
+        if state.policies.get(event).copied().unwrap_or_default() == OverflowPolicy::Coalesce {
+            let queued = state.events.iter_mut()
+                .find(|queued| queued.name == event && queued.plugin_id == plugin_id);
+            if let Some(queued) = queued {
+                queued.payload = payload;
+                state.stats.coalesced += 1;
+                return;
+            }
+        }
+        
+        if state.events.len() >= state.stats.capacity {
+            if let Some(dropped) = state.events.pop_front() {
+                state.stats.dropped += 1;
+                *state.stats.dropped_by_event.entry(dropped.name).or_default() += 1;
+            }
+        }
+        
+        state.events.push_back(QueuedEvent {
+            name: event.to_owned(),
+            plugin_id,
+            payload,
+        });
+        self.available.notify_one();
+    }
+    
+    /// Wait for the next event, or `None` once the queue is closed and empty
+    fn pop(&self) -> Option<(String, Value)> {
+        let mut state = lock(&self.state);
+        loop {
+            if let Some(event) = state.events.pop_front() {
+                return Some((event.name, event.payload));
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.available.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+    
+    /// Stop accepting events; queued events are still delivered
+    fn close(&self) {
+        lock(&self.state).closed = true;
+        self.available.notify_all();
+    }
+    
+    /// Get the number of queued events
+    pub fn len(&self) -> usize {
+        lock(&self.state).events.len()
+    }
+    
+    /// Check whether no events are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Get the queue counters
+    pub fn stats(&self) -> EventQueueStats {
+        let state = lock(&self.state);
+        EventQueueStats {
+            queued: state.events.len(),
+            ..state.stats.clone()
+        }
+    }
+}
+
+/// Emitter delivering events to another emitter through a bounded queue
+///
+/// Events are delivered in order on a dedicated thread, so a slow emitter
+/// never blocks the plugin manager.
+pub struct QueuedEventEmitter {
+    /// Queue shared with the delivery thread
+    queue: Arc<BoundedEventQueue>,
+    /// Delivery thread handle
+    handle: Option<JoinHandle<()>>,
+}
+
+impl QueuedEventEmitter {
+    /// Create an emitter queueing at most `capacity` events for `emitter`
+    pub fn new<E: PluginEventEmitter + 'static>(emitter: E, capacity: usize) -> Self {
+        let queue = Arc::new(BoundedEventQueue::new(capacity));
+        let delivery_queue = queue.clone();
+        
+        let handle = thread::Builder::new()
+            .name("plugin-event-queue".to_owned())
+            .spawn(move || {
+                while let Some((event, payload)) = delivery_queue.pop() {
+                    emitter.emit(&event, payload);
+                }
+            })
+            .map_err(|e| error!("Failed to spawn event queue thread: {}", e))
+            .ok();
+        
+        Self { queue, handle }
+    }
+    
+    /// Set the queueing policy of an event
+    pub fn with_policy(self, event: &str, policy: OverflowPolicy) -> Self {
+        self.queue.set_policy(event, policy);
+        self
+    }
+    
+    /// Get the queue
+    pub fn queue(&self) -> Arc<BoundedEventQueue> {
+        self.queue.clone()
+    }
+}
+
+impl PluginEventEmitter for QueuedEventEmitter {
+    fn emit(&self, event: &str, payload: Value) {
+        self.queue.push(event, payload);
+    }
+}
+
+impl Drop for QueuedEventEmitter {
+    fn drop(&mut self) {
+        // Deliver what is queued before the emitter goes away
+        self.queue.close();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Plugin system event emission
+//!
+//! Decouples the plugin manager from the UI layer: the manager publishes events
+//! through a `PluginEventEmitter`, which the Tauri integration forwards to the frontend.
+
+use std::sync::{Arc, Mutex};
+use log::warn;
+use serde::Serialize;
+
+use super::lock;
+
+/// Receiver for plugin system events destined for the UI
+pub trait PluginEventEmitter: Send + Sync {
+    /// Emit an event with a JSON payload
+    fn emit(&self, event: &str, payload: serde_json::Value);
+}
+
+/// Shared, replaceable handle to the configured event emitter
+#[derive(Clone, Default)]
+pub(crate) struct EventSink {
+    /// Emitter receiving events, if one is configured
+    emitter: Arc<Mutex<Option<Arc<dyn PluginEventEmitter>>>>,
+}
+
+impl EventSink {
+    /// Replace the configured emitter
+    pub(crate) fn set_emitter(&self, emitter: Arc<dyn PluginEventEmitter>) {
+        *lock(&self.emitter) = Some(emitter);
+    }
+    
+    /// Emit an event, dropping it if no emitter is configured
+    pub(crate) fn emit<T: Serialize>(&self, event: &str, payload: &T) {
+        let emitter = lock(&self.emitter).clone();
+        
+        if let Some(emitter) = emitter {
+            match serde_json::to_value(payload) {
+                Ok(value) => emitter.emit(event, value),
+                Err(e) => warn!("Failed to serialize '{}' event: {}", event, e),
+            }
+        }
+    }
+}
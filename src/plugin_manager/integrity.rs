@@ -0,0 +1,185 @@
+//! Plugin package integrity monitoring
+//!
+//! Records SHA-256 hashes of every installed plugin file and re-hashes them on
+//! demand to detect files that were modified, removed, or added after installation.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use log::warn;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+use super::{lock, PluginError};
+
+/// Result of re-verifying a plugin's installed files
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// Whether all files match their recorded hashes
+    pub ok: bool,
+    
+    /// Files whose contents changed
+    pub modified: Vec<String>,
+    
+    /// Files that were removed
+    pub missing: Vec<String>,
+    
+    /// Files that were not part of the installation
+    pub added: Vec<String>,
+}
+
+/// Recorded hashes for one plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IntegrityRecord {
+    /// Installation path the hashes were taken from
+    install_path: PathBuf,
+    
+    /// SHA-256 hash of each file, keyed by path relative to the installation path
+    files: BTreeMap<String, String>,
+}
+
+/// Tracks file hashes of installed plugins
+pub struct IntegrityMonitor {
+    /// Path of the persisted hash records
+    store_path: PathBuf,
+    
+    /// Recorded hashes for each plugin
+    records: Mutex<HashMap<String, IntegrityRecord>>,
+}
+
+impl IntegrityMonitor {
+    /// Load the integrity monitor from its records file, if present
+    pub fn load(store_path: PathBuf) -> Self {
+        let records = match fs::read_to_string(&store_path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Failed to parse integrity records: {}", e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        
+        Self {
+            store_path,
+            records: Mutex::new(records),
+        }
+    }
+    
+    /// Record the current file hashes of a plugin installation
+    pub fn record_plugin(&self, plugin_id: &str, install_path: &Path) -> Result<(), PluginError> {
+        let files = hash_directory(install_path)?;
+        
+        lock(&self.records).insert(plugin_id.to_owned(), IntegrityRecord {
+            install_path: install_path.to_path_buf(),
+            files,
+        });
+        
+        self.save()
+    }
+    
+    /// Forget the recorded hashes of a plugin
+    pub fn remove_plugin(&self, plugin_id: &str) -> Result<(), PluginError> {
+        lock(&self.records).remove(plugin_id);
+        self.save()
+    }
+    
+    /// Re-hash a plugin's files and compare them with the recorded hashes
+    pub fn verify_plugin(&self, plugin_id: &str) -> Result<IntegrityReport, PluginError> {
+        let record = lock(&self.records).get(plugin_id).cloned().ok_or_else(|| {
+            PluginError::NotFound(format!("No integrity record for plugin: {}", plugin_id))
+        })?;
+        
+        let current = if record.install_path.exists() {
+            hash_directory(&record.install_path)?
+        } else {
+            BTreeMap::new()
+        };
+        
+        let mut report = IntegrityReport {
+            ok: true,
+            modified: Vec::new(),
+            missing: Vec::new(),
+            added: Vec::new(),
+        };
+        
+        for (path, hash) in &record.files {
+            match current.get(path) {
+                Some(current_hash) if current_hash != hash => report.modified.push(path.clone()),
+                Some(_) => {},
+                None => report.missing.push(path.clone()),
+            }
+        }
+        
+        report.added = current.keys()
+            .filter(|path| !record.files.contains_key(*path))
+            .cloned()
+            .collect();
+        
+        report.ok = report.modified.is_empty() && report.missing.is_empty() && report.added.is_empty();
+        
+        Ok(report)
+    }
+    
+    /// Persist the recorded hashes
+    fn save(&self) -> Result<(), PluginError> {
+        let contents = {
+            let records = lock(&self.records);
+            serde_json::to_string_pretty(&*records)?
+        };
+        
+        if let Some(parent) = self.store_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        fs::write(&self.store_path, contents)?;
+        
+        Ok(())
+    }
+}
+
+/// Hash every file below a directory, keyed by relative path
+fn hash_directory(root: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut hashes = BTreeMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            
+            let relative = path.strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+            
+            hashes.insert(relative, hash_file(&path)?);
+        }
+    }
+    
+    Ok(hashes)
+}
+
+/// Compute the hex-encoded SHA-256 hash of a file
+fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    
+    Ok(format!("{:x}", hasher.finalize()))
+}
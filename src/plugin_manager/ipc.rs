@@ -0,0 +1,328 @@
+//! Plugin manager IPC
+//!
+//! Exposes a subset of `PluginManager` operations as JSON-RPC 2.0 methods over a
+//! Windows named pipe, so scripts and the `tauri-plugin-cli` tool can manage the
+//! plugins of a running application. Requests and responses are newline-delimited.
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+#[cfg(windows)]
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{PluginError, PluginManager, PluginSource};
+
+/// Default name of the plugin manager pipe
+pub const DEFAULT_PIPE_NAME: &str = r"\\.\pipe\tauri-plugin-system";
+
+/// JSON-RPC error code for unparseable requests
+pub const PARSE_ERROR: i64 = -32700;
+
+/// JSON-RPC error code for malformed requests
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// JSON-RPC error code for unknown methods
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC error code for missing or mistyped parameters
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// JSON-RPC error code for failed plugin operations
+pub const OPERATION_FAILED: i64 = -32000;
+
+/// A JSON-RPC 2.0 request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcRequest {
+    /// Protocol version, always "2.0"
+    pub jsonrpc: String,
+    
+    /// Request identifier echoed in the response
+    #[serde(default)]
+    pub id: Value,
+    
+    /// Name of the method to call
+    pub method: String,
+    
+    /// Method parameters
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl RpcRequest {
+    /// Create a request for a method
+    pub fn new(id: u64, method: impl Into<String>, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_owned(),
+            id: id.into(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+/// A JSON-RPC 2.0 error object
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RpcError {
+    /// Error code
+    pub code: i64,
+    
+    /// Human-readable error message
+    pub message: String,
+}
+
+impl RpcError {
+    /// Create an error object
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// A JSON-RPC 2.0 response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcResponse {
+    /// Protocol version, always "2.0"
+    pub jsonrpc: String,
+    
+    /// Identifier of the request this answers
+    pub id: Value,
+    
+    /// Result of a successful call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    
+    /// Error of a failed call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    /// Build a response from a call outcome
+    fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        
+        Self {
+            jsonrpc: "2.0".to_owned(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+impl PluginManager {
+    /// Handle a single JSON-RPC request line and return the response line
+    ///
+    /// Supported methods: `install`, `uninstall`, `enable`, `disable`, `list`,
+    /// `info`, `update`, `execute` and `search`.
+    pub async fn handle_ipc_request(&self, request: &str) -> String {
+        let response = match serde_json::from_str::<RpcRequest>(request) {
+            Ok(request) if request.jsonrpc != "2.0" => RpcResponse::new(
+                request.id,
+                Err(RpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported")),
+            ),
+            Ok(request) => {
+                let outcome = self.dispatch_ipc_method(&request.method, &request.params).await;
+                RpcResponse::new(request.id, outcome)
+            },
+            Err(e) => RpcResponse::new(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+        };
+        
+        serde_json::to_string(&response).unwrap_or_default()
+    }
+    
+    /// Listen for JSON-RPC requests on a named pipe
+    ///
+    /// Requires a Tokio runtime. Each client connection is served on its own task
+    /// and may send any number of requests.
+    #[cfg(windows)]
+    pub fn start_ipc_server(self: &Arc<Self>, pipe_name: &str) -> Result<(), PluginError> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        
+        let mut server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(pipe_name)?;
+        
+        info!("Plugin manager IPC server listening on {}", pipe_name);
+        
+        let manager = Arc::clone(self);
+        let pipe_name = pipe_name.to_owned();
+        
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = server.connect().await {
+                    warn!("Failed to accept IPC client: {}", e);
+                    continue;
+                }
+                
+                // Create the next instance before handing this one to the client
+                let client = server;
+                server = match ServerOptions::new().create(&pipe_name) {
+                    Ok(server) => server,
+                    Err(e) => {
+                        warn!("Failed to create IPC pipe instance, stopping server: {}", e);
+                        return;
+                    },
+                };
+                
+                let manager = Arc::clone(&manager);
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(&manager, client).await {
+                        warn!("IPC connection failed: {}", e);
+                    }
+                });
+            }
+        });
+        
+        Ok(())
+    }
+    
+    /// Listen for JSON-RPC requests on a named pipe
+    ///
+    /// Named pipes are only available on Windows.
+    #[cfg(not(windows))]
+    pub fn start_ipc_server(self: &Arc<Self>, pipe_name: &str) -> Result<(), PluginError> {
+        Err(PluginError::Other(format!(
+            "Named pipe IPC is only supported on Windows: {}", pipe_name
+        )))
+    }
+    
+    /// Run a JSON-RPC method against the manager
+    async fn dispatch_ipc_method(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "install" => {
+                let path: PathBuf = param(params, "path")?;
+                let source = if path.is_dir() {
+                    PluginSource::Directory(path)
+                } else {
+                    PluginSource::File(path)
+                };
+                
+                let plugin = self.install_plugin(source).await.map_err(operation_failed)?;
+                to_result(&plugin)
+            },
+            "uninstall" => {
+                self.uninstall_plugin(&param::<String>(params, "plugin_id")?).await.map_err(operation_failed)?;
+                Ok(Value::Null)
+            },
+            "enable" => {
+                self.enable_plugin(&param::<String>(params, "plugin_id")?).await.map_err(operation_failed)?;
+                Ok(Value::Null)
+            },
+            "disable" => {
+                self.disable_plugin(&param::<String>(params, "plugin_id")?).await.map_err(operation_failed)?;
+                Ok(Value::Null)
+            },
+            "list" => {
+                let mut plugins = self.get_all_plugins();
+                plugins.sort_by(|a, b| a.id.cmp(&b.id));
+                to_result(&plugins)
+            },
+            "info" => {
+                let plugin_id: String = param(params, "plugin_id")?;
+                let plugin = self.get_plugin(&plugin_id)
+                    .ok_or_else(|| operation_failed(PluginError::NotFound(plugin_id)))?;
+                to_result(&plugin)
+            },
+            "update" => {
+                let plugin_id: String = param(params, "plugin_id")?;
+                let path: Option<PathBuf> = param(params, "path")?;
+                
+                let plugin = self.update_plugin(&plugin_id, path.map(PluginSource::File)).await
+                    .map_err(operation_failed)?;
+                to_result(&plugin)
+            },
+            "execute" => {
+                let plugin_id: String = param(params, "plugin_id")?;
+                let event: String = param(params, "event")?;
+                let data: String = param(params, "data")?;
+                
+                let result = self.trigger_plugin_event(&plugin_id, &event, data).await.map_err(operation_failed)?;
+                Ok(result.into())
+            },
+            "search" => Err(RpcError::new(
+                OPERATION_FAILED,
+                "Plugin store search is not available: no store is configured",
+            )),
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        }
+    }
+}
+
+/// Send a single JSON-RPC request to a plugin manager pipe and wait for the response
+#[cfg(windows)]
+pub async fn send_ipc_request(pipe_name: &str, request: &RpcRequest) -> io::Result<RpcResponse> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::windows::named_pipe::ClientOptions;
+    
+    let client = ClientOptions::new().open(pipe_name)?;
+    let (reader, mut writer) = tokio::io::split(client);
+    
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    
+    let mut response = String::new();
+    BufReader::new(reader).read_line(&mut response).await?;
+    
+    Ok(serde_json::from_str(&response)?)
+}
+
+/// Send a single JSON-RPC request to a plugin manager pipe and wait for the response
+///
+/// Named pipes are only available on Windows.
+#[cfg(not(windows))]
+pub async fn send_ipc_request(pipe_name: &str, _request: &RpcRequest) -> io::Result<RpcResponse> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Named pipe IPC is only supported on Windows: {}", pipe_name),
+    ))
+}
+
+/// Answer every request line sent over a client connection
+#[cfg(windows)]
+async fn serve_connection(
+    manager: &PluginManager,
+    pipe: tokio::net::windows::named_pipe::NamedPipeServer,
+) -> io::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    
+    let (reader, mut writer) = tokio::io::split(pipe);
+    let mut lines = BufReader::new(reader).lines();
+    
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        
+        let mut response = manager.handle_ipc_request(&line).await;
+        response.push('\n');
+        writer.write_all(response.as_bytes()).await?;
+    }
+    
+    Ok(())
+}
+
+/// Extract a named parameter
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, RpcError> {
+    serde_json::from_value(params.get(name).cloned().unwrap_or(Value::Null))
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid parameter '{}': {}", name, e)))
+}
+
+/// Serialize a method result
+fn to_result<T: Serialize>(value: &T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(OPERATION_FAILED, e.to_string()))
+}
+
+/// Convert a failed plugin operation into a JSON-RPC error
+fn operation_failed(error: impl std::fmt::Display) -> RpcError {
+    RpcError::new(OPERATION_FAILED, error.to_string())
+}
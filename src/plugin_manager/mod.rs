@@ -4,19 +4,69 @@
 //! disabling, and uninstallation. Acts as the central coordinator for the plugin system.
 
 use std::path::{Path, PathBuf};
-use std::fs::{self, File};
-use std::io::{self, Read, Write};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::fs;
+use std::io;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock as StdRwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+use std::thread;
+use std::time::Duration;
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
 use thiserror::Error;
-use log::{info, warn, error};
+use log::{debug, info, warn, error};
 use tokio::sync::RwLock;
 
-use crate::plugin_loader::{PluginLoader, PluginMetadata, PluginLoadError};
-use crate::plugin_host::{PluginHost, PluginHostError};
-use crate::permission_system::{PermissionSystem, Permission, PermissionError, PermissionValidationError};
+use crate::plugin_loader::{
+    missing_host_features, CompatibilityReport, PluginLoader, PluginMetadata, PluginManifest, PluginLoadError,
+    ResourceLimits, ResourceProfile,
+};
+use crate::plugin_host::{
+    PluginHost, PluginHostError, PluginPanic, CallbackThreadModel, EventEnvelope, KvLimits, KvStore, ReplayLog,
+    FEATURE_FLAGS_EVENT, PAUSE_EVENT, RESUME_EVENT,
+};
+use crate::permission_system::{
+    PermissionSystem, Permission, PermissionCategory, PermissionError, PermissionValidationError, VendorTrustLevel,
+};
+
+mod conflicts;
+mod diagnostics;
+mod diff;
+mod event_queue;
+mod events;
+mod integrity;
+pub mod ipc;
+mod quota;
+mod rate_limit;
+mod registry;
+
+pub use conflicts::{CapabilityConflict, CapabilityConflictDetector, ConflictResolution};
+pub use diagnostics::{DiagnosticsBundle, DiagnosticsRedactor, REDACTED, REDACTED_PATH};
+pub use diff::ManifestDiff;
+pub use event_queue::{BoundedEventQueue, EventQueueStats, OverflowPolicy, QueuedEventEmitter, DEFAULT_EVENT_QUEUE_CAPACITY};
+pub use events::PluginEventEmitter;
+pub use integrity::{IntegrityMonitor, IntegrityReport};
+pub use quota::{DataDirQuotaEnforcer, PluginDataUsage, QuotaAction, QuotaStatus};
+pub use rate_limit::{CommandRateLimiter, DEFAULT_COMMAND_RATE_LIMIT, DEFAULT_COMMAND_RATE_WINDOW};
+pub use registry::RegistryFormat;
+
+use events::EventSink;
+use registry::RegistryStore;
+
+/// Version of the plugin host
+pub const HOST_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Resource limits a plugin may request when the host has not configured maxima
+pub const DEFAULT_MAX_RESOURCE_LIMITS: ResourceLimits = ResourceLimits {
+    max_cpu_percent: 100,
+    max_memory_mb: 1024,
+    data_dir_quota_bytes: None,
+};
+
+/// Consecutive load failures after which a plugin is quarantined by default
+pub const DEFAULT_QUARANTINE_THRESHOLD: u32 = 3;
+
+/// Largest plugin package accepted from memory
+pub const MAX_IN_MEMORY_PACKAGE_BYTES: usize = 64 * 1024 * 1024;
 
 /// Error type for plugin operations
 #[derive(Error, Debug)]
@@ -53,6 +103,32 @@ pub enum PluginError {
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
     
+    /// Plugin data directory is over its quota
+    #[error("Plugin {plugin_id} uses {used_bytes} bytes of data, quota is {limit_bytes} bytes")]
+    QuotaExceeded {
+        /// ID of the plugin
+        plugin_id: String,
+        /// Bytes currently used
+        used_bytes: u64,
+        /// Quota in bytes
+        limit_bytes: u64,
+    },
+    
+    /// Plugin received more commands than its rate limit allows
+    #[error("Command rate limit exceeded for plugin: {0}")]
+    RateLimited(String),
+    
+    /// Plugin command returned a nonzero status code
+    #[error("Plugin {plugin_id} failed command '{command}' with code {code}")]
+    CommandFailed {
+        /// ID of the plugin
+        plugin_id: String,
+        /// Name of the command
+        command: String,
+        /// Status code returned by the plugin
+        code: i32,
+    },
+    
     /// Other error
     #[error("{0}")]
     Other(String),
@@ -81,9 +157,22 @@ pub enum PluginInstallError {
     #[error("Registry update failed: {0}")]
     RegistryFailed(String),
     
+    /// Manifest requests resource limits above the host maxima
+    #[error("Resource limits exceed host maxima: {0}")]
+    ResourceLimitsExceeded(String),
+    
     /// Plugin already installed
     #[error("Plugin already installed: {0}")]
     AlreadyInstalled(String),
+    
+    /// Package exceeds the size accepted for its source
+    #[error("Package too large: {size} bytes exceeds the {max} byte limit")]
+    PackageTooLarge {
+        /// Package size in bytes
+        size: usize,
+        /// Maximum accepted size in bytes
+        max: usize,
+    },
 }
 
 /// Error type for plugin updates
@@ -142,9 +231,13 @@ pub struct PluginInfo {
     /// Installation path of the plugin
     pub install_path: PathBuf,
     
-    /// Status of the plugin (enabled, disabled, error)
+    /// Actual status of the plugin (enabled, disabled, error)
     pub status: PluginStatus,
     
+    /// State the user asked for, restored at startup by `restore_enabled_plugins`
+    #[serde(default)]
+    pub desired_state: DesiredState,
+    
     /// Permissions granted to the plugin
     pub permissions: Vec<Permission>,
     
@@ -153,6 +246,30 @@ pub struct PluginInfo {
     
     /// Last update timestamp, if any
     pub updated_at: Option<DateTime<Utc>>,
+    
+    /// Resource limits applied when the plugin was last enabled
+    #[serde(default)]
+    pub resource_limits: Option<ResourceLimits>,
+    
+    /// Whether the plugin is a development install loaded from its source directory
+    #[serde(default)]
+    pub dev: bool,
+    
+    /// Total size of the installed plugin files in bytes
+    #[serde(default)]
+    pub installed_size_bytes: u64,
+    
+    /// Number of installed plugin files
+    #[serde(default)]
+    pub file_count: u64,
+    
+    /// Capabilities the plugin provides
+    #[serde(default)]
+    pub provides_capabilities: Vec<String>,
+    
+    /// Optional plugin behavior switched on or off by the host
+    #[serde(default)]
+    pub feature_flags: HashMap<String, bool>,
 }
 
 /// Status of a plugin
@@ -164,11 +281,28 @@ pub enum PluginStatus {
     /// Plugin is disabled
     Disabled,
     
+    /// Plugin is loaded but not receiving events
+    Paused,
+    
     /// Plugin is in an error state
     Error(String),
     
     /// Plugin is incompatible with the current system
     Incompatible(String),
+    
+    /// Plugin failed to load too many times in a row and is not enabled until retried
+    Quarantined(String),
+}
+
+/// State a plugin should be in, independent of whether it got there
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DesiredState {
+    /// The user enabled the plugin
+    Enabled,
+    
+    /// The user disabled the plugin, or never enabled it
+    #[default]
+    Disabled,
 }
 
 /// Source of a plugin package
@@ -182,6 +316,99 @@ pub enum PluginSource {
     
     /// Plugin store identifier
     Store(String),
+    
+    /// Unpacked plugin directory, loaded in place as a development install
+    Directory(PathBuf),
+    
+    /// Package contents held in memory, such as a file dropped on the UI
+    Bytes(Vec<u8>),
+}
+
+/// A plugin version offered by an update source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailableVersion {
+    /// Version of the plugin
+    pub version: String,
+    
+    /// API version the plugin is compatible with
+    pub api_version: String,
+    
+    /// Minimum host version required by the plugin, if any
+    #[serde(default)]
+    pub min_host_version: Option<String>,
+    
+    /// Changelog for this version
+    #[serde(default)]
+    pub changelog: Option<String>,
+    
+    /// Permissions required by this version
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+/// Information about an available plugin update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateInfo {
+    /// ID of the plugin
+    pub plugin_id: String,
+    
+    /// Currently installed version
+    pub current_version: String,
+    
+    /// Latest compatible version
+    pub version: String,
+    
+    /// Changelog for the new version
+    pub changelog: Option<String>,
+    
+    /// Permissions requested by the new version that the installed one does not have
+    pub new_permissions: Vec<Permission>,
+}
+
+/// A plugin that failed to shut down cleanly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisableFailure {
+    /// ID of the plugin
+    pub plugin_id: String,
+    
+    /// Error reported while disabling the plugin
+    pub error: String,
+}
+
+/// Outcome of re-enabling the plugins that were enabled before a restart
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreReport {
+    /// Plugins enabled again
+    pub restored: Vec<String>,
+    
+    /// Plugins that failed to enable; they stay desired enabled
+    pub failed: Vec<RestoreFailure>,
+}
+
+/// A plugin that could not be re-enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreFailure {
+    /// ID of the plugin
+    pub plugin_id: String,
+    
+    /// Error reported while enabling the plugin
+    pub error: String,
+}
+
+/// Outcome of disabling every plugin at once
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisableAllReport {
+    /// Plugins disabled cleanly
+    pub disabled: Vec<String>,
+    
+    /// Plugins that reported an error; they are disabled regardless
+    pub failed: Vec<DisableFailure>,
+}
+
+/// Source queried for available plugin versions
+pub trait UpdateSource: Send + Sync {
+    /// List the versions available for a plugin
+    fn available_versions(&self, plugin_id: &str, plugin_name: &str) -> Result<Vec<AvailableVersion>, PluginUpdateError>;
 }
 
 /// Plugin registry for storing plugin metadata
@@ -211,13 +438,49 @@ pub struct PluginManager {
     permission_system: Arc<PermissionSystem>,
     
     /// Registry of installed plugins
-    registry: Arc<Mutex<PluginRegistry>>,
+    registry: Arc<StdRwLock<PluginRegistry>>,
     
     /// Base directory for plugins
     plugins_dir: PathBuf,
     
-    /// Path to the registry file
-    registry_path: PathBuf,
+    /// Persistent storage of the registry
+    registry_store: Arc<RegistryStore>,
+    
+    /// Source queried for plugin updates
+    update_source: Mutex<Option<Arc<dyn UpdateSource>>>,
+    
+    /// File hashes of installed plugins
+    integrity_monitor: Arc<IntegrityMonitor>,
+    
+    /// Sink for events destined for the UI
+    events: EventSink,
+    
+    /// Queue of the configured emitter, if it buffers events
+    event_queue: Mutex<Option<Arc<BoundedEventQueue>>>,
+    
+    /// Highest resource limits a plugin may be given
+    max_resource_limits: Mutex<ResourceLimits>,
+    
+    /// Host-configured resource limits taking precedence over manifest suggestions
+    resource_limit_overrides: Mutex<HashMap<String, ResourceLimits>>,
+    
+    /// Data directory quota enforcer
+    quota_enforcer: DataDirQuotaEnforcer,
+    
+    /// Action taken when a plugin exceeds its data directory quota
+    quota_action: Mutex<QuotaAction>,
+    
+    /// Limits the commands sent to each plugin through `execute_plugin_command`
+    command_rate_limiter: Mutex<Arc<CommandRateLimiter>>,
+    
+    /// Detects plugins providing the same capability
+    conflict_detector: Mutex<CapabilityConflictDetector>,
+    
+    /// Consecutive load failures of each plugin
+    load_failures: Mutex<HashMap<String, u32>>,
+    
+    /// Consecutive load failures after which a plugin is quarantined
+    quarantine_threshold: Mutex<u32>,
 }
 
 impl PluginManager {
@@ -227,7 +490,17 @@ impl PluginManager {
         registry_path: PathBuf,
         permission_system: Arc<PermissionSystem>,
     ) -> Result<Self, PluginError> {
-
+        Self::with_registry_format(plugins_dir, registry_path, permission_system, RegistryFormat::default())
+    }
+    
+    /// Create a new plugin manager storing its registry in the given format
+    pub fn with_registry_format(
+        plugins_dir: PathBuf,
+        registry_path: PathBuf,
+        permission_system: Arc<PermissionSystem>,
+        registry_format: RegistryFormat,
+    ) -> Result<Self, PluginError> {
+        
         // Create plugins directory if it doesn't exist
         fs::create_dir_all(&plugins_dir)?;
         
@@ -238,57 +511,369 @@ impl PluginManager {
         // Create plugin loader
         let plugin_loader = PluginLoader::new(extract_dir);
         
-        // Create plugin host
-        let plugin_host = Arc::new(RwLock::new(PluginHost::new()));
-        
         // Load registry if it exists
-        let registry = if registry_path.exists() {
-            let mut file = File::open(&registry_path)?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-            
-            match serde_json::from_str::<PluginRegistry>(&contents) {
-                Ok(reg) => reg,
-                Err(e) => {
-                    warn!("Failed to parse plugin registry: {}", e);
-                    PluginRegistry::default()
-                },
-            }
-        } else {
-            PluginRegistry::default()
-        };
+        let registry_store = Arc::new(RegistryStore::new(registry_path, registry_format));
+        let mut registry = registry_store.load()?;
+        
+        // Plugins installed before a host upgrade may no longer be supported
+        mark_incompatible_plugins(&mut registry);
+        
+        // Load integrity records stored next to the registry
+        let integrity_monitor = Arc::new(IntegrityMonitor::load(registry_store.path().with_extension("integrity.json")));
+        
+        // Plugin data directories live next to the installed plugins
+        let quota_enforcer = DataDirQuotaEnforcer::new(plugins_dir.join("data"));
+        
+        let registry = Arc::new(StdRwLock::new(registry));
+        let events = EventSink::default();
+        
+        // Create plugin host, marking plugins whose callbacks panic as errored and unloading them
+        let plugin_host = Arc::new_cyclic(|plugin_host: &Weak<RwLock<PluginHost>>| {
+            let host = PluginHost::new();
+            host.set_panic_listener(Box::new({
+                let registry = registry.clone();
+                let registry_store = registry_store.clone();
+                let events = events.clone();
+                let plugin_host = plugin_host.clone();
+                move |plugin_panic| {
+                    mark_panicked_plugin(&registry, &registry_store, &events, plugin_panic);
+                    teardown_panicked_plugin(plugin_host.clone(), plugin_panic.plugin_id.clone());
+                }
+            }));
+            RwLock::new(host)
+        });
         
         Ok(Self {
             plugin_loader,
             plugin_host,
             permission_system,
-            registry: Arc::new(Mutex::new(registry)),
+            registry,
             plugins_dir,
-            registry_path,
+            registry_store,
+            update_source: Mutex::new(None),
+            integrity_monitor,
+            events,
+            event_queue: Mutex::new(None),
+            max_resource_limits: Mutex::new(DEFAULT_MAX_RESOURCE_LIMITS),
+            resource_limit_overrides: Mutex::new(HashMap::new()),
+            quota_enforcer,
+            quota_action: Mutex::new(QuotaAction::default()),
+            command_rate_limiter: Mutex::new(Arc::new(CommandRateLimiter::default())),
+            conflict_detector: Mutex::new(CapabilityConflictDetector::new()),
+            load_failures: Mutex::new(HashMap::new()),
+            quarantine_threshold: Mutex::new(DEFAULT_QUARANTINE_THRESHOLD),
         })
     }
-
-    /// Save the plugin registry to disk
-    fn save_registry(&self) -> Result<(), PluginError> {
-        let registry = self.registry.lock().unwrap();
-        let contents = serde_json::to_string_pretty(&*registry)?;
+    
+    /// Get the data directory of a plugin
+    pub fn plugin_data_dir(&self, plugin_id: &str) -> PathBuf {
+        self.quota_enforcer.data_dir(plugin_id)
+    }
+    
+    /// Get the data directory quota enforcer
+    pub fn data_dir_quota_enforcer(&self) -> &DataDirQuotaEnforcer {
+        &self.quota_enforcer
+    }
+    
+    /// Set the action taken when a plugin exceeds its data directory quota
+    pub fn set_quota_action(&self, action: QuotaAction) {
+        *lock(&self.quota_action) = action;
+    }
+    
+    /// Check a plugin's data directory against its quota and apply the quota action
+    ///
+    /// Returns `None` when the plugin has no data directory quota. An exceeded
+    /// quota emits `plugin-data-quota-exceeded` once, whatever the action. With
+    /// `QuotaAction::Block` it is also reported as `PluginError::QuotaExceeded`.
+    pub fn enforce_data_quota(&self, plugin_id: &str) -> Result<Option<QuotaStatus>, PluginError> {
+        let limit_bytes = match self.effective_resource_limits(plugin_id)?.data_dir_quota_bytes {
+            Some(limit_bytes) => limit_bytes,
+            None => return Ok(None),
+        };
         
-        // Create parent directories if they don't exist
-        if let Some(parent) = self.registry_path.parent() {
-            fs::create_dir_all(parent)?;
+        let status = self.quota_enforcer.check_quota(plugin_id, limit_bytes);
+        let used_bytes = match status {
+            QuotaStatus::Exceeded { used_bytes, .. } => used_bytes,
+            QuotaStatus::WithinQuota { .. } => return Ok(Some(status)),
+        };
+        
+        self.events.emit("plugin-data-quota-exceeded", &serde_json::json!({
+            "plugin_id": plugin_id,
+            "used_bytes": used_bytes,
+            "limit_bytes": limit_bytes,
+        }));
+        
+        let action = *lock(&self.quota_action);
+        match action {
+            QuotaAction::Warn => {
+                warn!("Plugin '{}' exceeds its data quota: {} of {} bytes", plugin_id, used_bytes, limit_bytes);
+                Ok(Some(status))
+            },
+            QuotaAction::Block => Err(PluginError::QuotaExceeded {
+                plugin_id: plugin_id.to_owned(),
+                used_bytes,
+                limit_bytes,
+            }),
+            QuotaAction::Evict => {
+                let evicted = self.quota_enforcer.evict_to_quota(plugin_id, limit_bytes)?;
+                info!("Evicted {} files from the data directory of plugin '{}'", evicted.len(), plugin_id);
+                Ok(Some(self.quota_enforcer.check_quota(plugin_id, limit_bytes)))
+            },
+        }
+    }
+    
+    /// Get the number of bytes stored in a plugin's data directory
+    pub fn get_plugin_data_usage(&self, plugin_id: &str) -> u64 {
+        self.quota_enforcer.get_usage(plugin_id)
+    }
+    
+    /// Get the data directory usage of every installed plugin, largest first
+    pub fn get_all_data_usage(&self) -> Vec<PluginDataUsage> {
+        let mut usage: Vec<PluginDataUsage> = self.get_all_plugins().into_iter()
+            .map(|plugin| PluginDataUsage {
+                used_bytes: self.get_plugin_data_usage(&plugin.id),
+                limit_bytes: self.effective_resource_limits(&plugin.id).ok()
+                    .and_then(|limits| limits.data_dir_quota_bytes),
+                plugin_id: plugin.id,
+            })
+            .collect();
+        
+        usage.sort_by(|a, b| b.used_bytes.cmp(&a.used_bytes).then_with(|| a.plugin_id.cmp(&b.plugin_id)));
+        usage
+    }
+    
+    /// Delete a plugin's least recently modified data files until at most `keep_bytes` remain
+    ///
+    /// Returns the deleted files, oldest first.
+    pub fn trim_plugin_data(&self, plugin_id: &str, keep_bytes: u64) -> Result<Vec<PathBuf>, PluginError> {
+        if self.get_plugin(plugin_id).is_none() {
+            return Err(PluginError::NotFound(plugin_id.to_owned()));
         }
         
-        let mut file = File::create(&self.registry_path)?;
-        file.write_all(contents.as_bytes())?;
+        let deleted = self.quota_enforcer.evict_to_quota(plugin_id, keep_bytes)?;
+        info!("Trimmed {} files from the data directory of plugin '{}'", deleted.len(), plugin_id);
         
-        Ok(())
+        Ok(deleted)
+    }
+    
+    /// Periodically enforce the data directory quotas of all enabled plugins
+    ///
+    /// Requires a Tokio runtime; abort the returned handle to stop checking.
+    pub fn start_periodic_quota_check(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let manager = Arc::clone(self);
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            
+            loop {
+                ticker.tick().await;
+                
+                for plugin in manager.get_enabled_plugins() {
+                    match manager.enforce_data_quota(&plugin.id) {
+                        Ok(_) => {},
+                        Err(PluginError::QuotaExceeded { .. }) => {
+                            debug!("Plugin '{}' is blocked from storing more data", plugin.id);
+                        },
+                        Err(e) => warn!("Failed to check data quota of plugin '{}': {}", plugin.id, e),
+                    }
+                }
+            }
+        })
+    }
+    
+    /// Set the highest resource limits a plugin may be given
+    pub fn set_max_resource_limits(&self, maxima: ResourceLimits) {
+        *lock(&self.max_resource_limits) = maxima;
+    }
+    
+    /// Override the resource limits suggested by a plugin's manifest
+    pub fn set_resource_limits(&self, plugin_id: &str, limits: ResourceLimits) {
+        lock(&self.resource_limit_overrides).insert(plugin_id.to_owned(), limits);
+    }
+    
+    /// Get the resource limits that enabling a plugin will apply
+    ///
+    /// Host overrides take precedence over the manifest's suggested limits, which
+    /// take precedence over the plugin's resource profile. The result is clamped
+    /// to the host maxima.
+    pub fn effective_resource_limits(&self, plugin_id: &str) -> Result<ResourceLimits, PluginError> {
+        let plugin_info = self.get_plugin(plugin_id)
+            .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?;
+        
+        let limits = match lock(&self.resource_limit_overrides).get(plugin_id) {
+            Some(limits) => *limits,
+            None => {
+                let manifest = read_manifest(&plugin_info.install_path)?;
+                match manifest.resource_limits {
+                    Some(limits) => limits,
+                    None => self.profile_for_vendor(&manifest, &plugin_info.install_path).limits(),
+                }
+            },
+        };
+        
+        let maxima = *lock(&self.max_resource_limits);
+        Ok(limits.clamp_to(&maxima))
+    }
+    
+    /// Get the resource profile matching how far a plugin's vendor is trusted
+    pub fn resource_profile(&self, plugin_id: &str) -> Result<ResourceProfile, PluginError> {
+        let plugin_info = self.get_plugin(plugin_id)
+            .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?;
+        
+        let manifest = read_manifest(&plugin_info.install_path)?;
+        Ok(self.profile_for_vendor(&manifest, &plugin_info.install_path))
+    }
+    
+    /// Pick the resource profile for an installed plugin from its vendor's policy
+    ///
+    /// Only a vendor the permission system's verifier authenticated is looked up;
+    /// plugins from unverified vendors or vendors without a policy get the strict profile.
+    fn profile_for_vendor(&self, manifest: &PluginManifest, install_path: &Path) -> ResourceProfile {
+        let policy = self.permission_system.verified_vendor(manifest, install_path)
+            .and_then(|vendor_id| self.permission_system.vendor_policy(&vendor_id));
+        
+        match policy.map(|policy| policy.trust_level) {
+            Some(VendorTrustLevel::Trusted) => ResourceProfile::Trusted,
+            Some(VendorTrustLevel::Standard) => ResourceProfile::Standard,
+            Some(VendorTrustLevel::Blocked) | None => ResourceProfile::Strict,
+        }
+    }
+    
+    /// Check a manifest's suggested resource limits against the host maxima
+    fn check_resource_limits(&self, manifest: &PluginManifest) -> Result<(), String> {
+        let maxima = *lock(&self.max_resource_limits);
+        match &manifest.resource_limits {
+            Some(limits) if limits.exceeds(&maxima) => Err(format!(
+                "{} requests {}% CPU and {} MB memory, host allows {}% CPU and {} MB memory",
+                manifest.name, limits.max_cpu_percent, limits.max_memory_mb,
+                maxima.max_cpu_percent, maxima.max_memory_mb,
+            )),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Set how conflicts over a capability should be resolved
+    pub fn set_conflict_resolution_policy(&self, capability_id: &str, resolution: ConflictResolution) {
+        lock(&self.conflict_detector).set_policy(capability_id, resolution);
+    }
+    
+    /// Find the capabilities provided by more than one installed plugin
+    pub fn detect_capability_conflicts(&self) -> Vec<CapabilityConflict> {
+        lock(&self.conflict_detector).detect(&self.get_all_plugins())
+    }
+    
+    /// Emit a `plugin-capability-conflict` event for each manual conflict involving a plugin
+    fn report_capability_conflicts(&self, plugin_id: &str, plugins: &[PluginInfo]) {
+        let conflicts = lock(&self.conflict_detector).detect(plugins);
+        
+        for conflict in conflicts {
+            if !conflict.conflicting_plugins.iter().any(|id| id == plugin_id) {
+                continue;
+            }
+            
+            warn!(
+                "Capability '{}' is provided by several plugins: {}",
+                conflict.capability_id, conflict.conflicting_plugins.join(", ")
+            );
+            
+            if conflict.resolution == ConflictResolution::Manual {
+                self.events.emit("plugin-capability-conflict", &conflict);
+            }
+        }
+    }
+    
+    /// Set the emitter receiving plugin system events
+    pub fn set_event_emitter<E: PluginEventEmitter + 'static>(&self, emitter: E) {
+        *lock(&self.event_queue) = None;
+        self.events.set_emitter(Arc::new(emitter));
+    }
+    
+    /// Set an emitter receiving plugin system events through a bounded queue
+    pub fn set_queued_event_emitter(&self, emitter: QueuedEventEmitter) {
+        *lock(&self.event_queue) = Some(emitter.queue());
+        self.events.set_emitter(Arc::new(emitter));
+    }
+    
+    /// Get the counters of the event queue, if the emitter buffers events
+    pub fn event_queue_stats(&self) -> Option<EventQueueStats> {
+        lock(&self.event_queue).as_ref().map(|queue| queue.stats())
+    }
+    
+    /// Get the integrity monitor tracking installed plugin files
+    pub fn integrity_monitor(&self) -> &IntegrityMonitor {
+        &self.integrity_monitor
+    }
+    
+    /// Periodically verify the files of all enabled plugins
+    ///
+    /// Emits a `plugin-integrity-violation` event for every plugin whose files no
+    /// longer match the hashes recorded at installation. Requires a Tokio runtime;
+    /// abort the returned handle to stop checking.
+    pub fn start_periodic_integrity_check(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let registry = self.registry.clone();
+        let integrity_monitor = self.integrity_monitor.clone();
+        let events = self.events.clone();
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            
+            loop {
+                ticker.tick().await;
+                
+                let enabled: Vec<String> = read_registry(&registry).plugins.values()
+                    .filter(|p| p.status == PluginStatus::Enabled)
+                    .map(|p| p.id.clone())
+                    .collect();
+                
+                for plugin_id in enabled {
+                    match integrity_monitor.verify_plugin(&plugin_id) {
+                        Ok(report) if !report.ok => {
+                            warn!("Integrity violation detected for plugin '{}': {:?}", plugin_id, report);
+                            events.emit("plugin-integrity-violation", &serde_json::json!({
+                                "plugin_id": plugin_id,
+                                "report": report,
+                            }));
+                        },
+                        Ok(_) => {},
+                        Err(e) => warn!("Failed to verify integrity of plugin '{}': {}", plugin_id, e),
+                    }
+                }
+            }
+        })
+    }
+    
+    /// Set the source queried for plugin updates
+    pub fn set_update_source<S: UpdateSource + 'static>(&self, source: S) {
+        *lock(&self.update_source) = Some(Arc::new(source));
+    }
+    
+    /// Save the registry record of a plugin to disk
+    fn save_registry(&self, plugin_id: &str) -> Result<(), PluginError> {
+        let registry = read_registry(&self.registry);
+        self.registry_store.save_plugin(&registry, plugin_id)
     }
     
     /// Install a plugin from a package source
     pub async fn install_plugin(&self, source: PluginSource) -> Result<PluginInfo, PluginInstallError> {
-        // Get the package path
-        let package_path = match source {
-            PluginSource::File(path) => path,
+        // Load and validate the package
+        let (metadata, dev) = match source {
+            PluginSource::File(path) => (self.plugin_loader.load_plugin_package(&path).await?, false),
+            PluginSource::Directory(path) => {
+                warn!(
+                    "DEVELOPMENT INSTALL: loading plugin from unpacked directory {} without packaging or integrity checks",
+                    path.display()
+                );
+                (self.plugin_loader.load_plugin_directory(&path)?, true)
+            },
+            PluginSource::Bytes(package) => {
+                if package.len() > MAX_IN_MEMORY_PACKAGE_BYTES {
+                    return Err(PluginInstallError::PackageTooLarge {
+                        size: package.len(),
+                        max: MAX_IN_MEMORY_PACKAGE_BYTES,
+                    });
+                }
+                (self.plugin_loader.load_plugin_bytes(&package)?, false)
+            },
             PluginSource::Url(url) => {
                 return Err(PluginInstallError::DownloadFailed(
                     format!("URL installation not yet implemented: {}", url)
@@ -301,15 +886,12 @@ impl PluginManager {
             },
         };
         
-        // Load and validate the package
-        let metadata = self.plugin_loader.load_plugin_package(&package_path).await?;
-        
         // Generate a unique plugin ID
         let plugin_id = format!("{}-{}", metadata.manifest.name.to_lowercase().replace(" ", "-"), metadata.manifest.version);
         
         // Check if plugin is already installed
         {
-            let registry = self.registry.lock().unwrap();
+            let registry = read_registry(&self.registry);
             if registry.plugins.contains_key(&plugin_id) {
                 return Err(PluginInstallError::AlreadyInstalled(plugin_id));
             }
@@ -318,12 +900,24 @@ impl PluginManager {
         // Validate permissions
         self.permission_system.validate_permissions(&metadata.manifest.permissions)?;
         
-        // Create installation directory
-        let install_dir = self.plugins_dir.join(&plugin_id);
-        fs::create_dir_all(&install_dir)?;
+        // Validate requested resource limits
+        self.check_resource_limits(&metadata.manifest)
+            .map_err(PluginInstallError::ResourceLimitsExceeded)?;
         
-        // Copy files from extraction directory to installation directory
-        copy_dir_all(&metadata.install_path, &install_dir)?;
+        // Development installs are used in place; packages are copied into the plugins directory
+        let install_dir = if dev {
+            metadata.install_path.clone()
+        } else {
+            let install_dir = self.plugins_dir.join(&plugin_id);
+            fs::create_dir_all(&install_dir)?;
+            
+            // Copy files from extraction directory to installation directory
+            copy_dir_all(&metadata.install_path, &install_dir)?;
+            
+            install_dir
+        };
+        
+        let (installed_size_bytes, file_count) = dir_usage(&install_dir);
         
         // Create plugin info
         let plugin_info = PluginInfo {
@@ -335,23 +929,41 @@ impl PluginManager {
             homepage: metadata.manifest.homepage.clone(),
             install_path: install_dir.clone(),
             status: PluginStatus::Disabled, // Start disabled by default
+            desired_state: DesiredState::Disabled,
             permissions: metadata.manifest.permissions.clone(),
             installed_at: Utc::now(),
             updated_at: None,
+            resource_limits: None,
+            dev,
+            installed_size_bytes,
+            file_count,
+            provides_capabilities: metadata.manifest.provides_capabilities.clone(),
+            feature_flags: HashMap::new(),
         };
         
+        // Record file hashes for later integrity checks; development files are expected to change
+        if !dev {
+            if let Err(e) = self.integrity_monitor.record_plugin(&plugin_id, &install_dir) {
+                error!("Failed to record integrity hashes for plugin '{}': {}", plugin_id, e);
+            }
+        }
+        
         // Update registry
         {
-            let mut registry = self.registry.lock().unwrap();
+            let mut registry = write_registry(&self.registry);
             registry.plugins.insert(plugin_id.clone(), plugin_info.clone());
         }
         
         // Save registry
-        if let Err(e) = self.save_registry() {
+        if let Err(e) = self.save_registry(&plugin_id) {
             error!("Failed to save plugin registry: {}", e);
         }
         
+        // Warn about capabilities already provided by other installed plugins
+        self.report_capability_conflicts(&plugin_id, &self.get_all_plugins());
+        
         info!("Plugin '{}' installed successfully", plugin_id);
+        self.events.emit("plugin-installed", &plugin_info);
         
         Ok(plugin_info)
     }
@@ -360,7 +972,7 @@ impl PluginManager {
     pub async fn enable_plugin(&self, plugin_id: &str) -> Result<(), PluginError> {
         // Get plugin info
         let plugin_info = {
-            let registry = self.registry.lock().unwrap();
+            let registry = read_registry(&self.registry);
             registry.plugins.get(plugin_id).cloned().ok_or_else(|| {
                 PluginError::NotFound(plugin_id.to_owned())
             })?
@@ -371,6 +983,19 @@ impl PluginManager {
             return Ok(());
         }
         
+        // Paused plugins are still loaded
+        if plugin_info.status == PluginStatus::Paused {
+            return self.resume_plugin(plugin_id).await;
+        }
+        
+        // Failed plugins may not have been unloaded yet; reload them from scratch
+        if matches!(plugin_info.status, PluginStatus::Error(_)) {
+            let mut plugin_host = self.plugin_host.write().await;
+            if plugin_host.has_plugin(plugin_id) {
+                plugin_host.teardown_plugin(plugin_id)?;
+            }
+        }
+        
         // Check for incompatible status
         if let PluginStatus::Incompatible(reason) = &plugin_info.status {
             return Err(PluginError::InvalidState(
@@ -378,65 +1003,252 @@ impl PluginManager {
             ));
         }
         
+        // Quarantined plugins are only enabled through an explicit retry
+        if let PluginStatus::Quarantined(reason) = &plugin_info.status {
+            return Err(PluginError::InvalidState(
+                format!("Plugin is quarantined after repeated load failures: {}", reason)
+            ));
+        }
+        
+        // Refuse plugins needing features this build left out
+        let manifest = read_manifest(&plugin_info.install_path)?;
+        let missing_features = missing_host_features(&manifest);
+        if !missing_features.is_empty() {
+            let reason = format!("plugin requires host features not in this build: {}", missing_features.join(", "));
+            {
+                let mut registry = write_registry(&self.registry);
+                if let Some(plugin) = registry.plugins.get_mut(plugin_id) {
+                    plugin.status = PluginStatus::Incompatible(reason.clone());
+                }
+            }
+            self.save_registry(plugin_id)?;
+            
+            return Err(PluginLoadError::Incompatible(reason).into());
+        }
+        
+        // Warn about capabilities already provided by active plugins
+        let mut active_plugins: Vec<PluginInfo> = self.get_all_plugins().into_iter()
+            .filter(|p| matches!(p.status, PluginStatus::Enabled | PluginStatus::Paused))
+            .collect();
+        active_plugins.push(plugin_info.clone());
+        self.report_capability_conflicts(plugin_id, &active_plugins);
+        
+        // Resolve resource limits before loading
+        let resource_limits = self.effective_resource_limits(plugin_id)?;
+        
         // Load plugin DLL
         let dll_path = plugin_info.install_path.join("plugin.dll");
         let metadata = PluginMetadata {
-            manifest: serde_json::from_slice(&fs::read(
-                plugin_info.install_path.join("plugin.json")
-            )?)?,
+            manifest,
             install_path: plugin_info.install_path.clone(),
             dll_path,
             installed_at: plugin_info.installed_at,
         };
         
-        let loaded_plugin = self.plugin_loader.load_plugin_dll(&metadata)?;
+        let loaded_plugin = match self.plugin_loader.load_plugin_dll(&metadata) {
+            Ok(loaded_plugin) => loaded_plugin,
+            Err(e) => return Err(self.record_load_failure(plugin_id, e.into())),
+        };
         
         // Check and prompt for permissions if needed
         let permissions = self.permission_system.get_granted_permissions(plugin_id);
         if permissions.is_empty() {
-            // Prompt for permissions
-            let granted_permissions = self.permission_system.prompt_for_permissions(
+            // Grant what the vendor policy covers, then prompt for the rest
+            self.permission_system.apply_vendor_policy(plugin_id, &metadata.manifest, &metadata.install_path)?;
+            
+            let prompt_result = self.permission_system.prompt_for_permissions(
                 plugin_id,
                 &plugin_info.name,
                 &metadata.manifest.permissions,
-            ).await?;
+            ).await;
+            
+            let granted_permissions = match prompt_result {
+                Ok(granted_permissions) => granted_permissions,
+                Err(e) => {
+                    // Drop the vendor grants so the next attempt prompts again
+                    self.permission_system.revoke_permissions(plugin_id)?;
+                    return Err(e.into());
+                },
+            };
             
             // Store granted permissions
             self.permission_system.grant_permissions(plugin_id, granted_permissions, true)?;
         }
         
+        // Open the plugin's key-value store if it may use one
+        let storage = if self.permission_system.get_granted_permissions(plugin_id).contains(&Permission::Storage) {
+            Some(Arc::new(KvStore::open(&self.plugin_data_dir(plugin_id), KvLimits::default())?))
+        } else {
+            None
+        };
+        
         // Initialize plugin
-        let mut plugin_host = self.plugin_host.write().await;
-        plugin_host.init_plugin(plugin_id.to_owned(), loaded_plugin)?;
+        let init_result = self.plugin_host.write().await
+            .init_plugin_with_storage(plugin_id.to_owned(), loaded_plugin, storage);
+        if let Err(e) = init_result {
+            return Err(self.record_load_failure(plugin_id, e.into()));
+        }
+        lock(&self.load_failures).remove(plugin_id);
         
         // Update status
         {
-            let mut registry = self.registry.lock().unwrap();
+            let mut registry = write_registry(&self.registry);
             if let Some(plugin) = registry.plugins.get_mut(plugin_id) {
                 plugin.status = PluginStatus::Enabled;
+                plugin.desired_state = DesiredState::Enabled;
+                plugin.resource_limits = Some(resource_limits);
             }
         }
         
         // Save registry
-        self.save_registry()?;
+        self.save_registry(plugin_id)?;
+        
+        if !plugin_info.feature_flags.is_empty() {
+            self.send_feature_flags(plugin_id, &plugin_info.feature_flags, None).await;
+        }
         
         info!("Plugin '{}' enabled successfully", plugin_id);
         
         Ok(())
     }
     
+    /// Switch one of a plugin's feature flags on or off
+    ///
+    /// The flag is saved with the plugin's registry record. A running plugin is
+    /// sent `feature_flags_changed` with all its flags and the changed flag's name.
+    pub async fn set_plugin_feature(&self, plugin_id: &str, flag: &str, value: bool) -> Result<(), PluginError> {
+        let plugin_info = {
+            let mut registry = write_registry(&self.registry);
+            let plugin = registry.plugins.get_mut(plugin_id)
+                .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?;
+            
+            plugin.feature_flags.insert(flag.to_owned(), value);
+            plugin.clone()
+        };
+        
+        self.save_registry(plugin_id)?;
+        
+        if matches!(plugin_info.status, PluginStatus::Enabled | PluginStatus::Paused) {
+            self.send_feature_flags(plugin_id, &plugin_info.feature_flags, Some(flag)).await;
+        }
+        
+        self.events.emit("plugin-feature-changed", &serde_json::json!({
+            "plugin_id": plugin_id,
+            "flag": flag,
+            "value": value,
+        }));
+        
+        Ok(())
+    }
+    
+    /// Send a running plugin its feature flags
+    async fn send_feature_flags(&self, plugin_id: &str, flags: &HashMap<String, bool>, changed: Option<&str>) {
+        let payload = serde_json::json!({
+            "flags": flags,
+            "changed": changed,
+        });
+        
+        // Release the host lock before waiting on the result
+        let pending = self.plugin_host.read().await.dispatch_event(plugin_id, FEATURE_FLAGS_EVENT, payload.to_string());
+        match pending {
+            Ok(pending) => {
+                if let Err(e) = pending.await {
+                    warn!("Plugin '{}' failed to handle '{}': {}", plugin_id, FEATURE_FLAGS_EVENT, e);
+                }
+            },
+            Err(PluginHostError::InvalidEventName(_)) => {
+                debug!("Plugin '{}' does not handle '{}'", plugin_id, FEATURE_FLAGS_EVENT);
+            },
+            Err(e) => warn!("Failed to send '{}' to plugin '{}': {}", FEATURE_FLAGS_EVENT, plugin_id, e),
+        }
+    }
+    
+    /// Set the number of consecutive load failures after which a plugin is quarantined
+    pub fn set_quarantine_threshold(&self, threshold: u32) {
+        *lock(&self.quarantine_threshold) = threshold.max(1);
+    }
+    
+    /// Get the number of consecutive load failures of a plugin
+    pub fn load_failure_count(&self, plugin_id: &str) -> u32 {
+        lock(&self.load_failures).get(plugin_id).copied().unwrap_or(0)
+    }
+    
+    /// Count a failed load, quarantining the plugin once the threshold is reached
+    ///
+    /// Returns the error so callers can pass it on.
+    fn record_load_failure(&self, plugin_id: &str, error: PluginError) -> PluginError {
+        let failures = {
+            let mut load_failures = lock(&self.load_failures);
+            let failures = load_failures.entry(plugin_id.to_owned()).or_insert(0);
+            *failures += 1;
+            *failures
+        };
+        
+        if failures < *lock(&self.quarantine_threshold) {
+            return error;
+        }
+        
+        {
+            let mut registry = write_registry(&self.registry);
+            if let Some(plugin) = registry.plugins.get_mut(plugin_id) {
+                plugin.status = PluginStatus::Quarantined(error.to_string());
+            }
+        }
+        
+        if let Err(e) = self.save_registry(plugin_id) {
+            warn!("Failed to save registry after quarantining plugin '{}': {}", plugin_id, e);
+        }
+        
+        warn!("Plugin '{}' quarantined after {} consecutive load failures: {}", plugin_id, failures, error);
+        self.events.emit("plugin-quarantined", &serde_json::json!({
+            "plugin_id": plugin_id,
+            "failures": failures,
+            "error": error.to_string(),
+        }));
+        
+        error
+    }
+    
+    /// Take a plugin out of quarantine and try to enable it again
+    pub async fn retry_quarantined_plugin(&self, plugin_id: &str) -> Result<(), PluginError> {
+        {
+            let mut registry = write_registry(&self.registry);
+            let plugin = registry.plugins.get_mut(plugin_id)
+                .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?;
+            
+            if !matches!(plugin.status, PluginStatus::Quarantined(_)) {
+                return Err(PluginError::InvalidState(format!("Plugin '{}' is not quarantined", plugin_id)));
+            }
+            
+            plugin.status = PluginStatus::Disabled;
+        }
+        
+        lock(&self.load_failures).remove(plugin_id);
+        self.save_registry(plugin_id)?;
+        
+        info!("Retrying quarantined plugin '{}'", plugin_id);
+        
+        self.enable_plugin(plugin_id).await
+    }
+    
     /// Disable a plugin
     pub async fn disable_plugin(&self, plugin_id: &str) -> Result<(), PluginError> {
         // Get plugin info
         let plugin_info = {
-            let registry = self.registry.lock().unwrap();
+            let registry = read_registry(&self.registry);
             registry.plugins.get(plugin_id).cloned().ok_or_else(|| {
                 PluginError::NotFound(plugin_id.to_owned())
             })?
         };
         
-        // Check if already disabled
-        if plugin_info.status == PluginStatus::Disabled {
+        // Check if already disabled and unloaded, forgetting any pending restore
+        if plugin_info.status == PluginStatus::Disabled && !self.plugin_host.read().await.has_plugin(plugin_id) {
+            if plugin_info.desired_state == DesiredState::Enabled {
+                if let Some(plugin) = write_registry(&self.registry).plugins.get_mut(plugin_id) {
+                    plugin.desired_state = DesiredState::Disabled;
+                }
+                self.save_registry(plugin_id)?;
+            }
             return Ok(());
         }
         
@@ -449,51 +1261,338 @@ impl PluginManager {
         
         // Update status
         {
-            let mut registry = self.registry.lock().unwrap();
+            let mut registry = write_registry(&self.registry);
             if let Some(plugin) = registry.plugins.get_mut(plugin_id) {
                 plugin.status = PluginStatus::Disabled;
+                plugin.desired_state = DesiredState::Disabled;
             }
         }
         
         // Save registry
-        self.save_registry()?;
+        self.save_registry(plugin_id)?;
         
         info!("Plugin '{}' disabled successfully", plugin_id);
         
         Ok(())
     }
     
+    /// Pause an enabled plugin without unloading it
+    ///
+    /// The plugin is sent a `pause_requested` event and receives no other events
+    /// until it is resumed; its state stays in memory. Plugins run inside the host
+    /// process, so threads a plugin started itself keep running unless the plugin
+    /// stops them when asked to pause.
+    pub async fn pause_plugin(&self, plugin_id: &str) -> Result<(), PluginError> {
+        self.change_pause_state(plugin_id, PluginStatus::Enabled, PluginStatus::Paused, PAUSE_EVENT).await
+    }
+    
+    /// Resume a paused plugin
+    ///
+    /// The plugin is sent a `resume_requested` event and receives events again.
+    pub async fn resume_plugin(&self, plugin_id: &str) -> Result<(), PluginError> {
+        self.change_pause_state(plugin_id, PluginStatus::Paused, PluginStatus::Enabled, RESUME_EVENT).await
+    }
+    
+    /// Ask the user to grant a running plugin an additional permission
+    ///
+    /// Denied when no permission prompt is available. An approved grant is saved
+    /// and takes effect right away, so a plugin granted storage can use its store
+    /// without a restart. Emits `plugin-permissions-changed` with the updated grants.
+    pub async fn request_additional_permission(
+        &self,
+        plugin_id: &str,
+        permission: Permission,
+    ) -> Result<Vec<Permission>, PluginError> {
+        let plugin_info = self.get_plugin(plugin_id)
+            .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?;
+        
+        if !matches!(plugin_info.status, PluginStatus::Enabled | PluginStatus::Paused) {
+            return Err(PluginError::InvalidState(format!(
+                "Plugin '{}' must be running to request permissions", plugin_id
+            )));
+        }
+        
+        self.permission_system.validate_permissions(std::slice::from_ref(&permission))
+            .map_err(|e| PermissionError::Denied(e.to_string()))?;
+        
+        let granted = self.permission_system.request_additional_permission(
+            plugin_id,
+            &plugin_info.name,
+            permission,
+        ).await?;
+        
+        // The running plugin gets its store as soon as storage is granted
+        if granted.contains(&Permission::Storage) {
+            let plugin_host = self.plugin_host.read().await;
+            if plugin_host.has_plugin(plugin_id) {
+                let storage = KvStore::open(&self.plugin_data_dir(plugin_id), KvLimits::default())?;
+                plugin_host.attach_storage(plugin_id, Arc::new(storage))?;
+            }
+        }
+        
+        self.events.emit("plugin-permissions-changed", &serde_json::json!({
+            "plugin_id": plugin_id,
+            "permissions": granted,
+        }));
+        
+        info!("Plugin '{}' was granted an additional permission", plugin_id);
+        
+        Ok(granted)
+    }
+    
+    /// Get the permissions granted to a plugin
+    pub fn get_granted_permissions(&self, plugin_id: &str) -> Result<Vec<Permission>, PluginError> {
+        if self.get_plugin(plugin_id).is_none() {
+            return Err(PluginError::NotFound(plugin_id.to_owned()));
+        }
+        
+        Ok(self.permission_system.get_granted_permissions(plugin_id))
+    }
+    
+    /// Revoke one category of a plugin's permissions, or one scope within it
+    ///
+    /// Takes effect for a running plugin right away where the host enforces the
+    /// permission. Emits `plugin-permissions-changed` with the remaining grants.
+    pub async fn revoke_specific_permission(
+        &self,
+        plugin_id: &str,
+        category: PermissionCategory,
+        scope: Option<&str>,
+    ) -> Result<Vec<Permission>, PluginError> {
+        if self.get_plugin(plugin_id).is_none() {
+            return Err(PluginError::NotFound(plugin_id.to_owned()));
+        }
+        
+        let remaining = self.permission_system.revoke_specific(plugin_id, category, scope)?;
+        
+        // The host hands out storage only while the permission is held
+        if !remaining.contains(&Permission::Storage) {
+            let plugin_host = self.plugin_host.read().await;
+            if plugin_host.has_plugin(plugin_id) {
+                plugin_host.detach_storage(plugin_id)?;
+            }
+        }
+        
+        self.events.emit("plugin-permissions-changed", &serde_json::json!({
+            "plugin_id": plugin_id,
+            "permissions": remaining,
+        }));
+        
+        info!("Revoked {:?} permission of plugin '{}'", category, plugin_id);
+        
+        Ok(remaining)
+    }
+    
+    /// Move a loaded plugin between the enabled and paused states
+    async fn change_pause_state(
+        &self,
+        plugin_id: &str,
+        from: PluginStatus,
+        to: PluginStatus,
+        signal: &str,
+    ) -> Result<(), PluginError> {
+        let plugin_info = self.get_plugin(plugin_id)
+            .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?;
+        
+        if plugin_info.status != from {
+            return Err(PluginError::InvalidState(format!(
+                "Plugin '{}' is {:?}, expected {:?}", plugin_id, plugin_info.status, from
+            )));
+        }
+        
+        // Signal the plugin, releasing the host lock before waiting on the result
+        let pending = self.plugin_host.read().await.dispatch_event(plugin_id, signal, "{}");
+        match pending {
+            Ok(pending) => {
+                if let Err(e) = pending.await {
+                    warn!("Plugin '{}' failed to handle '{}': {}", plugin_id, signal, e);
+                }
+            },
+            Err(PluginHostError::InvalidEventName(_)) => {
+                debug!("Plugin '{}' does not handle '{}'", plugin_id, signal);
+            },
+            Err(e) => warn!("Failed to send '{}' to plugin '{}': {}", signal, plugin_id, e),
+        }
+        
+        // Update status
+        {
+            let mut registry = write_registry(&self.registry);
+            if let Some(plugin) = registry.plugins.get_mut(plugin_id) {
+                plugin.status = to.clone();
+            }
+        }
+        
+        // Save registry
+        self.save_registry(plugin_id)?;
+        
+        self.events.emit("plugin-status-changed", &serde_json::json!({
+            "plugin_id": plugin_id,
+            "status": to,
+        }));
+        
+        info!("Plugin '{}' is now {:?}", plugin_id, to);
+        
+        Ok(())
+    }
+    
+    /// Enable every plugin that was enabled when the application last ran
+    ///
+    /// Intended to run once at startup. Plugins that fail to load are listed in
+    /// the report and stay desired enabled, so the next restore tries again.
+    /// Emits a single `plugins-restored` event with the report.
+    pub async fn restore_enabled_plugins(&self) -> RestoreReport {
+        let mut pending: Vec<String> = self.get_all_plugins().into_iter()
+            .filter(|p| p.desired_state == DesiredState::Enabled)
+            .map(|p| p.id)
+            .collect();
+        pending.sort();
+        
+        let mut report = RestoreReport::default();
+        for plugin_id in pending {
+            // The registry may still show the status from before the restart
+            let loaded = self.plugin_host.read().await.has_plugin(&plugin_id);
+            if !loaded {
+                if let Some(plugin) = write_registry(&self.registry).plugins.get_mut(&plugin_id) {
+                    if matches!(plugin.status, PluginStatus::Enabled | PluginStatus::Paused) {
+                        plugin.status = PluginStatus::Disabled;
+                    }
+                }
+            }
+            
+            match self.enable_plugin(&plugin_id).await {
+                Ok(()) => report.restored.push(plugin_id),
+                Err(e) => {
+                    warn!("Failed to restore plugin '{}': {}", plugin_id, e);
+                    
+                    if let Err(e) = self.save_registry(&plugin_id) {
+                        error!("Failed to save plugin registry: {}", e);
+                    }
+                    
+                    report.failed.push(RestoreFailure { plugin_id, error: e.to_string() });
+                },
+            }
+        }
+        
+        self.events.emit("plugins-restored", &report);
+        report
+    }
+    
+    /// Disable every enabled plugin
+    ///
+    /// Plugins whose teardown fails are still marked disabled and listed in the
+    /// report. Emits a single `all-plugins-disabled` event with the report.
+    pub async fn disable_all(&self) -> DisableAllReport {
+        let report = self.disable_enabled_plugins().await;
+        self.events.emit("all-plugins-disabled", &report);
+        report
+    }
+    
+    /// Disable every enabled plugin and unload anything still running in the host
+    ///
+    /// Unlike `disable_all`, this also tears down plugins the host is running
+    /// without a matching enabled registry entry.
+    pub async fn emergency_shutdown(&self) -> DisableAllReport {
+        let mut report = self.disable_enabled_plugins().await;
+        
+        let mut plugin_host = self.plugin_host.write().await;
+        for plugin_id in plugin_host.loaded_plugins() {
+            warn!("Tearing down orphaned plugin '{}'", plugin_id);
+            
+            match plugin_host.teardown_plugin(&plugin_id) {
+                Ok(()) => report.disabled.push(plugin_id),
+                Err(e) => report.failed.push(DisableFailure { plugin_id, error: e.to_string() }),
+            }
+        }
+        drop(plugin_host);
+        
+        self.events.emit("all-plugins-disabled", &report);
+        report
+    }
+    
+    /// Disable all running and failed plugins, forcing ones that fail to disable into the disabled state
+    ///
+    /// Covers every registered plugin the host still has loaded, whatever its status.
+    async fn disable_enabled_plugins(&self) -> DisableAllReport {
+        let loaded: HashSet<String> = self.plugin_host.read().await.loaded_plugins().into_iter().collect();
+        let mut enabled: Vec<String> = self.get_all_plugins().into_iter()
+            .filter(|p| {
+                matches!(p.status, PluginStatus::Enabled | PluginStatus::Paused | PluginStatus::Error(_))
+                    || loaded.contains(&p.id)
+            })
+            .map(|p| p.id)
+            .collect();
+        enabled.sort();
+        
+        let mut report = DisableAllReport::default();
+        for plugin_id in enabled {
+            match self.disable_plugin(&plugin_id).await {
+                Ok(()) => report.disabled.push(plugin_id),
+                Err(e) => {
+                    error!("Failed to disable plugin '{}': {}", plugin_id, e);
+                    
+                    // Teardown unloads the plugin from the host even when it fails
+                    {
+                        let mut registry = write_registry(&self.registry);
+                        if let Some(plugin) = registry.plugins.get_mut(&plugin_id) {
+                            plugin.status = PluginStatus::Disabled;
+                            plugin.desired_state = DesiredState::Disabled;
+                        }
+                    }
+                    
+                    if let Err(e) = self.save_registry(&plugin_id) {
+                        error!("Failed to save plugin registry: {}", e);
+                    }
+                    
+                    report.failed.push(DisableFailure { plugin_id, error: e.to_string() });
+                },
+            }
+        }
+        
+        report
+    }
+    
+    /// Check whether a plugin may still be loaded and must be disabled before its files change
+    async fn needs_teardown(&self, plugin_info: &PluginInfo) -> bool {
+        matches!(plugin_info.status, PluginStatus::Enabled | PluginStatus::Paused | PluginStatus::Error(_))
+            || self.plugin_host.read().await.has_plugin(&plugin_info.id)
+    }
+    
     /// Uninstall a plugin
     pub async fn uninstall_plugin(&self, plugin_id: &str) -> Result<(), PluginError> {
         // Get plugin info
         let plugin_info = {
-            let registry = self.registry.lock().unwrap();
+            let registry = read_registry(&self.registry);
             registry.plugins.get(plugin_id).cloned().ok_or_else(|| {
                 PluginError::NotFound(plugin_id.to_owned())
             })?
         };
         
-        // Disable the plugin if it's enabled
-        if plugin_info.status == PluginStatus::Enabled {
+        // Disable the plugin if it's running or failed
+        if self.needs_teardown(&plugin_info).await {
             self.disable_plugin(plugin_id).await?;
         }
         
-        // Remove the plugin files
-        if plugin_info.install_path.exists() {
+        // Remove the plugin files, leaving the source directory of development installs alone
+        if !plugin_info.dev && plugin_info.install_path.exists() {
             fs::remove_dir_all(&plugin_info.install_path)?;
         }
         
         // Remove from registry
         {
-            let mut registry = self.registry.lock().unwrap();
+            let mut registry = write_registry(&self.registry);
             registry.plugins.remove(plugin_id);
         }
         
         // Revoke permissions
         self.permission_system.revoke_permissions(plugin_id)?;
         
+        // Forget integrity records
+        if let Err(e) = self.integrity_monitor.remove_plugin(plugin_id) {
+            warn!("Failed to remove integrity records for plugin '{}': {}", plugin_id, e);
+        }
+        
         // Save registry
-        self.save_registry()?;
+        self.save_registry(plugin_id)?;
         
         info!("Plugin '{}' uninstalled successfully", plugin_id);
         
@@ -502,19 +1601,96 @@ impl PluginManager {
     
     /// Get all installed plugins
     pub fn get_all_plugins(&self) -> Vec<PluginInfo> {
-        let registry = self.registry.lock().unwrap();
+        let registry = read_registry(&self.registry);
         registry.plugins.values().cloned().collect()
     }
     
     /// Get a specific plugin by ID
     pub fn get_plugin(&self, plugin_id: &str) -> Option<PluginInfo> {
-        let registry = self.registry.lock().unwrap();
+        let registry = read_registry(&self.registry);
         registry.plugins.get(plugin_id).cloned()
     }
     
+    /// Export a diagnostics bundle for attaching to a bug report
+    ///
+    /// Writes a zip archive with the host version, the plugin registry, granted
+    /// permissions, and a status file per plugin under `plugins/`. Secrets and
+    /// file paths outside the application directory are redacted.
+    pub async fn export_diagnostics(&self, path: &Path) -> Result<(), PluginError> {
+        let plugins = self.get_all_plugins();
+        let loaded_plugins = self.plugin_host.read().await.loaded_plugins();
+        
+        let mut bundle = DiagnosticsBundle::new();
+        bundle.add_section("host.json", serde_json::json!({
+            "host_version": HOST_VERSION,
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "exported_at": Utc::now(),
+            "event_queue": self.event_queue_stats(),
+        }));
+        bundle.add_section("registry.json", serde_json::to_value(&plugins).unwrap_or_default());
+        
+        let permissions: serde_json::Map<String, serde_json::Value> = plugins.iter()
+            .map(|plugin| {
+                let granted = self.permission_system.get_granted_permissions(&plugin.id);
+                (plugin.id.clone(), serde_json::to_value(granted).unwrap_or_default())
+            })
+            .collect();
+        bundle.add_section("permissions.json", serde_json::Value::Object(permissions));
+        
+        for plugin in &plugins {
+            let integrity = match self.integrity_monitor.verify_plugin(&plugin.id) {
+                Ok(report) => serde_json::to_value(report).unwrap_or_default(),
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
+            };
+            
+            bundle.add_section(format!("plugins/{}.json", plugin.id), serde_json::json!({
+                "status": plugin.status,
+                "loaded": loaded_plugins.contains(&plugin.id),
+                "resource_limits": plugin.resource_limits,
+                "installed_size_bytes": plugin.installed_size_bytes,
+                "file_count": plugin.file_count,
+                "data_dir_usage_bytes": self.quota_enforcer.get_usage(&plugin.id),
+                "integrity": integrity,
+            }));
+        }
+        
+        // Paths next to the plugins directory, such as the registry, are kept
+        let app_dir = self.plugins_dir.parent().unwrap_or(&self.plugins_dir);
+        bundle.redact(&DiagnosticsRedactor::new(app_dir));
+        bundle.write(path)?;
+        
+        info!("Exported diagnostics for {} plugins to {}", plugins.len(), path.display());
+        
+        Ok(())
+    }
+    
+    /// Recompute the installed size and file count of a plugin
+    pub fn recompute_size(&self, plugin_id: &str) -> Result<PluginInfo, PluginError> {
+        let install_path = self.get_plugin(plugin_id)
+            .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?
+            .install_path;
+        
+        let (installed_size_bytes, file_count) = dir_usage(&install_path);
+        
+        let plugin_info = {
+            let mut registry = write_registry(&self.registry);
+            let plugin = registry.plugins.get_mut(plugin_id)
+                .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?;
+            
+            plugin.installed_size_bytes = installed_size_bytes;
+            plugin.file_count = file_count;
+            plugin.clone()
+        };
+        
+        self.save_registry(plugin_id)?;
+        
+        Ok(plugin_info)
+    }
+    
     /// Get all enabled plugins
     pub fn get_enabled_plugins(&self) -> Vec<PluginInfo> {
-        let registry = self.registry.lock().unwrap();
+        let registry = read_registry(&self.registry);
         registry.plugins.values()
             .filter(|p| p.status == PluginStatus::Enabled)
             .cloned()
@@ -523,13 +1699,79 @@ impl PluginManager {
     
     /// Get all disabled plugins
     pub fn get_disabled_plugins(&self) -> Vec<PluginInfo> {
-        let registry = self.registry.lock().unwrap();
+        let registry = read_registry(&self.registry);
         registry.plugins.values()
             .filter(|p| p.status == PluginStatus::Disabled)
             .cloned()
             .collect()
     }
     
+    /// Check whether a compatible update is available for a plugin
+    ///
+    /// Queries the update source without downloading any package. Versions that
+    /// require a newer host or an unsupported API version are skipped.
+    pub async fn check_update(&self, plugin_id: &str) -> Result<Option<UpdateInfo>, PluginUpdateError> {
+        // Get plugin info
+        let plugin_info = self.get_plugin(plugin_id).ok_or_else(|| {
+            PluginUpdateError::NotFound(plugin_id.to_owned())
+        })?;
+        
+        let source = lock(&self.update_source).clone().ok_or_else(|| {
+            PluginUpdateError::DownloadFailed("No update source configured".to_owned())
+        })?;
+        
+        let current_version = semver::Version::parse(&plugin_info.version).map_err(|e| {
+            PluginUpdateError::Other(format!("Invalid installed version '{}': {}", plugin_info.version, e))
+        })?;
+        let host_version = semver::Version::parse(HOST_VERSION)
+            .map_err(|e| PluginUpdateError::Other(format!("Invalid host version: {}", e)))?;
+        
+        let mut latest: Option<(semver::Version, AvailableVersion)> = None;
+        for candidate in source.available_versions(plugin_id, &plugin_info.name)? {
+            let version = match semver::Version::parse(&candidate.version) {
+                Ok(version) => version,
+                Err(e) => {
+                    warn!("Skipping invalid version '{}' for plugin '{}': {}", candidate.version, plugin_id, e);
+                    continue;
+                },
+            };
+            
+            if version <= current_version || !CompatibilityReport::check(&candidate.api_version).is_compatible() {
+                continue;
+            }
+            
+            if let Some(min_host_version) = &candidate.min_host_version {
+                match semver::Version::parse(min_host_version) {
+                    Ok(min_host_version) if min_host_version <= host_version => {},
+                    _ => continue,
+                }
+            }
+            
+            let is_newer = match &latest {
+                Some((latest_version, _)) => version > *latest_version,
+                None => true,
+            };
+            if is_newer {
+                latest = Some((version, candidate));
+            }
+        }
+        
+        Ok(latest.map(|(_, candidate)| {
+            let new_permissions = candidate.permissions.iter()
+                .filter(|p| !plugin_info.permissions.contains(p))
+                .cloned()
+                .collect();
+            
+            UpdateInfo {
+                plugin_id: plugin_id.to_owned(),
+                current_version: plugin_info.version.clone(),
+                version: candidate.version,
+                changelog: candidate.changelog,
+                new_permissions,
+            }
+        }))
+    }
+    
     /// Update a plugin
     pub async fn update_plugin(
         &self,
@@ -538,12 +1780,19 @@ impl PluginManager {
     ) -> Result<PluginInfo, PluginUpdateError> {
         // Get plugin info
         let plugin_info = {
-            let registry = self.registry.lock().unwrap();
+            let registry = read_registry(&self.registry);
             registry.plugins.get(plugin_id).cloned().ok_or_else(|| {
                 PluginUpdateError::NotFound(plugin_id.to_owned())
             })?
         };
         
+        // Development installs pick up changes from their source directory directly
+        if plugin_info.dev {
+            return Err(PluginUpdateError::Other(
+                format!("Development install cannot be updated, reinstall it instead: {}", plugin_id)
+            ));
+        }
+        
         // Use provided source or try to get from original install
         let package_path = match source {
             Some(PluginSource::File(path)) => path,
@@ -552,6 +1801,16 @@ impl PluginManager {
                     "URL and Store updates not yet implemented".to_owned()
                 ));
             },
+            Some(PluginSource::Directory(_)) => {
+                return Err(PluginUpdateError::DownloadFailed(
+                    "Directory sources are only supported for development installs".to_owned()
+                ));
+            },
+            Some(PluginSource::Bytes(_)) => {
+                return Err(PluginUpdateError::DownloadFailed(
+                    "In-memory packages are only supported for installation".to_owned()
+                ));
+            },
             None => {
                 return Err(PluginUpdateError::DownloadFailed(
                     "Automatic update source detection not yet implemented".to_owned()
@@ -570,9 +1829,30 @@ impl PluginManager {
         // Validate permissions
         self.permission_system.validate_permissions(&metadata.manifest.permissions)?;
         
-        // Disable the plugin if it's enabled
-        let was_enabled = plugin_info.status == PluginStatus::Enabled;
-        if was_enabled {
+        // Validate requested resource limits
+        self.check_resource_limits(&metadata.manifest)
+            .map_err(PluginUpdateError::Other)?;
+        
+        // Let the UI present the manifest changes before they are applied
+        match read_manifest(&plugin_info.install_path) {
+            Ok(old_manifest) => {
+                let diff = ManifestDiff::compute(&old_manifest, &metadata.manifest);
+                if diff.has_security_implications() {
+                    warn!("Update of plugin '{}' requests new system or file write access", plugin_id);
+                }
+                
+                self.events.emit("plugin-update-manifest-diff", &serde_json::json!({
+                    "plugin_id": plugin_id,
+                    "diff": diff,
+                    "has_security_implications": diff.has_security_implications(),
+                }));
+            },
+            Err(e) => warn!("Failed to read installed manifest of plugin '{}': {}", plugin_id, e),
+        }
+        
+        // Disable the plugin if it's running or failed
+        let was_enabled = matches!(plugin_info.status, PluginStatus::Enabled | PluginStatus::Paused);
+        if self.needs_teardown(&plugin_info).await {
             self.disable_plugin(plugin_id).await
                 .map_err(|e| PluginUpdateError::Other(format!("Failed to disable plugin: {}", e)))?;
         }
@@ -591,9 +1871,16 @@ impl PluginManager {
         // Copy files from extraction directory to installation directory
         copy_dir_all(&metadata.install_path, &plugin_info.install_path)?;
         
+        // Record file hashes of the new version
+        if let Err(e) = self.integrity_monitor.record_plugin(plugin_id, &plugin_info.install_path) {
+            error!("Failed to record integrity hashes for plugin '{}': {}", plugin_id, e);
+        }
+        
+        let (installed_size_bytes, file_count) = dir_usage(&plugin_info.install_path);
+        
         // Update registry
         let updated_plugin_info = {
-            let mut registry = self.registry.lock().unwrap();
+            let mut registry = write_registry(&self.registry);
             let plugin = registry.plugins.get_mut(plugin_id).ok_or_else(|| {
                 PluginUpdateError::Other(format!("Plugin disappeared from registry: {}", plugin_id))
             })?;
@@ -602,14 +1889,17 @@ impl PluginManager {
             plugin.description = metadata.manifest.description.clone();
             plugin.homepage = metadata.manifest.homepage.clone();
             plugin.permissions = metadata.manifest.permissions.clone();
+            plugin.provides_capabilities = metadata.manifest.provides_capabilities.clone();
             plugin.status = PluginStatus::Disabled;
             plugin.updated_at = Some(Utc::now());
+            plugin.installed_size_bytes = installed_size_bytes;
+            plugin.file_count = file_count;
             
             plugin.clone()
         };
         
         // Save registry
-        self.save_registry()
+        self.save_registry(plugin_id)
             .map_err(|e| PluginUpdateError::Other(format!("Failed to save registry: {}", e)))?;
         
         // Re-enable the plugin if it was enabled before
@@ -633,29 +1923,278 @@ impl PluginManager {
         &self,
         plugin_id: &str,
         event_name: &str,
-        event_data: &str,
+        event: impl Into<EventEnvelope>,
     ) -> Result<i32, PluginError> {
-        // Check if plugin exists
+        let event = event.into();
+        
+        // Check if plugin exists and is accepting events
         {
-            let registry = self.registry.lock().unwrap();
-            if !registry.plugins.contains_key(plugin_id) {
-                return Err(PluginError::NotFound(plugin_id.to_owned()));
+            let registry = read_registry(&self.registry);
+            match registry.plugins.get(plugin_id) {
+                None => return Err(PluginError::NotFound(plugin_id.to_owned())),
+                Some(plugin) if plugin.status == PluginStatus::Paused => {
+                    return Err(PluginError::InvalidState(format!("Plugin is paused: {}", plugin_id)));
+                },
+                Some(plugin) if matches!(plugin.status, PluginStatus::Error(_)) => {
+                    return Err(PluginError::InvalidState(format!("Plugin has failed: {}", plugin_id)));
+                },
+                Some(_) => {},
             }
         }
         
-        // Check if plugin is enabled
-        let plugin_host = self.plugin_host.read().await;
-        if !plugin_host.has_plugin(plugin_id) {
-            return Err(PluginError::InvalidState(
-                format!("Plugin is not enabled: {}", plugin_id)
-            ));
-        }
+        // Dispatch the event, releasing the host lock before waiting on the result
+        let pending = {
+            // Check if plugin is enabled
+            let plugin_host = self.plugin_host.read().await;
+            if !plugin_host.has_plugin(plugin_id) {
+                return Err(PluginError::InvalidState(
+                    format!("Plugin is not enabled: {}", plugin_id)
+                ));
+            }
+            
+            plugin_host.dispatch_event(plugin_id, event_name, event)?
+        };
         
-        // Trigger the event
-        let result = plugin_host.trigger_event(plugin_id, event_name, event_data)?;
+        let result = pending.await?;
         
         Ok(result)
     }
+    
+    /// Execute a plugin command with JSON arguments and return its result as JSON
+    ///
+    /// The command is delivered as an event named after it, with the serialized
+    /// arguments as payload. Only enabled plugins granted the `Command` permission
+    /// accept commands, and each plugin is subject to the command rate limit.
+    /// The result is the JSON the plugin reported through `set_result`, or null
+    /// if it reported none; a nonzero status code fails the command. A plugin
+    /// whose commands keep timing out is marked as errored, unloaded, and
+    /// `plugin-unhealthy` is emitted.
+    pub async fn execute_plugin_command(
+        &self,
+        plugin_id: &str,
+        command: &str,
+        args: &serde_json::Value,
+    ) -> Result<serde_json::Value, PluginError> {
+        let plugin_info = self.get_plugin(plugin_id)
+            .ok_or_else(|| PluginError::NotFound(plugin_id.to_owned()))?;
+        
+        if plugin_info.status != PluginStatus::Enabled {
+            return Err(PluginError::InvalidState(format!("Plugin is not enabled: {}", plugin_id)));
+        }
+        
+        if !self.permission_system.get_granted_permissions(plugin_id).contains(&Permission::Command) {
+            return Err(PluginError::Permission(PermissionError::Denied(
+                format!("Plugin {} does not accept commands", plugin_id)
+            )));
+        }
+        
+        let rate_limiter = lock(&self.command_rate_limiter).clone();
+        if !rate_limiter.try_acquire(plugin_id) {
+            return Err(PluginError::RateLimited(plugin_id.to_owned()));
+        }
+        
+        // Dispatch the command, releasing the host lock before waiting on the result
+        let mut timed_event = {
+            let plugin_host = self.plugin_host.read().await;
+            if !plugin_host.has_plugin(plugin_id) {
+                return Err(PluginError::InvalidState(format!("Plugin is not enabled: {}", plugin_id)));
+            }
+            
+            plugin_host.dispatch_command(plugin_id, command, args.to_string())?
+        };
+        
+        // The host enforces the command timeout
+        if let Some(output) = timed_event.wait().await {
+            self.plugin_host.read().await.record_completion(plugin_id);
+            let output = output?;
+            if output.code != 0 {
+                return Err(PluginError::CommandFailed {
+                    plugin_id: plugin_id.to_owned(),
+                    command: command.to_owned(),
+                    code: output.code,
+                });
+            }
+            return Ok(output.result.unwrap_or(serde_json::Value::Null));
+        }
+        
+        let unhealthy_timeouts = {
+            let plugin_host = self.plugin_host.read().await;
+            let timeouts = plugin_host.record_timeout(plugin_id, command);
+            (!plugin_host.is_plugin_healthy(plugin_id)).then_some(timeouts)
+        };
+        
+        let error = timed_event.finish_timed_out().await;
+        if let Some(consecutive_timeouts) = unhealthy_timeouts {
+            self.mark_unhealthy(plugin_id, consecutive_timeouts).await;
+        }
+        
+        Err(error.into())
+    }
+    
+    /// Mark a plugin whose commands keep timing out as errored and unload it
+    async fn mark_unhealthy(&self, plugin_id: &str, consecutive_timeouts: u32) {
+        {
+            let mut registry = write_registry(&self.registry);
+            if let Some(plugin) = registry.plugins.get_mut(plugin_id) {
+                plugin.status = PluginStatus::Error(
+                    format!("unhealthy: {} consecutive command timeouts", consecutive_timeouts)
+                );
+            }
+        }
+        
+        if let Err(e) = self.save_registry(plugin_id) {
+            error!("Failed to save plugin registry: {}", e);
+        }
+        
+        // A callback still blocked makes the teardown give up instead of waiting forever
+        let mut plugin_host = self.plugin_host.write().await;
+        if plugin_host.has_plugin(plugin_id) {
+            if let Err(e) = plugin_host.teardown_plugin(plugin_id) {
+                error!("Failed to tear down unhealthy plugin '{}': {}", plugin_id, e);
+            }
+        }
+        drop(plugin_host);
+        
+        self.events.emit("plugin-unhealthy", &serde_json::json!({
+            "plugin_id": plugin_id,
+            "consecutive_timeouts": consecutive_timeouts,
+        }));
+    }
+    
+    /// Set how many commands each plugin accepts within a time window
+    ///
+    /// Replaces the rate limiter, forgetting previously counted commands.
+    pub fn set_command_rate_limit(&self, max_calls: u32, window: Duration) {
+        *lock(&self.command_rate_limiter) = Arc::new(CommandRateLimiter::new(max_calls, window));
+    }
+    
+    /// Start recording the events triggered on plugins
+    ///
+    /// Discards any events recorded by a previous recording that was not stopped.
+    pub async fn start_event_recording(&self) {
+        self.plugin_host.read().await.recorder().start();
+    }
+    
+    /// Stop recording events and return the recorded events
+    pub async fn stop_event_recording(&self) -> ReplayLog {
+        self.plugin_host.read().await.recorder().stop()
+    }
+    
+    /// Set the callback thread model used for plugins enabled from now on
+    pub async fn set_callback_thread_model(&self, thread_model: CallbackThreadModel) {
+        self.plugin_host.write().await.set_thread_model(thread_model);
+    }
+}
+
+/// Mark a plugin whose callback panicked as errored and tell the UI
+fn mark_panicked_plugin(
+    registry: &StdRwLock<PluginRegistry>,
+    registry_store: &RegistryStore,
+    events: &EventSink,
+    plugin_panic: &PluginPanic,
+) {
+    {
+        let mut registry = write_registry(registry);
+        if let Some(plugin) = registry.plugins.get_mut(&plugin_panic.plugin_id) {
+            plugin.status = PluginStatus::Error(format!("panicked: {}", plugin_panic.message));
+        }
+        
+        if let Err(e) = registry_store.save_plugin(&registry, &plugin_panic.plugin_id) {
+            error!("Failed to save plugin registry: {}", e);
+        }
+    }
+    
+    events.emit("plugin-panicked", &serde_json::json!({
+        "plugin_id": plugin_panic.plugin_id,
+        "message": plugin_panic.message,
+    }));
+}
+
+/// Unload a plugin whose callback panicked
+///
+/// Runs on its own thread: the panic is reported from inside the dispatch, which
+/// may hold the host lock or run on the worker teardown waits for.
+fn teardown_panicked_plugin(plugin_host: Weak<RwLock<PluginHost>>, plugin_id: String) {
+    let spawned = thread::Builder::new()
+        .name(format!("plugin-{}-teardown", plugin_id))
+        .spawn(move || {
+            let plugin_host = match plugin_host.upgrade() {
+                Some(plugin_host) => plugin_host,
+                None => return,
+            };
+            
+            let mut plugin_host = plugin_host.blocking_write();
+            if plugin_host.has_plugin(&plugin_id) {
+                if let Err(e) = plugin_host.teardown_plugin(&plugin_id) {
+                    error!("Failed to tear down panicked plugin '{}': {}", plugin_id, e);
+                }
+            }
+        });
+    
+    if let Err(e) = spawned {
+        error!("Failed to start teardown of panicked plugin: {}", e);
+    }
+}
+
+/// Lock a mutex, recovering the data if a previous holder panicked
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Lock the registry for reading, recovering it if a writer panicked
+fn read_registry(registry: &StdRwLock<PluginRegistry>) -> RwLockReadGuard<'_, PluginRegistry> {
+    registry.read().unwrap_or_else(|poisoned| {
+        warn!("Plugin registry lock was poisoned, continuing with its current contents");
+        poisoned.into_inner()
+    })
+}
+
+/// Lock the registry for writing, recovering it if a writer panicked
+fn write_registry(registry: &StdRwLock<PluginRegistry>) -> RwLockWriteGuard<'_, PluginRegistry> {
+    registry.write().unwrap_or_else(|poisoned| {
+        warn!("Plugin registry lock was poisoned, continuing with its current contents");
+        poisoned.into_inner()
+    })
+}
+
+/// Read the manifest of an installed plugin
+fn read_manifest(install_path: &Path) -> Result<PluginManifest, PluginError> {
+    Ok(serde_json::from_slice(&fs::read(install_path.join("plugin.json"))?)?)
+}
+
+/// Mark installed plugins whose API version the host does not support as incompatible
+fn mark_incompatible_plugins(registry: &mut PluginRegistry) {
+    for plugin in registry.plugins.values_mut() {
+        let manifest = match read_manifest(&plugin.install_path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                warn!("Failed to read manifest of plugin '{}': {}", plugin.id, e);
+                continue;
+            },
+        };
+        
+        match PluginLoader::compatibility_report(&manifest).reason() {
+            Some(reason) => {
+                warn!("Plugin '{}' is incompatible: {}", plugin.id, reason);
+                plugin.status = PluginStatus::Incompatible(reason);
+            },
+            // A plugin made compatible again by a host upgrade starts disabled
+            None if matches!(plugin.status, PluginStatus::Incompatible(_)) => {
+                plugin.status = PluginStatus::Disabled;
+            },
+            None => {},
+        }
+    }
+}
+
+/// Get the total size in bytes and the number of files below a directory
+fn dir_usage(dir: &Path) -> (u64, u64) {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .fold((0, 0), |(size, count), metadata| (size + metadata.len(), count + 1))
 }
 
 /// Recursively copy a directory
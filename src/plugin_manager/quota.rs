@@ -0,0 +1,163 @@
+//! Plugin data directory quotas
+//!
+//! Measures the size of each plugin's data directory and, when a plugin goes
+//! over its quota, frees space by deleting its least recently modified files.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use log::warn;
+use serde::{Serialize, Deserialize};
+use walkdir::WalkDir;
+
+/// Usage of a plugin data directory relative to its quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuotaStatus {
+    /// The directory is within its quota
+    WithinQuota {
+        /// Bytes currently used
+        used_bytes: u64,
+        /// Quota in bytes
+        limit_bytes: u64,
+    },
+    
+    /// The directory is over its quota
+    Exceeded {
+        /// Bytes currently used
+        used_bytes: u64,
+        /// Quota in bytes
+        limit_bytes: u64,
+    },
+}
+
+impl QuotaStatus {
+    /// Check whether the quota is exceeded
+    pub fn is_exceeded(&self) -> bool {
+        matches!(self, QuotaStatus::Exceeded { .. })
+    }
+}
+
+/// Data directory usage of one plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginDataUsage {
+    /// ID of the plugin
+    pub plugin_id: String,
+    
+    /// Bytes stored in the plugin's data directory
+    pub used_bytes: u64,
+    
+    /// Data directory quota in bytes, if the plugin has one
+    pub limit_bytes: Option<u64>,
+}
+
+/// Action taken when a plugin exceeds its data directory quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum QuotaAction {
+    /// Only log the violation, leaving the `plugin-data-quota-exceeded` event to report it
+    #[default]
+    Warn,
+    
+    /// Refuse operations that would store more plugin data
+    Block,
+    
+    /// Delete the oldest files until the directory is within quota
+    Evict,
+}
+
+/// Measures and enforces plugin data directory quotas
+pub struct DataDirQuotaEnforcer {
+    /// Directory containing one data directory per plugin
+    data_root: PathBuf,
+}
+
+impl DataDirQuotaEnforcer {
+    /// Create an enforcer for the data directories under a root directory
+    pub fn new(data_root: PathBuf) -> Self {
+        Self { data_root }
+    }
+    
+    /// Get the data directory of a plugin
+    pub fn data_dir(&self, plugin_id: &str) -> PathBuf {
+        self.data_root.join(plugin_id)
+    }
+    
+    /// Get the number of bytes stored in a plugin's data directory
+    pub fn get_usage(&self, plugin_id: &str) -> u64 {
+        data_files(&self.data_dir(plugin_id))
+            .iter()
+            .map(|file| file.size)
+            .sum()
+    }
+    
+    /// Compare a plugin's data directory usage against a quota
+    pub fn check_quota(&self, plugin_id: &str, limit_bytes: u64) -> QuotaStatus {
+        let used_bytes = self.get_usage(plugin_id);
+        
+        if used_bytes > limit_bytes {
+            QuotaStatus::Exceeded { used_bytes, limit_bytes }
+        } else {
+            QuotaStatus::WithinQuota { used_bytes, limit_bytes }
+        }
+    }
+    
+    /// Delete the least recently modified files until the directory is within quota
+    ///
+    /// Returns the deleted files, oldest first.
+    pub fn evict_to_quota(&self, plugin_id: &str, limit_bytes: u64) -> io::Result<Vec<PathBuf>> {
+        let mut files = data_files(&self.data_dir(plugin_id));
+        let mut used_bytes: u64 = files.iter().map(|file| file.size).sum();
+        let mut evicted = Vec::new();
+        
+        files.sort_by(|a, b| a.modified.cmp(&b.modified).then_with(|| a.path.cmp(&b.path)));
+        
+        for file in files {
+            if used_bytes <= limit_bytes {
+                break;
+            }
+            
+            fs::remove_file(&file.path)?;
+            used_bytes -= file.size;
+            evicted.push(file.path);
+        }
+        
+        Ok(evicted)
+    }
+}
+
+/// A file inside a plugin data directory
+struct DataFile {
+    /// Path of the file
+    path: PathBuf,
+    /// Size in bytes
+    size: u64,
+    /// Last modification time
+    modified: SystemTime,
+}
+
+/// List every file below a data directory
+fn data_files(dir: &Path) -> Vec<DataFile> {
+    if !dir.exists() {
+        return Vec::new();
+    }
+    
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("Failed to read plugin data directory entry: {}", e);
+                None
+            },
+        })
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(DataFile {
+                path: entry.into_path(),
+                size: metadata.len(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            })
+        })
+        .collect()
+}
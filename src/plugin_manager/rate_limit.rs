@@ -0,0 +1,78 @@
+//! Plugin command rate limiting
+//!
+//! Limits how many commands the frontend may send to each plugin within a
+//! sliding time window.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::lock;
+
+/// Default number of commands a plugin accepts per window
+pub const DEFAULT_COMMAND_RATE_LIMIT: u32 = 100;
+
+/// Default length of the rate limiting window
+pub const DEFAULT_COMMAND_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Sliding window rate limiter keyed by plugin ID
+#[derive(Debug)]
+pub struct CommandRateLimiter {
+    /// Maximum number of commands per window
+    max_calls: u32,
+    
+    /// Length of the window
+    window: Duration,
+    
+    /// Times of the commands accepted within the window, per plugin
+    calls: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl CommandRateLimiter {
+    /// Create a limiter accepting `max_calls` commands per plugin within `window`
+    pub fn new(max_calls: u32, window: Duration) -> Self {
+        Self {
+            max_calls,
+            window,
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Get the maximum number of commands per window
+    pub fn max_calls(&self) -> u32 {
+        self.max_calls
+    }
+    
+    /// Get the length of the window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+    
+    /// Record a command for a plugin, returning false if it exceeds the limit
+    pub fn try_acquire(&self, plugin_id: &str) -> bool {
+        let now = Instant::now();
+        let mut calls = lock(&self.calls);
+        let plugin_calls = calls.entry(plugin_id.to_owned()).or_default();
+        
+        // Forget commands that left the window
+        while let Some(oldest) = plugin_calls.front() {
+            if now.duration_since(*oldest) < self.window {
+                break;
+            }
+            plugin_calls.pop_front();
+        }
+        
+        if plugin_calls.len() >= self.max_calls as usize {
+            return false;
+        }
+        
+        plugin_calls.push_back(now);
+        true
+    }
+}
+
+impl Default for CommandRateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMMAND_RATE_LIMIT, DEFAULT_COMMAND_RATE_WINDOW)
+    }
+}
@@ -0,0 +1,222 @@
+//! Plugin registry persistence
+//!
+//! Stores the plugin registry either as a single JSON file or as one JSON record
+//! per plugin plus an index, so that a change to one plugin only rewrites that
+//! plugin's record.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+
+use super::{DesiredState, PluginError, PluginInfo, PluginRegistry, PluginStatus};
+
+/// Name of the index file in a per-plugin registry directory
+const INDEX_FILE: &str = "index.json";
+
+/// On-disk layout of the plugin registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RegistryFormat {
+    /// A single JSON file rewritten on every change
+    #[default]
+    Monolithic,
+    
+    /// A directory holding one `<id>.json` record per plugin and an `index.json`
+    ///
+    /// The directory sits next to the registry path with a `.d` extension. An
+    /// existing monolithic registry is migrated on first load and kept with a
+    /// `.migrated` extension.
+    PerPlugin,
+}
+
+/// Reads and writes the plugin registry in the configured format
+pub(super) struct RegistryStore {
+    /// Path of the monolithic registry file
+    path: PathBuf,
+    
+    /// Registry layout
+    format: RegistryFormat,
+}
+
+impl RegistryStore {
+    /// Create a store for a registry path
+    pub(super) fn new(path: PathBuf, format: RegistryFormat) -> Self {
+        Self { path, format }
+    }
+    
+    /// Path of the monolithic registry file
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Directory holding per-plugin records
+    fn records_dir(&self) -> PathBuf {
+        self.path.with_extension("d")
+    }
+    
+    /// Load the registry, migrating a monolithic registry if needed
+    pub(super) fn load(&self) -> Result<PluginRegistry, PluginError> {
+        match self.format {
+            RegistryFormat::Monolithic => Ok(self.load_monolithic()),
+            RegistryFormat::PerPlugin if self.records_dir().join(INDEX_FILE).exists() => Ok(self.load_per_plugin()),
+            RegistryFormat::PerPlugin if self.path.exists() => {
+                let registry = self.load_monolithic();
+                self.save_all(&registry)?;
+                
+                let mut migrated = self.path.clone().into_os_string();
+                migrated.push(".migrated");
+                let migrated = PathBuf::from(migrated);
+                fs::rename(&self.path, &migrated)?;
+                info!("Migrated plugin registry to per-plugin records, previous file kept at {}", migrated.display());
+                
+                Ok(registry)
+            },
+            RegistryFormat::PerPlugin => Ok(PluginRegistry::default()),
+        }
+    }
+    
+    /// Write the whole registry
+    pub(super) fn save_all(&self, registry: &PluginRegistry) -> Result<(), PluginError> {
+        match self.format {
+            RegistryFormat::Monolithic => write_atomic(&self.path, &serde_json::to_string_pretty(registry)?),
+            RegistryFormat::PerPlugin => {
+                for plugin in registry.plugins.values() {
+                    self.write_record(plugin)?;
+                }
+                
+                self.write_index(registry)
+            },
+        }
+    }
+    
+    /// Persist the change to a single plugin
+    ///
+    /// Writes the plugin's record, or removes it when the plugin is no longer in
+    /// the registry. The monolithic format rewrites the whole registry.
+    pub(super) fn save_plugin(&self, registry: &PluginRegistry, plugin_id: &str) -> Result<(), PluginError> {
+        if self.format == RegistryFormat::Monolithic {
+            return self.save_all(registry);
+        }
+        
+        match registry.plugins.get(plugin_id) {
+            Some(plugin) => self.write_record(plugin)?,
+            None => {
+                let record = self.record_path(plugin_id);
+                if record.exists() {
+                    fs::remove_file(record)?;
+                }
+            },
+        }
+        
+        // Only rewrite the index when the set of plugins changed
+        if self.read_index() != registry.plugins.keys().cloned().collect::<BTreeSet<_>>() {
+            self.write_index(registry)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Path of a plugin's record
+    fn record_path(&self, plugin_id: &str) -> PathBuf {
+        self.records_dir().join(format!("{}.json", plugin_id))
+    }
+    
+    /// Write a plugin's record
+    fn write_record(&self, plugin: &PluginInfo) -> Result<(), PluginError> {
+        write_atomic(&self.record_path(&plugin.id), &serde_json::to_string_pretty(plugin)?)
+    }
+    
+    /// Write the index of plugin IDs
+    fn write_index(&self, registry: &PluginRegistry) -> Result<(), PluginError> {
+        let ids: BTreeSet<&String> = registry.plugins.keys().collect();
+        write_atomic(&self.records_dir().join(INDEX_FILE), &serde_json::to_string_pretty(&ids)?)
+    }
+    
+    /// Read the index of plugin IDs
+    fn read_index(&self) -> BTreeSet<String> {
+        fs::read_to_string(self.records_dir().join(INDEX_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Load a monolithic registry file
+    fn load_monolithic(&self) -> PluginRegistry {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(_) => return PluginRegistry::default(),
+        };
+        
+        let registry = serde_json::from_str::<StoredRegistry>(&contents).and_then(|stored| {
+            let plugins = stored.plugins.into_iter()
+                .map(|(plugin_id, record)| Ok((plugin_id, parse_record(record)?)))
+                .collect::<Result<_, serde_json::Error>>()?;
+            Ok(PluginRegistry { plugins })
+        });
+        
+        match registry {
+            Ok(reg) => reg,
+            Err(e) => {
+                warn!("Failed to parse plugin registry: {}", e);
+                PluginRegistry::default()
+            },
+        }
+    }
+    
+    /// Load per-plugin records listed in the index
+    fn load_per_plugin(&self) -> PluginRegistry {
+        let mut registry = PluginRegistry::default();
+        
+        for plugin_id in self.read_index() {
+            let record = fs::read_to_string(self.record_path(&plugin_id))
+                .map_err(PluginError::from)
+                .and_then(|contents| Ok(parse_record(serde_json::from_str(&contents)?)?));
+            
+            match record {
+                Ok(plugin) => {
+                    registry.plugins.insert(plugin_id, plugin);
+                },
+                Err(e) => warn!("Failed to load registry record for plugin '{}': {}", plugin_id, e),
+            }
+        }
+        
+        registry
+    }
+}
+
+/// Monolithic registry with its plugin records not yet parsed
+#[derive(Deserialize)]
+struct StoredRegistry {
+    /// Plugin records keyed by plugin ID
+    plugins: HashMap<String, serde_json::Value>,
+}
+
+/// Parse a plugin record
+///
+/// Records written before desired states were tracked only hold the status, so
+/// plugins they list as enabled or paused are taken to be wanted enabled.
+fn parse_record(record: serde_json::Value) -> Result<PluginInfo, serde_json::Error> {
+    let has_desired_state = record.get("desired_state").is_some();
+    let mut plugin: PluginInfo = serde_json::from_value(record)?;
+    
+    if !has_desired_state && matches!(plugin.status, PluginStatus::Enabled | PluginStatus::Paused) {
+        plugin.desired_state = DesiredState::Enabled;
+    }
+    
+    Ok(plugin)
+}
+
+/// Replace a file's contents without leaving it half-written
+fn write_atomic(path: &Path, contents: &str) -> Result<(), PluginError> {
+    // Create parent directories if they don't exist
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)?;
+    
+    Ok(())
+}
@@ -3,12 +3,32 @@
 //! Integrates the plugin system with the Tauri UI via commands and events.
 //! Provides the interface for the frontend to interact with the plugin system.
 
+use std::path::Path;
 use std::sync::Arc;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use tauri::{command, State, AppHandle, Runtime, Manager};
 
-use crate::plugin_manager::{PluginManager, PluginInfo, PluginStatus, PluginSource};
-use crate::permission_system::{Permission, PermissionSystem, PermissionPromptHandler, PermissionPromptResult, PermissionError};
+use crate::plugin_host::{EventEnvelope, ReplayLog};
+use crate::plugin_loader::{PackageInspection, PluginLoader};
+use crate::plugin_manager::{
+    DisableAllReport, PluginDataUsage, PluginManager, PluginInfo, PluginStatus, PluginSource, PluginEventEmitter,
+    UpdateInfo, OverflowPolicy, QueuedEventEmitter, DEFAULT_EVENT_QUEUE_CAPACITY, MAX_IN_MEMORY_PACKAGE_BYTES,
+};
+use crate::permission_system::{
+    Permission, PermissionCategory, PermissionSystem, PermissionPromptHandler, PermissionPromptQueue,
+    PermissionPromptResult, PermissionError, PermissionRiskLevel, PromptStyle, RiskRules, RiskScore,
+};
+
+pub mod notifications;
+
+pub use notifications::{
+    NotificationError, NotificationIcon, PluginNotificationManager, ToastAction, ToastActivationType,
+};
+
+use notifications::WindowsToastBackend;
 
 /// Plugin system state for Tauri
 pub struct PluginSystemState(pub Arc<PluginManager>);
@@ -78,6 +98,85 @@ pub struct PermissionDeniedEvent {
     pub permissions: Vec<String>,
 }
 
+/// A single permission in a permission request event
+#[derive(Clone, Serialize)]
+pub struct PermissionRequestItem {
+    /// Human-readable description of the permission
+    pub description: String,
+    
+    /// Risk level of the permission
+    pub risk: PermissionRiskLevel,
+    
+    /// Explanation and mitigations for the permission's risk
+    pub score: RiskScore,
+}
+
+/// Permission request event
+#[derive(Clone, Serialize)]
+pub struct PermissionRequestEvent {
+    /// ID of the plugin
+    pub plugin_id: String,
+    
+    /// Name of the plugin
+    pub plugin_name: String,
+    
+    /// Requested permissions
+    pub permissions: Vec<PermissionRequestItem>,
+    
+    /// Combined risk of the request
+    pub risk: PermissionRiskLevel,
+    
+    /// Suggested presentation style for the prompt
+    pub style: PromptStyle,
+}
+
+impl PermissionRequestEvent {
+    /// Create a permission request event for a set of permissions
+    pub fn new(
+        plugin_id: &str,
+        plugin_name: &str,
+        permissions: &[Permission],
+        risk_rules: &RiskRules,
+    ) -> Self {
+        let risk = PermissionRiskLevel::for_permissions_with(permissions, risk_rules);
+        
+        Self {
+            plugin_id: plugin_id.to_owned(),
+            plugin_name: plugin_name.to_owned(),
+            permissions: permissions.iter()
+                .map(|p| {
+                    let score = risk_rules.score(p);
+                    PermissionRequestItem {
+                        description: p.to_string(),
+                        risk: score.level,
+                        score,
+                    }
+                })
+                .collect(),
+            risk,
+            style: PromptStyle::from(risk),
+        }
+    }
+}
+
+/// Queued permission prompt event, answered with `resolve_permission_prompt`
+#[derive(Clone, Serialize)]
+pub struct PermissionPromptEvent {
+    /// ID to resolve the prompt with
+    pub request_id: u64,
+    
+    /// Details of the request
+    #[serde(flatten)]
+    pub request: PermissionRequestEvent,
+}
+
+/// Notification action event
+#[derive(Clone, Serialize)]
+pub struct NotificationActionEvent {
+    /// Label of the activated action
+    pub action: String,
+}
+
 /// Command result type
 type CommandResult<T> = Result<T, String>;
 
@@ -85,12 +184,24 @@ type CommandResult<T> = Result<T, String>;
 pub struct TauriPermissionPromptHandler<R: Runtime> {
     /// Tauri app handle
     app: AppHandle<R>,
+    
+    /// Rules deciding the risk of requested permissions
+    risk_rules: Arc<RiskRules>,
 }
 
 impl<R: Runtime> TauriPermissionPromptHandler<R> {
     /// Create a new Tauri permission prompt handler
     pub fn new(app: AppHandle<R>) -> Self {
-        Self { app }
+        Self {
+            app,
+            risk_rules: Arc::new(RiskRules::default()),
+        }
+    }
+    
+    /// Use custom risk rules for permission requests
+    pub fn with_risk_rules(mut self, risk_rules: Arc<RiskRules>) -> Self {
+        self.risk_rules = risk_rules;
+        self
     }
 }
 
@@ -98,7 +209,7 @@ impl<R: Runtime> PermissionPromptHandler for TauriPermissionPromptHandler<R> {
     fn prompt_for_permissions(
         &self,
         plugin_id: &str,
-        _plugin_name: &str,
+        plugin_name: &str,
         permissions: &[Permission],
     ) -> Result<PermissionPromptResult, PermissionError> {
         // Convert permissions to strings for display
@@ -106,6 +217,12 @@ impl<R: Runtime> PermissionPromptHandler for TauriPermissionPromptHandler<R> {
             .map(|p| p.to_string())
             .collect();
         
+        // Let the frontend render the request according to its risk
+        let _ = self.app.emit_all(
+            "plugin-permission-request",
+            PermissionRequestEvent::new(plugin_id, plugin_name, permissions, &self.risk_rules),
+        );
+        
         // In a real implementation, this would show a UI dialog
         // For now, we'll just automatically allow all permissions
         // This should be replaced with actual UI interaction
@@ -123,16 +240,46 @@ impl<R: Runtime> PermissionPromptHandler for TauriPermissionPromptHandler<R> {
     }
 }
 
+/// Event emitter forwarding plugin system events to the Tauri frontend
+pub struct TauriEventEmitter<R: Runtime> {
+    /// Tauri app handle
+    app: AppHandle<R>,
+}
+
+impl<R: Runtime> TauriEventEmitter<R> {
+    /// Create a new Tauri event emitter
+    pub fn new(app: AppHandle<R>) -> Self {
+        Self { app }
+    }
+}
+
+impl<R: Runtime> PluginEventEmitter for TauriEventEmitter<R> {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        if let Err(e) = self.app.emit_all(event, payload) {
+            log::warn!("Failed to emit '{}' event: {}", event, e);
+        }
+    }
+}
+
 /// Convert plugin status to string
 fn status_to_string(status: &PluginStatus) -> String {
     match status {
         PluginStatus::Enabled => "enabled".to_owned(),
         PluginStatus::Disabled => "disabled".to_owned(),
+        PluginStatus::Paused => "paused".to_owned(),
         PluginStatus::Error(_) => "error".to_owned(),
         PluginStatus::Incompatible(_) => "incompatible".to_owned(),
+        PluginStatus::Quarantined(_) => "quarantined".to_owned(),
     }
 }
 
+/// Command to preview a plugin package before installing it
+#[command]
+pub fn inspect_plugin_package(path: String) -> CommandResult<PackageInspection> {
+    PluginLoader::inspect_package(Path::new(&path))
+        .map_err(|e| format!("Failed to inspect plugin package: {}", e))
+}
+
 /// Command to install a plugin from a file
 #[command]
 pub async fn install_plugin_from_file(
@@ -153,6 +300,35 @@ pub async fn install_plugin_from_file(
     }
 }
 
+/// Command to install a plugin from base64-encoded package contents
+///
+/// Used for drag-and-drop and fetched blobs, where the webview has no file path.
+#[command]
+pub async fn install_plugin_from_bytes(
+    state: State<'_, PluginSystemState>,
+    data: String,
+) -> CommandResult<PluginInfo> {
+    // Reject oversized packages before decoding them
+    let encoded_limit = MAX_IN_MEMORY_PACKAGE_BYTES.div_ceil(3) * 4;
+    if data.len() > encoded_limit {
+        return Err(format!(
+            "Failed to install plugin: package exceeds the {} byte limit", MAX_IN_MEMORY_PACKAGE_BYTES
+        ));
+    }
+    
+    let package = BASE64.decode(data.trim())
+        .map_err(|e| format!("Failed to decode plugin package: {}", e))?;
+    
+    let manager = state.manager();
+    match manager.install_plugin(PluginSource::Bytes(package)).await {
+        Ok(plugin_info) => {
+            println!("Plugin installed: {}", plugin_info.name);
+            Ok(plugin_info)
+        },
+        Err(e) => Err(format!("Failed to install plugin: {}", e)),
+    }
+}
+
 /// Command to install a plugin from a URL
 #[command]
 pub async fn install_plugin_from_url(
@@ -234,6 +410,42 @@ pub async fn disable_plugin(
     }
 }
 
+/// Command to pause a plugin without unloading it
+#[command]
+pub async fn pause_plugin(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+) -> CommandResult<()> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.pause_plugin(&plugin_id).await
+        .map_err(|e| format!("Failed to pause plugin: {}", e))
+}
+
+/// Command to resume a paused plugin
+#[command]
+pub async fn resume_plugin(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+) -> CommandResult<()> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.resume_plugin(&plugin_id).await
+        .map_err(|e| format!("Failed to resume plugin: {}", e))
+}
+
+/// Command to retry enabling a quarantined plugin
+#[command]
+pub async fn retry_quarantined_plugin(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+) -> CommandResult<()> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.retry_quarantined_plugin(&plugin_id).await
+        .map_err(|e| format!("Failed to retry plugin: {}", e))
+}
+
 /// Command to uninstall a plugin
 #[command]
 pub async fn uninstall_plugin(
@@ -283,17 +495,215 @@ pub async fn update_plugin(
     }
 }
 
+/// Command to check whether a compatible update is available for a plugin
+#[command]
+pub async fn check_plugin_update(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+) -> CommandResult<Option<UpdateInfo>> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    match manager.check_update(&plugin_id).await {
+        Ok(update) => Ok(update),
+        Err(e) => Err(format!("Failed to check for plugin update: {}", e)),
+    }
+}
+
+/// Command to disable every plugin at once
+///
+/// Must be called with `confirm` set to `true`.
+#[command]
+pub async fn kill_switch(
+    state: State<'_, PluginSystemState>,
+    confirm: bool,
+) -> CommandResult<DisableAllReport> {
+    if !confirm {
+        return Err("Kill switch requires confirmation".to_owned());
+    }
+    
+    // Access manager through the accessor method
+    let manager = state.manager();
+    Ok(manager.emergency_shutdown().await)
+}
+
+/// Command to suppress notifications until a given time, or resume them with `None`
+#[command]
+pub fn set_notifications_do_not_disturb(
+    notifications: State<'_, Arc<PluginNotificationManager>>,
+    until: Option<DateTime<Utc>>,
+) -> CommandResult<()> {
+    notifications.set_do_not_disturb(until);
+    Ok(())
+}
+
+/// Command to score requested permissions for display
+#[command]
+pub fn get_permission_risk_scores(
+    risk_rules: State<'_, Arc<RiskRules>>,
+    permissions: Vec<Permission>,
+) -> CommandResult<Vec<RiskScore>> {
+    Ok(permissions.iter().map(|p| risk_rules.score(p)).collect())
+}
+
+/// Command to get the permissions granted to a plugin
+#[command]
+pub fn get_granted_permissions(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+) -> CommandResult<Vec<Permission>> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.get_granted_permissions(&plugin_id)
+        .map_err(|e| format!("Failed to get permissions: {}", e))
+}
+
+/// Command to revoke one permission category of a plugin, or one scope within it
+#[command]
+pub async fn revoke_specific_permission(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+    category: PermissionCategory,
+    scope: Option<String>,
+) -> CommandResult<Vec<Permission>> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.revoke_specific_permission(&plugin_id, category, scope.as_deref()).await
+        .map_err(|e| format!("Failed to revoke permission: {}", e))
+}
+
+/// Command to switch one of a plugin's feature flags on or off
+#[command]
+pub async fn set_plugin_feature(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+    flag: String,
+    value: bool,
+) -> CommandResult<()> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.set_plugin_feature(&plugin_id, &flag, value).await
+        .map_err(|e| format!("Failed to set plugin feature: {}", e))
+}
+
+/// Command to answer the permission prompt being shown
+#[command]
+pub fn resolve_permission_prompt<R: Runtime>(
+    app: AppHandle<R>,
+    prompt_queue: State<'_, Arc<PermissionPromptQueue>>,
+    request_id: u64,
+    approved: bool,
+) -> CommandResult<()> {
+    let prompt = prompt_queue.current_prompt()
+        .filter(|prompt| prompt.request_id == request_id)
+        .ok_or_else(|| format!("No pending permission prompt {}", request_id))?;
+    
+    let permission_strings: Vec<String> = prompt.permissions.iter()
+        .map(|p| p.to_string())
+        .collect();
+    
+    let result = if approved {
+        let _ = app.emit_all("plugin-permission-granted", PermissionGrantedEvent {
+            plugin_id: prompt.plugin_id,
+            permissions: permission_strings,
+        });
+        PermissionPromptResult::Allowed(prompt.permissions)
+    } else {
+        let _ = app.emit_all("plugin-permission-denied", PermissionDeniedEvent {
+            plugin_id: prompt.plugin_id,
+            permissions: permission_strings,
+        });
+        PermissionPromptResult::Denied(prompt.permissions)
+    };
+    
+    prompt_queue.resolve(request_id, result)
+        .map_err(|e| format!("Failed to resolve permission prompt: {}", e))
+}
+
+/// Command to get the data directory usage of every installed plugin
+#[command]
+pub fn get_all_data_usage(state: State<'_, PluginSystemState>) -> CommandResult<Vec<PluginDataUsage>> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    Ok(manager.get_all_data_usage())
+}
+
+/// Command to delete a plugin's oldest data files until at most `keep_bytes` remain
+#[command]
+pub fn trim_plugin_data(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+    keep_bytes: u64,
+) -> CommandResult<usize> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.trim_plugin_data(&plugin_id, keep_bytes)
+        .map(|deleted| deleted.len())
+        .map_err(|e| format!("Failed to trim plugin data: {}", e))
+}
+
+/// Command to execute a plugin command and return its JSON result
+///
+/// Only plugins granted the `Command` permission accept commands.
+#[command]
+pub async fn execute_plugin_command(
+    state: State<'_, PluginSystemState>,
+    plugin_id: String,
+    command: String,
+    args: serde_json::Value,
+) -> CommandResult<serde_json::Value> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.execute_plugin_command(&plugin_id, &command, &args).await
+        .map_err(|e| format!("Failed to execute plugin command: {}", e))
+}
+
+/// Command to start recording the events triggered on plugins
+#[command]
+pub async fn start_recording(state: State<'_, PluginSystemState>) -> CommandResult<()> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    manager.start_event_recording().await;
+    Ok(())
+}
+
+/// Command to stop recording events, optionally saving the replay log to a file
+#[command]
+pub async fn stop_recording(
+    state: State<'_, PluginSystemState>,
+    path: Option<String>,
+) -> CommandResult<ReplayLog> {
+    // Access manager through the accessor method
+    let manager = state.manager();
+    let log = manager.stop_event_recording().await;
+    
+    if let Some(path) = path {
+        log.save(std::path::Path::new(&path))
+            .map_err(|e| format!("Failed to save replay log: {}", e))?;
+    }
+    
+    Ok(log)
+}
+
 /// Command to trigger a plugin event
+///
+/// Events sharing an `idempotency_key` are delivered only once within the
+/// host's deduplication window.
 #[command]
 pub async fn trigger_plugin_event(
     state: State<'_, PluginSystemState>,
     plugin_id: String,
     event_name: String,
     event_data: String,
+    idempotency_key: Option<String>,
 ) -> CommandResult<i32> {
+    let mut event = EventEnvelope::new(event_data);
+    if let Some(key) = idempotency_key {
+        event = event.with_idempotency_key(key);
+    }
+    
     // Access manager through the accessor method
     let manager = state.manager();
-    match manager.trigger_plugin_event(&plugin_id, &event_name, &event_data).await {
+    match manager.trigger_plugin_event(&plugin_id, &event_name, event).await {
         Ok(result) => Ok(result),
         Err(e) => Err(format!("Failed to trigger plugin event: {}", e)),
     }
@@ -304,6 +714,11 @@ pub fn register_commands<R: Runtime>(
     app: &mut tauri::App<R>,
     plugin_manager: Arc<PluginManager>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Forward plugin system events to the frontend without letting a slow frontend buffer them unboundedly
+    let emitter = QueuedEventEmitter::new(TauriEventEmitter::new(app.handle()), DEFAULT_EVENT_QUEUE_CAPACITY)
+        .with_policy("plugin-data-quota-exceeded", OverflowPolicy::Coalesce);
+    plugin_manager.set_queued_event_emitter(emitter);
+    
     // Create and register the plugin system state
     let plugin_system_state = PluginSystemState(plugin_manager);
     
@@ -315,18 +730,84 @@ pub fn register_commands<R: Runtime>(
 /// Setup a Tauri permission prompt handler
 pub fn setup_permission_handler<R: Runtime>(
     app: &mut tauri::App<R>,
-    _permission_system: Arc<PermissionSystem>,
+    permission_system: Arc<PermissionSystem>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let app_handle = app.handle().clone();
-    let _handler = TauriPermissionPromptHandler::new(app_handle);
+    let risk_rules = permission_system.risk_rules();
+    app.manage(risk_rules.clone());
     
-    // Note: In a real implementation, we'd need to clone and modify the permission system
-    // Since we're using an Arc, we'd need interior mutability or other mechanism
-    // For simplicity, we're just showing the concept here
+    // Show queued prompts one at a time; the frontend answers with `resolve_permission_prompt`
+    let app_handle = app.handle();
+    let prompt_queue = permission_system.prompt_queue();
+    prompt_queue.set_presenter(Box::new(move |prompt| {
+        let event = PermissionPromptEvent {
+            request_id: prompt.request_id,
+            request: PermissionRequestEvent::new(
+                &prompt.plugin_id,
+                &prompt.plugin_name,
+                &prompt.permissions,
+                &risk_rules,
+            ),
+        };
+        
+        if let Err(e) = app_handle.emit_all("plugin-permission-prompt", event) {
+            log::warn!("Failed to emit permission prompt: {}", e);
+        }
+    }));
+    app.manage(prompt_queue);
     
     Ok(())
 }
 
+/// Show toast notifications for plugin crash, update and resource events
+pub fn setup_notifications<R: Runtime>(
+    app: &mut tauri::App<R>,
+) -> Result<Arc<PluginNotificationManager>, Box<dyn std::error::Error>> {
+    let backend = WindowsToastBackend::new(&app.config().tauri.bundle.identifier);
+    let notifications = Arc::new(PluginNotificationManager::with_backend(Arc::new(backend)));
+    
+    // Forward toast button clicks to the frontend
+    let app_handle = app.handle();
+    notifications.set_action_handler(Arc::new(move |action| {
+        let event = NotificationActionEvent { action: action.to_owned() };
+        if let Err(e) = app_handle.emit_all("notification-action", event) {
+            log::warn!("Failed to emit notification action: {}", e);
+        }
+    }));
+    
+    let wired_events = [
+        ("plugin-crash", "Plugin crashed", NotificationIcon::Error),
+        ("plugin-update-available", "Plugin update available", NotificationIcon::Info),
+        ("plugin-resource-warning", "Plugin resource warning", NotificationIcon::Warning),
+    ];
+    
+    for (event_name, title, icon) in wired_events {
+        let notifications = notifications.clone();
+        app.listen_global(event_name, move |event| {
+            let message = notification_message(event.payload());
+            if let Err(e) = notifications.show_notification(title, &message, icon.clone(), Vec::new()) {
+                log::warn!("Failed to show '{}' notification: {}", event_name, e);
+            }
+        });
+    }
+    
+    app.manage(notifications.clone());
+    
+    Ok(notifications)
+}
+
+/// Build a notification message from a plugin event payload
+fn notification_message(payload: Option<&str>) -> String {
+    let payload = payload.unwrap_or_default();
+    let value: serde_json::Value = serde_json::from_str(payload).unwrap_or_default();
+    
+    match (value["plugin_id"].as_str(), value["message"].as_str()) {
+        (Some(plugin_id), Some(message)) => format!("{}: {}", plugin_id, message),
+        (Some(plugin_id), None) => format!("Plugin {}", plugin_id),
+        (None, Some(message)) => message.to_owned(),
+        (None, None) => payload.to_owned(),
+    }
+}
+
 /// Plugin system setup for Tauri
 pub fn setup<R: Runtime>(
     app: &mut tauri::App<R>,
@@ -334,10 +815,18 @@ pub fn setup<R: Runtime>(
     permission_system: Arc<PermissionSystem>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Register commands
-    register_commands(app, plugin_manager)?;
+    register_commands(app, plugin_manager.clone())?;
     
     // Setup permission handler
     setup_permission_handler(app, permission_system)?;
     
+    // Setup notifications
+    setup_notifications(app)?;
+    
+    // Re-enable the plugins that were enabled when the application last ran
+    tauri::async_runtime::spawn(async move {
+        plugin_manager.restore_enabled_plugins().await;
+    });
+    
     Ok(())
 }
@@ -0,0 +1,282 @@
+//! Plugin system notifications
+//!
+//! Shows Windows toast notifications for plugin system events. Toasts follow
+//! the system light/dark theme; the built-in icons are the Windows security
+//! and maintenance glyphs, which are drawn for both themes.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+/// Application ID toasts are shown under
+pub const DEFAULT_APP_ID: &str = "Tauri.PluginSystem";
+
+/// Icon shown on a notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NotificationIcon {
+    /// Informational notification
+    Info,
+    
+    /// Warning notification
+    Warning,
+    
+    /// Error notification
+    Error,
+    
+    /// Custom image file
+    Custom(PathBuf),
+}
+
+impl NotificationIcon {
+    /// Get the image URI for the icon
+    fn uri(&self) -> String {
+        let path = match self {
+            NotificationIcon::Custom(path) => path.clone(),
+            builtin => {
+                let system_root = std::env::var("SYSTEMROOT").unwrap_or_else(|_| r"C:\Windows".to_owned());
+                let file = match builtin {
+                    NotificationIcon::Warning => "SecurityAndMaintenance_Alert.png",
+                    NotificationIcon::Error => "SecurityAndMaintenance_Error.png",
+                    _ => "SecurityAndMaintenance.png",
+                };
+                PathBuf::from(system_root).join("System32").join(file)
+            },
+        };
+        
+        format!("file:///{}", path.display().to_string().replace('\\', "/"))
+    }
+}
+
+/// How a toast action is activated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToastActivationType {
+    /// Bring the application to the foreground
+    Foreground,
+    
+    /// Handle the action without showing the application
+    Background,
+    
+    /// Launch a protocol handler
+    Protocol,
+}
+
+impl ToastActivationType {
+    /// Get the toast XML activation type name
+    fn as_str(&self) -> &'static str {
+        match self {
+            ToastActivationType::Foreground => "foreground",
+            ToastActivationType::Background => "background",
+            ToastActivationType::Protocol => "protocol",
+        }
+    }
+}
+
+/// A button shown on a toast
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToastAction {
+    /// Button label, also reported when the action is activated
+    pub label: String,
+    
+    /// How the action is activated
+    pub activation_type: ToastActivationType,
+}
+
+/// A notification to display
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Toast {
+    /// Notification title
+    pub title: String,
+    
+    /// Notification body
+    pub message: String,
+    
+    /// Notification icon
+    pub icon: NotificationIcon,
+    
+    /// Buttons shown on the notification
+    pub actions: Vec<ToastAction>,
+}
+
+impl Toast {
+    /// Render the toast as Windows toast XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = format!(
+            r#"<toast><visual><binding template="ToastGeneric"><text>{}</text><text>{}</text><image placement="appLogoOverride" src="{}"/></binding></visual>"#,
+            escape_xml(&self.title),
+            escape_xml(&self.message),
+            escape_xml(&self.icon.uri()),
+        );
+        
+        if !self.actions.is_empty() {
+            xml.push_str("<actions>");
+            for action in &self.actions {
+                xml.push_str(&format!(
+                    r#"<action content="{0}" arguments="{0}" activationType="{1}"/>"#,
+                    escape_xml(&action.label),
+                    action.activation_type.as_str(),
+                ));
+            }
+            xml.push_str("</actions>");
+        }
+        
+        xml.push_str("</toast>");
+        xml
+    }
+}
+
+/// Error type for notifications
+#[derive(Error, Debug)]
+pub enum NotificationError {
+    /// The platform notification API failed
+    #[error("Notification API error: {0}")]
+    Backend(String),
+    
+    /// Notifications are not supported on this platform
+    #[error("Notifications are not supported on this platform")]
+    Unsupported,
+}
+
+/// Callback invoked with the label of an activated toast action
+pub type ToastActionCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Platform API used to display toasts
+pub trait ToastBackend: Send + Sync {
+    /// Display a toast, reporting activated actions to the callback
+    fn show(&self, toast: &Toast, on_action: ToastActionCallback) -> Result<(), NotificationError>;
+}
+
+/// Manages notifications shown for plugin system events
+pub struct PluginNotificationManager {
+    /// Platform API used to display toasts
+    backend: Arc<dyn ToastBackend>,
+    
+    /// Callback invoked when a toast action is activated
+    on_action: Mutex<ToastActionCallback>,
+    
+    /// Notifications are suppressed until this time, if set
+    do_not_disturb_until: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl PluginNotificationManager {
+    /// Create a notification manager using the Windows toast API
+    pub fn new() -> Self {
+        Self::with_backend(Arc::new(WindowsToastBackend::new(DEFAULT_APP_ID)))
+    }
+    
+    /// Create a notification manager using a custom toast backend
+    pub fn with_backend(backend: Arc<dyn ToastBackend>) -> Self {
+        Self {
+            backend,
+            on_action: Mutex::new(Arc::new(|_| {})),
+            do_not_disturb_until: Mutex::new(None),
+        }
+    }
+    
+    /// Set the callback invoked when a toast action is activated
+    pub fn set_action_handler(&self, on_action: ToastActionCallback) {
+        *self.on_action.lock().unwrap() = on_action;
+    }
+    
+    /// Suppress notifications until the given time, or clear the suppression with `None`
+    pub fn set_do_not_disturb(&self, until: Option<DateTime<Utc>>) {
+        *self.do_not_disturb_until.lock().unwrap() = until;
+    }
+    
+    /// Check whether notifications are currently suppressed
+    pub fn is_do_not_disturb(&self) -> bool {
+        matches!(*self.do_not_disturb_until.lock().unwrap(), Some(until) if until > Utc::now())
+    }
+    
+    /// Show a notification unless do-not-disturb is active
+    pub fn show_notification(
+        &self,
+        title: &str,
+        message: &str,
+        icon: NotificationIcon,
+        actions: Vec<ToastAction>,
+    ) -> Result<(), NotificationError> {
+        if self.is_do_not_disturb() {
+            debug!("Suppressing notification '{}' while do-not-disturb is active", title);
+            return Ok(());
+        }
+        
+        let toast = Toast {
+            title: title.to_owned(),
+            message: message.to_owned(),
+            icon,
+            actions,
+        };
+        
+        let on_action = self.on_action.lock().unwrap().clone();
+        self.backend.show(&toast, on_action)
+    }
+}
+
+impl Default for PluginNotificationManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Toast backend using `Windows.UI.Notifications`
+pub struct WindowsToastBackend {
+    /// Application ID toasts are shown under
+    #[cfg_attr(not(windows), allow(dead_code))]
+    app_id: String,
+}
+
+impl WindowsToastBackend {
+    /// Create a backend showing toasts under an application ID
+    pub fn new(app_id: &str) -> Self {
+        Self { app_id: app_id.to_owned() }
+    }
+}
+
+#[cfg(windows)]
+impl ToastBackend for WindowsToastBackend {
+    fn show(&self, toast: &Toast, on_action: ToastActionCallback) -> Result<(), NotificationError> {
+        use windows::core::{ComInterface, IInspectable, HSTRING};
+        use windows::Data::Xml::Dom::XmlDocument;
+        use windows::Foundation::TypedEventHandler;
+        use windows::UI::Notifications::{ToastActivatedEventArgs, ToastNotification, ToastNotificationManager};
+        
+        let backend_error = |e: windows::core::Error| NotificationError::Backend(e.message().to_string());
+        
+        let document = XmlDocument::new().map_err(backend_error)?;
+        document.LoadXml(&HSTRING::from(toast.to_xml())).map_err(backend_error)?;
+        
+        let notification = ToastNotification::CreateToastNotification(&document).map_err(backend_error)?;
+        notification.Activated(&TypedEventHandler::<ToastNotification, IInspectable>::new(
+            move |_, args| {
+                if let Some(args) = args {
+                    let args: ToastActivatedEventArgs = args.cast()?;
+                    on_action(&args.Arguments()?.to_string());
+                }
+                Ok(())
+            },
+        )).map_err(backend_error)?;
+        
+        ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(self.app_id.as_str()))
+            .and_then(|notifier| notifier.Show(&notification))
+            .map_err(backend_error)
+    }
+}
+
+#[cfg(not(windows))]
+impl ToastBackend for WindowsToastBackend {
+    fn show(&self, _toast: &Toast, _on_action: ToastActionCallback) -> Result<(), NotificationError> {
+        Err(NotificationError::Unsupported)
+    }
+}
+
+/// Escape text for use in XML content and attributes
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -204,7 +204,7 @@ pub mod helpers {
         let mut zip = zip::ZipWriter::new(file);
         let options = FileOptions::default()
             .compression_method(zip::CompressionMethod::Stored);
-            
+        
         // Add manifest
         let manifest_content = if valid {
             create_valid_manifest_json()
@@ -235,3 +235,84 @@ pub mod helpers {
         Ok(())
     }
 }
+
+/// Fixtures for the loader and host unit tests, using the current manifest format
+///
+/// Includes the harness plugin, a real plugin DLL built from `tests/harness-plugin`.
+pub mod harness {
+    use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+    use zip::write::FileOptions;
+    
+    /// Create a plugin manifest JSON string
+    pub fn create_manifest_json(name: &str) -> String {
+        format!(r#"{{
+            "name": "{}",
+            "version": "1.0.0",
+            "entry": "plugin.dll",
+            "api_version": "1.0.0",
+            "description": "Test harness plugin",
+            "author": "Test Author",
+            "permissions": []
+        }}"#, name)
+    }
+    
+    /// Get the path of the harness plugin DLL built with the workspace
+    ///
+    /// The harness plugin is a workspace member, so `cargo build --workspace`
+    /// puts it next to the test binaries. Returns `None` when it has not been
+    /// built, printing how to build it; tests needing it are then skipped.
+    pub fn harness_plugin_dll() -> Option<&'static Path> {
+        static DLL_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+        
+        DLL_PATH.get_or_init(|| {
+            // Test binaries live in `<target>/<profile>/deps`
+            let dll_name = format!("{}harness_plugin{}", DLL_PREFIX, DLL_SUFFIX);
+            let dll_path = std::env::current_exe().ok()
+                .and_then(|test_binary| Some(test_binary.parent()?.parent()?.join(&dll_name)));
+            
+            match dll_path {
+                Some(dll_path) if dll_path.exists() => Some(dll_path),
+                _ => {
+                    eprintln!(
+                        "Skipping harness plugin tests: {} has not been built, run `cargo build --workspace` first",
+                        dll_name
+                    );
+                    None
+                },
+            }
+        }).as_deref()
+    }
+    
+    /// Write a plugin package with a file that is not a real DLL as `plugin.dll`
+    pub fn create_dummy_plugin_package(path: &Path, name: &str) -> std::io::Result<()> {
+        write_package(path, name, b"This is not a real DLL but simulates one for testing")
+    }
+    
+    /// Write a plugin package containing the harness plugin as `plugin.dll`
+    pub fn create_harness_plugin_package(path: &Path, name: &str, dll_path: &Path) -> std::io::Result<()> {
+        write_package(path, name, &std::fs::read(dll_path)?)
+    }
+    
+    /// Write a plugin package with a manifest, a DLL and a resource file
+    fn write_package(path: &Path, name: &str, dll: &[u8]) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = FileOptions::default();
+        
+        zip.start_file("plugin.json", options)?;
+        zip.write_all(create_manifest_json(name).as_bytes())?;
+        
+        zip.start_file("plugin.dll", options)?;
+        zip.write_all(dll)?;
+        
+        zip.start_file("resources/test.txt", options)?;
+        zip.write_all(b"Test resource file")?;
+        
+        zip.finish()?;
+        Ok(())
+    }
+}
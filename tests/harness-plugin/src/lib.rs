@@ -0,0 +1,202 @@
+//! Test harness plugin for the tauri-windows-plugin-system
+//!
+//! A real plugin DLL for the integration tests to load. It has no dependencies,
+//! so it mirrors the host's `PluginContext` layout instead of importing it.
+//!
+//! On init it registers a `harness_register` command. Triggering that command
+//! with an event name registers the echo callback for that event. The echo
+//! callback returns the length of its event data. It also registers a
+//! `process_text` command, standing in for a data-processor plugin, which
+//! reports the number of words in its arguments as a JSON result. Counters
+//! exported alongside the plugin ABI let tests check what the host called.
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Event callback, matching the host's `CallbackFn`
+type CallbackFn = unsafe extern "C-unwind" fn(
+    context: *mut PluginContext,
+    event_data: *const c_char,
+    data_len: u32,
+) -> c_int;
+
+/// Trace context, matching the host's `TraceContext`
+#[repr(C)]
+pub struct TraceContext {
+    /// ID of the whole trace
+    pub trace_id: [u8; 16],
+    
+    /// ID of the span the plugin's work is a child of
+    pub parent_span_id: [u8; 8],
+    
+    /// Trace flags
+    pub flags: u8,
+}
+
+/// Context passed by the host, matching the host's `PluginContext`
+#[repr(C)]
+pub struct PluginContext {
+    /// API version for compatibility checking
+    pub api_version: u32,
+    
+    /// Pointer to host-specific data
+    pub host_data: *mut c_void,
+    
+    /// Pointer to plugin-specific data
+    pub plugin_data: *mut c_void,
+    
+    /// Function to register callbacks for events
+    pub register_callback: Option<
+        unsafe extern "C" fn(context: *mut PluginContext, event_name: *const c_char, callback: Option<CallbackFn>) -> c_int,
+    >,
+    
+    /// Function to log messages to the host application
+    pub log: Option<unsafe extern "C" fn(context: *mut PluginContext, level: u32, message: *const c_char)>,
+    
+    /// Function to read a value from the plugin's key-value store
+    pub kv_get: Option<
+        unsafe extern "C" fn(context: *mut PluginContext, key: *const c_char, buffer: *mut c_char, buffer_len: u32) -> c_int,
+    >,
+    
+    /// Function to store a value in the plugin's key-value store
+    pub kv_set: Option<
+        unsafe extern "C" fn(context: *mut PluginContext, key: *const c_char, value: *const c_char) -> c_int,
+    >,
+    
+    /// Function to delete a value from the plugin's key-value store
+    pub kv_delete: Option<unsafe extern "C" fn(context: *mut PluginContext, key: *const c_char) -> c_int>,
+    
+    /// Trace context of the event being handled
+    pub trace_context: TraceContext,
+    
+    /// Function to register asynchronous callbacks, unused by this plugin
+    pub register_async_callback: Option<unsafe extern "C" fn()>,
+    
+    /// Function to report asynchronous results, unused by this plugin
+    pub complete_async: Option<unsafe extern "C" fn()>,
+    
+    /// Set by the host when the running callback should stop early
+    pub shutdown_requested: AtomicU32,
+    
+    /// Function to report the JSON result of the running command
+    pub set_result: Option<unsafe extern "C" fn(context: *mut PluginContext, json: *const c_char) -> c_int>,
+}
+
+/// Name of the command registering the echo callback for another event
+const REGISTER_COMMAND: &CStr = c"harness_register";
+
+/// Name of the command counting the words of its arguments
+const PROCESS_TEXT_COMMAND: &CStr = c"process_text";
+
+/// Log level the host maps to `info`, matching the host's `LOG_INFO`
+const LOG_LEVEL_INFO: u32 = 1;
+
+/// Number of times `plugin_init` was called
+static INIT_CALLS: AtomicU32 = AtomicU32::new(0);
+
+/// Number of events handled by the echo callback
+static EVENT_CALLS: AtomicU32 = AtomicU32::new(0);
+
+/// Number of times `plugin_teardown` was called
+static TEARDOWN_CALLS: AtomicU32 = AtomicU32::new(0);
+
+/// Initialize the plugin, registering the `harness_register` command
+///
+/// # Safety
+///
+/// `context` must be null or point to a context provided by the host.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_init(context: *mut PluginContext) -> c_int {
+    if context.is_null() {
+        return -1;
+    }
+    
+    let register_callback = match (*context).register_callback {
+        Some(register_callback) => register_callback,
+        None => return -2,
+    };
+    
+    let result = register_callback(context, REGISTER_COMMAND.as_ptr(), Some(register_command));
+    if result != 0 {
+        return result;
+    }
+    
+    let result = register_callback(context, PROCESS_TEXT_COMMAND.as_ptr(), Some(process_text));
+    if result != 0 {
+        return result;
+    }
+    
+    if let Some(log) = (*context).log {
+        log(context, LOG_LEVEL_INFO, c"Harness plugin initialized".as_ptr());
+    }
+    
+    INIT_CALLS.fetch_add(1, Ordering::SeqCst);
+    0
+}
+
+/// Tear down the plugin
+///
+/// # Safety
+///
+/// `context` must be null or point to a context provided by the host.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_teardown(_context: *mut PluginContext) -> c_int {
+    TEARDOWN_CALLS.fetch_add(1, Ordering::SeqCst);
+    0
+}
+
+/// Get the number of times `plugin_init` was called
+#[no_mangle]
+pub extern "C" fn harness_init_calls() -> u32 {
+    INIT_CALLS.load(Ordering::SeqCst)
+}
+
+/// Get the number of events handled by the echo callback
+#[no_mangle]
+pub extern "C" fn harness_event_calls() -> u32 {
+    EVENT_CALLS.load(Ordering::SeqCst)
+}
+
+/// Get the number of times `plugin_teardown` was called
+#[no_mangle]
+pub extern "C" fn harness_teardown_calls() -> u32 {
+    TEARDOWN_CALLS.load(Ordering::SeqCst)
+}
+
+/// Register the echo callback for the event named by the event data
+unsafe extern "C-unwind" fn register_command(
+    context: *mut PluginContext,
+    event_data: *const c_char,
+    _data_len: u32,
+) -> c_int {
+    match (*context).register_callback {
+        Some(register_callback) => register_callback(context, event_data, Some(echo)),
+        None => -2,
+    }
+}
+
+/// Count the event and return the length of its data
+unsafe extern "C-unwind" fn echo(
+    _context: *mut PluginContext,
+    _event_data: *const c_char,
+    data_len: u32,
+) -> c_int {
+    EVENT_CALLS.fetch_add(1, Ordering::SeqCst);
+    data_len as c_int
+}
+
+/// Report the number of whitespace-separated words in the event data as `{"words": n}`
+unsafe extern "C-unwind" fn process_text(
+    context: *mut PluginContext,
+    event_data: *const c_char,
+    _data_len: u32,
+) -> c_int {
+    let set_result = match (*context).set_result {
+        Some(set_result) => set_result,
+        None => return -2,
+    };
+    
+    let words = CStr::from_ptr(event_data).to_string_lossy().split_whitespace().count();
+    let result = CString::new(format!("{{\"words\": {}}}", words)).expect("JSON result contains no NUL");
+    set_result(context, result.as_ptr())
+}
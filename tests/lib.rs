@@ -6,18 +6,27 @@ pub mod common;
 // Basic functionality tests that pass
 mod basic_functionality_test;
 
-// Unit tests - temporarily disabled due to API mismatches
+// Unit tests for the loader and host run against the harness plugin in tests/harness-plugin,
+// built by `cargo build --workspace`; the tests needing it are skipped until it is built
+#[cfg(test)]
+mod unit {
+    pub mod plugin_loader {
+        pub mod manifest_tests;
+        pub mod package_tests;
+    }
+    
+    pub mod plugin_host {
+        pub mod host_tests;
+    }
+    
+    pub mod plugin_manager {
+        pub mod command_tests;
+    }
+}
+
+// Remaining unit tests - temporarily disabled due to API mismatches
 // #[cfg(test)]
 // mod unit {
-//     pub mod plugin_loader {
-//         pub mod manifest_tests;
-//         pub mod package_tests;
-//     }
-//     
-//     pub mod plugin_host {
-//         pub mod host_tests;
-//     }
-//     
 //     pub mod permission_system {
 //         pub mod permission_tests;
 //     }
@@ -0,0 +1,313 @@
+//! Load tests stressing the plugin host with concurrent plugin lifecycles
+//!
+//! Run with `cargo test --test load_test -- --nocapture` to print the report.
+
+use tauri_windows_plugin_system::plugin_host::{PluginContext, PluginHost};
+use tauri_windows_plugin_system::plugin_loader::{LoadedPlugin, PluginManifest, PluginMetadata};
+use chrono::Utc;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ffi::{c_char, c_int, CString};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Allocator tracking the number of live heap bytes
+struct CountingAllocator;
+
+/// Heap bytes currently allocated by the test binary
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+    
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Heap growth allowed per 10,000 commands
+const MAX_HEAP_GROWTH_PER_10K_COMMANDS: usize = 10 * 1024 * 1024;
+
+/// Load test parameters
+#[derive(Debug, Clone)]
+struct LoadTestConfig {
+    /// Number of plugins exercised concurrently
+    concurrency: usize,
+    
+    /// Commands sent to each plugin
+    commands_per_plugin: usize,
+    
+    /// Time the whole run may take
+    timeout: Duration,
+}
+
+impl Default for LoadTestConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            commands_per_plugin: 1000,
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Timings collected from one plugin's lifecycle
+#[derive(Debug, Default)]
+struct TaskTimings {
+    /// Time taken to initialize the plugin
+    load: Duration,
+    
+    /// Time taken to tear the plugin down
+    unload: Duration,
+    
+    /// Latency of each command
+    commands: Vec<Duration>,
+}
+
+/// Results of a load test run
+#[derive(Debug)]
+struct LoadTestReport {
+    /// Wall-clock duration of the run
+    elapsed: Duration,
+    
+    /// Latency of every command, sorted ascending
+    command_latencies: Vec<Duration>,
+    
+    /// Plugin initialization times, sorted ascending
+    load_times: Vec<Duration>,
+    
+    /// Plugin teardown times, sorted ascending
+    unload_times: Vec<Duration>,
+    
+    /// Live heap bytes sampled as plugin lifecycles completed
+    heap_samples: Vec<usize>,
+    
+    /// Live heap bytes before the run
+    heap_before: usize,
+    
+    /// Live heap bytes after the run
+    heap_after: usize,
+}
+
+impl LoadTestReport {
+    /// Get the number of commands sent
+    fn total_commands(&self) -> usize {
+        self.command_latencies.len()
+    }
+    
+    /// Get the command throughput
+    fn commands_per_second(&self) -> f64 {
+        self.total_commands() as f64 / self.elapsed.as_secs_f64()
+    }
+    
+    /// Get the heap growth over the run
+    fn heap_growth(&self) -> usize {
+        self.heap_after.saturating_sub(self.heap_before)
+    }
+    
+    /// Print the report
+    fn print(&self) {
+        println!("commands:        {} in {:?}", self.total_commands(), self.elapsed);
+        println!("throughput:      {:.0} commands/s", self.commands_per_second());
+        println!(
+            "command latency: p50 {:?}, p99 {:?}, p999 {:?}",
+            percentile(&self.command_latencies, 0.50),
+            percentile(&self.command_latencies, 0.99),
+            percentile(&self.command_latencies, 0.999),
+        );
+        println!(
+            "load time:       p50 {:?}, max {:?}",
+            percentile(&self.load_times, 0.50),
+            percentile(&self.load_times, 1.0),
+        );
+        println!(
+            "unload time:     p50 {:?}, max {:?}",
+            percentile(&self.unload_times, 0.50),
+            percentile(&self.unload_times, 1.0),
+        );
+        println!("heap samples:    {:?}", self.heap_samples);
+        println!("heap growth:     {} bytes", self.heap_growth());
+    }
+}
+
+/// Get a percentile of sorted durations
+fn percentile(sorted: &[Duration], quantile: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    
+    let index = ((sorted.len() - 1) as f64 * quantile).round() as usize;
+    sorted[index]
+}
+
+/// Callback echoing the payload length
+unsafe extern "C-unwind" fn echo_callback(_context: *mut PluginContext, _event_data: *const c_char, data_len: u32) -> c_int {
+    data_len as c_int
+}
+
+/// Plugin init registering the echo callback
+unsafe extern "C" fn load_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = match (*context).register_callback {
+        Some(register) => register,
+        None => return -1,
+    };
+    
+    let event_name = CString::new("command").unwrap();
+    register(context, event_name.as_ptr(), Some(echo_callback))
+}
+
+/// Plugin teardown that always succeeds
+unsafe extern "C" fn load_plugin_teardown(_context: *mut PluginContext) -> i32 {
+    0
+}
+
+/// Build a statically linked plugin for the load test
+fn static_plugin(name: &str) -> LoadedPlugin {
+    let metadata = PluginMetadata {
+        manifest: PluginManifest {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            entry: "plugin.dll".to_string(),
+            api_version: "1.0.0".to_string(),
+            permissions: Vec::new(),
+            description: "Load test plugin".to_string(),
+            author: "Test Author".to_string(),
+            homepage: None,
+            resource_limits: None,
+            provides_capabilities: Vec::new(),
+            required_host_features: Vec::new(),
+            total_size_bytes: None,
+            file_count: None,
+        },
+        install_path: PathBuf::new(),
+        dll_path: PathBuf::new(),
+        installed_at: Utc::now(),
+    };
+    
+    LoadedPlugin::from_static(metadata, load_plugin_init, load_plugin_teardown)
+}
+
+/// Drives concurrent plugin lifecycles against a shared plugin host
+struct LoadTestHarness {
+    /// Load test parameters
+    config: LoadTestConfig,
+    
+    /// Host shared by all tasks, locked the way `PluginManager` locks it
+    host: Arc<RwLock<PluginHost>>,
+}
+
+impl LoadTestHarness {
+    /// Create a harness with its own plugin host
+    fn new(config: LoadTestConfig) -> Self {
+        Self {
+            config,
+            host: Arc::new(RwLock::new(PluginHost::new())),
+        }
+    }
+    
+    /// Run one plugin through initialization, its commands, and teardown
+    async fn run_plugin(host: Arc<RwLock<PluginHost>>, plugin_id: String, commands: usize) -> TaskTimings {
+        let mut timings = TaskTimings::default();
+        
+        let started = Instant::now();
+        host.write().await.init_plugin(plugin_id.clone(), static_plugin(&plugin_id))
+            .expect("Failed to initialize plugin");
+        timings.load = started.elapsed();
+        
+        for i in 0..commands {
+            let payload = format!("{{\"sequence\":{}}}", i);
+            let started = Instant::now();
+            // Release the host lock before awaiting the result, as `PluginManager` does
+            let pending = host.read().await.dispatch_event(&plugin_id, "command", payload.as_str());
+            let result = pending.expect("Failed to dispatch command").await;
+            timings.commands.push(started.elapsed());
+            
+            assert_eq!(result.expect("Command failed"), payload.len() as i32);
+        }
+        
+        let started = Instant::now();
+        host.write().await.teardown_plugin(&plugin_id).expect("Failed to teardown plugin");
+        timings.unload = started.elapsed();
+        
+        timings
+    }
+    
+    /// Run all plugin lifecycles concurrently and collect the report
+    async fn run(&self) -> LoadTestReport {
+        let heap_before = LIVE_BYTES.load(Ordering::Relaxed);
+        let started = Instant::now();
+        
+        let tasks: Vec<_> = (0..self.config.concurrency)
+            .map(|i| tokio::spawn(Self::run_plugin(
+                self.host.clone(),
+                format!("load-plugin-{}", i),
+                self.config.commands_per_plugin,
+            )))
+            .collect();
+        
+        let mut command_latencies = Vec::new();
+        let mut load_times = Vec::new();
+        let mut unload_times = Vec::new();
+        let mut heap_samples = Vec::new();
+        
+        for task in tasks {
+            let timings = tokio::time::timeout(self.config.timeout, task)
+                .await
+                .expect("Load test timed out")
+                .expect("Plugin task panicked");
+            
+            command_latencies.extend(timings.commands);
+            load_times.push(timings.load);
+            unload_times.push(timings.unload);
+            heap_samples.push(LIVE_BYTES.load(Ordering::Relaxed));
+        }
+        
+        let elapsed = started.elapsed();
+        command_latencies.sort();
+        load_times.sort();
+        unload_times.sort();
+        
+        LoadTestReport {
+            elapsed,
+            command_latencies,
+            load_times,
+            unload_times,
+            heap_samples,
+            heap_before,
+            heap_after: LIVE_BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_plugin_lifecycles_under_load() {
+    let config = LoadTestConfig::default();
+    let harness = LoadTestHarness::new(config.clone());
+    
+    let report = harness.run().await;
+    report.print();
+    
+    assert_eq!(report.total_commands(), config.concurrency * config.commands_per_plugin);
+    assert_eq!(report.load_times.len(), config.concurrency);
+    assert!(harness.host.read().await.loaded_plugins().is_empty());
+    
+    let allowed_growth = MAX_HEAP_GROWTH_PER_10K_COMMANDS * report.total_commands().div_ceil(10_000);
+    assert!(
+        report.heap_growth() <= allowed_growth,
+        "Heap grew by {} bytes, more than {} bytes allowed",
+        report.heap_growth(),
+        allowed_growth,
+    );
+}
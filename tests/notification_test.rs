@@ -0,0 +1,79 @@
+//! Notification manager tests using a mock toast backend
+
+use tauri_windows_plugin_system::ui_integration::notifications::{
+    NotificationError, NotificationIcon, PluginNotificationManager, Toast, ToastAction, ToastActionCallback,
+    ToastActivationType, ToastBackend,
+};
+use chrono::{Duration, Utc};
+use std::sync::{Arc, Mutex};
+
+/// Toast backend recording shown toasts and activating their first action
+#[derive(Default)]
+struct MockToastBackend {
+    shown: Mutex<Vec<Toast>>,
+}
+
+impl ToastBackend for MockToastBackend {
+    fn show(&self, toast: &Toast, on_action: ToastActionCallback) -> Result<(), NotificationError> {
+        self.shown.lock().unwrap().push(toast.clone());
+        
+        if let Some(action) = toast.actions.first() {
+            on_action(&action.label);
+        }
+        
+        Ok(())
+    }
+}
+
+#[test]
+fn test_notification_actions_reach_handler() {
+    // Arrange
+    let backend = Arc::new(MockToastBackend::default());
+    let manager = PluginNotificationManager::with_backend(backend.clone());
+    
+    let activated = Arc::new(Mutex::new(Vec::new()));
+    let sink = activated.clone();
+    manager.set_action_handler(Arc::new(move |label| sink.lock().unwrap().push(label.to_owned())));
+    
+    // Act
+    manager.show_notification("Plugin crashed", "demo-1.0.0 stopped", NotificationIcon::Error, vec![
+        ToastAction { label: "Restart".to_string(), activation_type: ToastActivationType::Foreground },
+    ]).expect("Failed to show notification");
+    
+    // Assert
+    let shown = backend.shown.lock().unwrap();
+    assert_eq!(shown.len(), 1);
+    assert_eq!(shown[0].icon, NotificationIcon::Error);
+    assert_eq!(*activated.lock().unwrap(), vec!["Restart".to_string()]);
+}
+
+#[test]
+fn test_do_not_disturb_suppresses_notifications() {
+    let backend = Arc::new(MockToastBackend::default());
+    let manager = PluginNotificationManager::with_backend(backend.clone());
+    
+    manager.set_do_not_disturb(Some(Utc::now() + Duration::hours(1)));
+    manager.show_notification("Suppressed", "", NotificationIcon::Info, Vec::new()).unwrap();
+    assert!(backend.shown.lock().unwrap().is_empty());
+    
+    // An expired window no longer suppresses anything
+    manager.set_do_not_disturb(Some(Utc::now() - Duration::seconds(1)));
+    manager.show_notification("Shown", "", NotificationIcon::Info, Vec::new()).unwrap();
+    assert_eq!(backend.shown.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_toast_xml_escapes_text() {
+    let toast = Toast {
+        title: "Update <ready>".to_string(),
+        message: "Tom & Jerry's \"plugin\"".to_string(),
+        icon: NotificationIcon::Custom("C:\\icons\\plugin.png".into()),
+        actions: vec![ToastAction { label: "Later".to_string(), activation_type: ToastActivationType::Background }],
+    };
+    
+    let xml = toast.to_xml();
+    assert!(xml.contains("<text>Update &lt;ready&gt;</text>"));
+    assert!(xml.contains("<text>Tom &amp; Jerry&apos;s &quot;plugin&quot;</text>"));
+    assert!(xml.contains(r#"src="file:///C:/icons/plugin.png""#));
+    assert!(xml.contains(r#"<action content="Later" arguments="Later" activationType="background"/>"#));
+}
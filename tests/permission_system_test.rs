@@ -0,0 +1,445 @@
+//! Permission system tests
+
+use tauri_windows_plugin_system::permission_system::{
+    FileSystemPermission, NetworkPermission, Permission, PermissionCategory, PermissionError,
+    PermissionPromptHandler, PermissionPromptQueue, PermissionPromptResult, PermissionRiskLevel, PermissionSystem,
+    PromptStyle, RiskRules, RiskScore, SystemPermission, UIPermission, VendorPolicy, VendorTrustLevel, VendorVerifier,
+};
+use tauri_windows_plugin_system::plugin_loader::PluginManifest;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Network access to a single host
+fn network_permission() -> Permission {
+    Permission::Network(NetworkPermission {
+        allowed_hosts: vec!["api.example.com".to_string()],
+    })
+}
+
+/// System information access
+fn system_permission() -> Permission {
+    Permission::System(SystemPermission {
+        read_clipboard: false,
+        write_clipboard: false,
+        read_system_info: true,
+    })
+}
+
+/// Notification-only UI access
+fn ui_permission() -> Permission {
+    Permission::UI(UIPermission {
+        show_notifications: true,
+        create_windows: false,
+    })
+}
+
+#[test]
+fn test_network_and_system_request_is_riskier_than_ui_only() {
+    let risky = PermissionRiskLevel::for_permissions(&[network_permission(), system_permission()]);
+    let benign = PermissionRiskLevel::for_permissions(&[ui_permission()]);
+    
+    assert!(risky > benign);
+    assert_eq!(risky, PermissionRiskLevel::Critical);
+    assert_eq!(PromptStyle::from(risky), PromptStyle::Critical);
+    assert_eq!(PromptStyle::from(benign), PromptStyle::Standard);
+}
+
+#[test]
+fn test_filesystem_write_to_system_directory_is_critical() {
+    let permission = Permission::FileSystem(FileSystemPermission {
+        read: false,
+        write: true,
+        paths: vec!["C:/Windows/System32".to_string()],
+    });
+    
+    assert_eq!(permission.risk_level(), PermissionRiskLevel::Critical);
+}
+
+/// Score a permission with the built-in rules
+fn score(permission: &Permission) -> RiskScore {
+    RiskRules::builtin().score(permission)
+}
+
+#[test]
+fn test_risk_rule_for_system_directory_writes() {
+    let scored = score(&Permission::FileSystem(FileSystemPermission {
+        read: false,
+        write: true,
+        paths: vec!["C:/Windows/System32".to_string()],
+    }));
+    
+    assert_eq!(scored.level, PermissionRiskLevel::Critical);
+    assert!(!scored.mitigations.is_empty());
+    
+    // Read-only access to the same directory does not hit the rule
+    let scored = score(&Permission::FileSystem(FileSystemPermission {
+        read: true,
+        write: false,
+        paths: vec!["C:\\Windows".to_string()],
+    }));
+    assert_eq!(scored.level, PermissionRiskLevel::Medium);
+}
+
+#[test]
+fn test_risk_rule_for_any_network_host() {
+    let wildcard = score(&Permission::Network(NetworkPermission {
+        allowed_hosts: vec!["*".to_string()],
+    }));
+    let single_host = score(&network_permission());
+    
+    assert_eq!(wildcard.level, PermissionRiskLevel::High);
+    assert!(single_host.level < wildcard.level);
+}
+
+#[test]
+fn test_risk_rule_for_clipboard_reads() {
+    let scored = score(&Permission::System(SystemPermission {
+        read_clipboard: true,
+        write_clipboard: false,
+        read_system_info: false,
+    }));
+    
+    assert_eq!(scored.level, PermissionRiskLevel::High);
+    assert!(scored.explanation.contains("copy"));
+}
+
+#[test]
+fn test_custom_risk_rules_from_toml() {
+    let rules = RiskRules::from_toml(r#"
+        [[rule]]
+        category = "ui"
+        access = "create_windows"
+        level = "High"
+        explanation = "Can open windows"
+    "#).expect("Failed to parse rules");
+    
+    assert_eq!(rules.rules().len(), 1);
+    assert_eq!(rules.score(&ui_permission()).level, ui_permission().risk_level());
+    
+    let windows = Permission::UI(UIPermission {
+        show_notifications: false,
+        create_windows: true,
+    });
+    assert_eq!(rules.score(&windows).level, PermissionRiskLevel::High);
+    
+    let result = RiskRules::from_toml("[[rule]]\ncategory = \"registry\"");
+    assert!(matches!(result, Err(PermissionError::InvalidRiskRules(_))));
+}
+
+#[test]
+fn test_custom_risk_rules_decide_the_request_risk() {
+    // Arrange
+    let windows = Permission::UI(UIPermission {
+        show_notifications: false,
+        create_windows: true,
+    });
+    let rules = RiskRules::from_toml(r#"
+        [[rule]]
+        category = "ui"
+        access = "create_windows"
+        level = "Critical"
+        explanation = "Can open windows"
+    "#).expect("Failed to parse rules");
+    let mut system = PermissionSystem::new();
+    
+    // Act
+    let builtin_risk = system.get_risk_level(std::slice::from_ref(&windows));
+    system.set_risk_rules(Arc::new(rules));
+    let custom_risk = system.get_risk_level(std::slice::from_ref(&windows));
+    
+    // Assert: the same rules drive both the risk level and its explanation
+    assert_eq!(builtin_risk, windows.risk_level());
+    assert_eq!(custom_risk, PermissionRiskLevel::Critical);
+    assert_eq!(system.get_risk_scores(&[windows])[0].level, custom_risk);
+}
+
+/// Prompt handler approving every request
+struct ApproveAll;
+
+impl PermissionPromptHandler for ApproveAll {
+    fn prompt_for_permissions(
+        &self,
+        _plugin_id: &str,
+        _plugin_name: &str,
+        permissions: &[Permission],
+    ) -> Result<PermissionPromptResult, PermissionError> {
+        Ok(PermissionPromptResult::Allowed(permissions.to_vec()))
+    }
+}
+
+/// File system access to the data directory
+fn data_permission(write: bool) -> Permission {
+    Permission::FileSystem(FileSystemPermission {
+        read: true,
+        write,
+        paths: vec!["C:/Data".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn test_runtime_escalation_from_read_only_to_read_write() {
+    let mut system = PermissionSystem::new();
+    system.set_prompt_handler(ApproveAll);
+    system.grant_permissions("plugin", vec![data_permission(false), ui_permission()], true).unwrap();
+    
+    assert!(!system.is_permission_granted("plugin", &data_permission(true)));
+    
+    let granted = system.request_additional_permission("plugin", "Plugin", data_permission(true))
+        .await
+        .expect("Escalation should be approved");
+    
+    // The read-write grant replaces the read-only one it covers
+    assert_eq!(granted, vec![ui_permission(), data_permission(true)]);
+    assert!(system.is_permission_granted("plugin", &data_permission(true)));
+    assert!(!system.is_permission_granted("plugin", &data_permission(false)));
+}
+
+#[tokio::test]
+async fn test_runtime_grant_is_saved() {
+    // Arrange
+    let temp_dir = tempfile::tempdir().unwrap();
+    let settings_path = temp_dir.path().join("plugin_permissions.json");
+    let mut system = PermissionSystem::new();
+    system.set_prompt_handler(ApproveAll);
+    system.set_settings_path(settings_path.clone());
+    
+    // Act
+    system.request_additional_permission("plugin", "Plugin", Permission::Storage)
+        .await
+        .expect("Grant should be approved");
+    
+    // Assert: the grant survives a restart without an explicit save
+    let mut reloaded = PermissionSystem::new();
+    reloaded.load_permissions(&settings_path).unwrap();
+    assert!(reloaded.is_permission_granted("plugin", &Permission::Storage));
+}
+
+#[tokio::test]
+async fn test_runtime_escalation_denied_without_prompt() {
+    let system = PermissionSystem::new();
+    system.grant_permissions("plugin", vec![data_permission(false)], true).unwrap();
+    
+    let result = system.request_additional_permission("plugin", "Plugin", data_permission(true)).await;
+    
+    assert!(matches!(result, Err(PermissionError::Denied(_))));
+    assert_eq!(system.get_granted_permissions("plugin"), vec![data_permission(false)]);
+}
+
+/// Prompt handler approving every request and recording what it was asked for
+#[derive(Clone, Default)]
+struct RecordingPrompt(Arc<Mutex<Vec<Permission>>>);
+
+impl PermissionPromptHandler for RecordingPrompt {
+    fn prompt_for_permissions(
+        &self,
+        _plugin_id: &str,
+        _plugin_name: &str,
+        permissions: &[Permission],
+    ) -> Result<PermissionPromptResult, PermissionError> {
+        self.0.lock().unwrap().extend_from_slice(permissions);
+        Ok(PermissionPromptResult::Allowed(permissions.to_vec()))
+    }
+}
+
+/// Build a manifest requesting permissions on behalf of a vendor
+fn vendor_manifest(author: &str, permissions: Vec<Permission>) -> PluginManifest {
+    PluginManifest {
+        name: "vendor-plugin".to_string(),
+        version: "1.0.0".to_string(),
+        entry: "plugin.dll".to_string(),
+        api_version: "1.0.0".to_string(),
+        permissions,
+        description: "Vendor plugin".to_string(),
+        author: author.to_string(),
+        homepage: None,
+        resource_limits: None,
+        provides_capabilities: Vec::new(),
+        required_host_features: Vec::new(),
+        total_size_bytes: None,
+        file_count: None,
+    }
+}
+
+/// Verifier authenticating plugins installed in `signed/acme` as published by Acme
+fn acme_verifier() -> VendorVerifier {
+    Box::new(|_, install_path| (install_path == Path::new("signed/acme")).then(|| "Acme".to_string()))
+}
+
+#[tokio::test]
+async fn test_trusted_vendor_permissions_are_granted_without_prompt() {
+    let prompt = RecordingPrompt::default();
+    let mut system = PermissionSystem::new();
+    system.set_prompt_handler(prompt.clone());
+    system.set_vendor_verifier(acme_verifier());
+    system.register_vendor_policy(VendorPolicy {
+        vendor_id: "Acme".to_string(),
+        auto_grant_permissions: vec![data_permission(true)],
+        trust_level: VendorTrustLevel::Trusted,
+    });
+    
+    let requested = vec![data_permission(false), network_permission()];
+    
+    // The trusted vendor's plugin is only prompted for what the policy does not cover
+    let manifest = vendor_manifest("Acme", requested.clone());
+    system.apply_vendor_policy("acme-plugin", &manifest, Path::new("signed/acme")).unwrap();
+    let granted = system.prompt_for_permissions("acme-plugin", "Acme Plugin", &requested).await.unwrap();
+    
+    assert_eq!(granted, requested);
+    assert_eq!(*prompt.0.lock().unwrap(), vec![network_permission()]);
+    
+    // An unknown vendor's plugin is prompted for everything
+    prompt.0.lock().unwrap().clear();
+    let manifest = vendor_manifest("Unknown", requested.clone());
+    system.apply_vendor_policy("other-plugin", &manifest, Path::new("other")).unwrap();
+    system.prompt_for_permissions("other-plugin", "Other Plugin", &requested).await.unwrap();
+    
+    assert_eq!(*prompt.0.lock().unwrap(), requested);
+}
+
+#[tokio::test]
+async fn test_unverified_vendor_claim_is_prompted() {
+    let prompt = RecordingPrompt::default();
+    let mut system = PermissionSystem::new();
+    system.set_prompt_handler(prompt.clone());
+    system.set_vendor_verifier(acme_verifier());
+    system.register_vendor_policy(VendorPolicy {
+        vendor_id: "Acme".to_string(),
+        auto_grant_permissions: vec![data_permission(true)],
+        trust_level: VendorTrustLevel::Trusted,
+    });
+    
+    // A package naming Acme as its author without proving it gets no trust
+    let requested = vec![data_permission(false)];
+    let manifest = vendor_manifest("Acme", requested.clone());
+    assert_eq!(system.vendor_trust(&manifest, Path::new("unsigned")), VendorTrustLevel::Standard);
+    
+    system.apply_vendor_policy("spoofed-plugin", &manifest, Path::new("unsigned")).unwrap();
+    assert!(system.get_granted_permissions("spoofed-plugin").is_empty());
+    
+    system.prompt_for_permissions("spoofed-plugin", "Spoofed Plugin", &requested).await.unwrap();
+    assert_eq!(*prompt.0.lock().unwrap(), requested);
+}
+
+#[test]
+fn test_vendor_policies_are_persisted() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let policies_path = temp_dir.path().join("vendor_policies.json");
+    let policy = VendorPolicy {
+        vendor_id: "Acme".to_string(),
+        auto_grant_permissions: vec![data_permission(false)],
+        trust_level: VendorTrustLevel::Blocked,
+    };
+    
+    let system = PermissionSystem::new();
+    system.register_vendor_policy(policy.clone());
+    system.save_vendor_policies(&policies_path).unwrap();
+    
+    let reloaded = PermissionSystem::new();
+    reloaded.load_vendor_policies(&policies_path).unwrap();
+    assert_eq!(reloaded.vendor_policy("Acme"), Some(policy));
+    
+    // Blocked vendors are denied outright
+    let manifest = vendor_manifest("Acme", vec![data_permission(false)]);
+    let result = reloaded.apply_vendor_policy("acme-plugin", &manifest, Path::new("unsigned"));
+    assert!(matches!(result, Err(PermissionError::Denied(_))));
+    
+    assert!(reloaded.remove_vendor_policy("Acme").is_some());
+    assert!(reloaded.vendor_policy("Acme").is_none());
+}
+
+#[test]
+fn test_unreadable_vendor_policies_fail_to_load() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let policies_path = temp_dir.path().join("vendor_policies.json");
+    std::fs::write(&policies_path, "not json").unwrap();
+    
+    let result = PermissionSystem::new().load_vendor_policies(&policies_path);
+    
+    assert!(matches!(result, Err(PermissionError::LoadFailed(_))));
+}
+
+#[test]
+fn test_revoke_specific_keeps_other_categories() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let settings_path = temp_dir.path().join("plugin_permissions.json");
+    let mut system = PermissionSystem::new();
+    system.set_settings_path(settings_path.clone());
+    system.grant_permissions("plugin", vec![data_permission(true), network_permission()], true).unwrap();
+    
+    let remaining = system.revoke_specific("plugin", PermissionCategory::Network, None).unwrap();
+    
+    assert_eq!(remaining, vec![data_permission(true)]);
+    assert!(system.is_permission_granted("plugin", &data_permission(true)));
+    assert!(!system.is_permission_granted("plugin", &network_permission()));
+    
+    // Revoking what is no longer granted fails
+    let result = system.revoke_specific("plugin", PermissionCategory::Network, None);
+    assert!(matches!(result, Err(PermissionError::NotGranted(_))));
+    
+    // The change was saved without an explicit call
+    let mut reloaded = PermissionSystem::new();
+    reloaded.load_permissions(&settings_path).unwrap();
+    assert_eq!(reloaded.get_granted_permissions("plugin"), vec![data_permission(true)]);
+}
+
+#[test]
+fn test_revoke_specific_scope_drops_only_that_path() {
+    let system = PermissionSystem::new();
+    let permission = Permission::FileSystem(FileSystemPermission {
+        read: true,
+        write: false,
+        paths: vec!["C:/Data".to_string(), "C:/Logs".to_string()],
+    });
+    system.grant_permissions("plugin", vec![permission], true).unwrap();
+    
+    let remaining = system.revoke_specific("plugin", PermissionCategory::FileSystem, Some("C:/Logs")).unwrap();
+    assert_eq!(remaining, vec![data_permission(false)]);
+    
+    // Dropping the last path drops the grant
+    let remaining = system.revoke_specific("plugin", PermissionCategory::FileSystem, Some("C:/Data")).unwrap();
+    assert!(remaining.is_empty());
+}
+
+#[tokio::test]
+async fn test_queued_prompts_are_shown_one_at_a_time() {
+    let system = Arc::new(PermissionSystem::new());
+    let queue: Arc<PermissionPromptQueue> = system.prompt_queue();
+    let (presented_tx, mut presented) = tokio::sync::mpsc::unbounded_channel();
+    queue.set_presenter(Box::new(move |prompt| {
+        presented_tx.send(prompt.clone()).unwrap();
+    }));
+    let wait = std::time::Duration::from_secs(5);
+    
+    let first = tokio::spawn({
+        let system = system.clone();
+        async move { system.prompt_for_permissions("first", "First", &[network_permission()]).await }
+    });
+    let first_prompt = tokio::time::timeout(wait, presented.recv()).await.unwrap().unwrap();
+    assert_eq!(first_prompt.plugin_id, "first");
+    
+    let second = tokio::spawn({
+        let system = system.clone();
+        async move { system.prompt_for_permissions("second", "Second", &[system_permission()]).await }
+    });
+    
+    // The second dialog waits until the first one is answered
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(presented.try_recv().is_err());
+    assert_eq!(queue.current_prompt().unwrap().request_id, first_prompt.request_id);
+    
+    queue.resolve(first_prompt.request_id, PermissionPromptResult::Allowed(first_prompt.permissions.clone())).unwrap();
+    let granted = tokio::time::timeout(wait, first).await.unwrap().unwrap().unwrap();
+    assert_eq!(granted, vec![network_permission()]);
+    
+    let second_prompt = tokio::time::timeout(wait, presented.recv()).await.unwrap().unwrap();
+    assert_eq!(second_prompt.plugin_id, "second");
+    assert!(matches!(
+        queue.resolve(first_prompt.request_id, PermissionPromptResult::Allowed(Vec::new())),
+        Err(PermissionError::PromptFailed(_))
+    ));
+    
+    queue.resolve(second_prompt.request_id, PermissionPromptResult::Denied(second_prompt.permissions.clone())).unwrap();
+    let result = tokio::time::timeout(wait, second).await.unwrap().unwrap();
+    assert!(matches!(result, Err(PermissionError::Denied(_))));
+    assert!(queue.current_prompt().is_none());
+}
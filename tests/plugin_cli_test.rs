@@ -0,0 +1,65 @@
+//! Command-line interface tests against a plugin manager IPC server
+#![cfg(windows)]
+
+use tauri_windows_plugin_system::plugin_manager::{PluginManager, PluginSource};
+use tauri_windows_plugin_system::permission_system::PermissionSystem;
+use std::process::Command;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+/// Run the CLI against a pipe and return its stdout
+async fn run_cli(pipe_name: &str, args: &[&str]) -> (bool, String) {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_tauri-plugin-cli"));
+    command.arg("--pipe").arg(pipe_name).args(args);
+    
+    let output = tokio::task::spawn_blocking(move || command.output()).await.unwrap()
+        .expect("Failed to run CLI");
+    
+    (output.status.success(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[tokio::test]
+async fn test_cli_prints_plugins_as_json() {
+    // Arrange: a manager with one development install serving a unique pipe
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let manager = Arc::new(PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        Arc::new(PermissionSystem::new()),
+    ).expect("Failed to create plugin manager"));
+    
+    let plugin_dir = temp_dir.path().join("cli-plugin");
+    std::fs::create_dir_all(&plugin_dir).unwrap();
+    std::fs::write(plugin_dir.join("plugin.json"), serde_json::json!({
+        "name": "cli-plugin",
+        "version": "1.0.0",
+        "entry": "plugin.dll",
+        "api_version": "1.0.0",
+        "permissions": [],
+        "description": "Test plugin",
+        "author": "Test Author"
+    }).to_string()).unwrap();
+    let plugin_id = manager.install_plugin(PluginSource::Directory(plugin_dir)).await
+        .expect("Failed to install plugin")
+        .id;
+    
+    let pipe_name = format!(r"\\.\pipe\tauri-plugin-cli-test-{}", std::process::id());
+    manager.start_ipc_server(&pipe_name).expect("Failed to start IPC server");
+    
+    // Act
+    let (list_ok, list) = run_cli(&pipe_name, &["list", "--json"]).await;
+    let (info_ok, info) = run_cli(&pipe_name, &["info", &plugin_id]).await;
+    let (missing_ok, _) = run_cli(&pipe_name, &["info", "missing-plugin"]).await;
+    
+    // Assert
+    assert!(list_ok);
+    let list: serde_json::Value = serde_json::from_str(&list).expect("List output is not JSON");
+    assert_eq!(list[0]["id"], plugin_id.as_str());
+    
+    assert!(info_ok);
+    let info: serde_json::Value = serde_json::from_str(&info).expect("Info output is not JSON");
+    assert_eq!(info["name"], "cli-plugin");
+    assert_eq!(info["dev"], true);
+    
+    assert!(!missing_ok);
+}
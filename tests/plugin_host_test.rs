@@ -0,0 +1,1019 @@
+//! Plugin host tests using statically linked plugin entry points
+
+use tauri_windows_plugin_system::plugin_host::{
+    AsyncHandle, CallbackThreadModel, EventDeduplicator, EventEnvelope, EventReplayer, HostData, KvLimits, KvStore, KvStoreError,
+    MockClock, PluginContext, PluginContextPool, PluginHost, PluginHostError, ReplayLog, TraceContext, DEDUP_SKIPPED_CODE,
+    COMMAND_RESULT_NOT_ACCEPTED, KV_NOT_FOUND, KV_NO_PERMISSION,
+};
+use tauri_windows_plugin_system::permission_system::{FileSystemPermission, NetworkPermission, Permission, UIPermission};
+use tauri_windows_plugin_system::plugin_loader::{LoadedPlugin, PluginChunkCallback, PluginManifest, PluginMetadata};
+use chrono::Utc;
+use futures::StreamExt;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::path::PathBuf;
+use tempfile::tempdir;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Callback that takes a while before returning
+unsafe extern "C-unwind" fn slow_callback(_context: *mut PluginContext, _event_data: *const c_char, _data_len: u32) -> c_int {
+    thread::sleep(Duration::from_millis(300));
+    7
+}
+
+/// Plugin init registering the slow callback
+unsafe extern "C" fn slow_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = match (*context).register_callback {
+        Some(register) => register,
+        None => return -1,
+    };
+    
+    let event_name = CString::new("slow").unwrap();
+    register(context, event_name.as_ptr(), Some(slow_callback))
+}
+
+/// Whether the polling callback saw the host's shutdown request
+static SHUTDOWN_OBSERVED: AtomicBool = AtomicBool::new(false);
+
+/// Callback blocking until the host requests a shutdown, or for at most five seconds
+unsafe extern "C-unwind" fn polling_callback(context: *mut PluginContext, _event_data: *const c_char, _data_len: u32) -> c_int {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if (*context).shutdown_requested.load(Ordering::SeqCst) != 0 {
+            SHUTDOWN_OBSERVED.store(true, Ordering::SeqCst);
+            return -1;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    0
+}
+
+/// Plugin init registering the polling callback
+unsafe extern "C" fn polling_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = match (*context).register_callback {
+        Some(register) => register,
+        None => return -1,
+    };
+    
+    let event_name = CString::new("poll").unwrap();
+    register(context, event_name.as_ptr(), Some(polling_callback))
+}
+
+/// Number of times the counting callback was invoked
+static COUNTED_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Callback counting its invocations
+unsafe extern "C-unwind" fn counting_callback(_context: *mut PluginContext, _event_data: *const c_char, _data_len: u32) -> c_int {
+    COUNTED_CALLS.fetch_add(1, Ordering::SeqCst);
+    0
+}
+
+/// Plugin init registering the counting callback
+unsafe extern "C" fn counting_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = match (*context).register_callback {
+        Some(register) => register,
+        None => return -1,
+    };
+    
+    let event_name = CString::new("count").unwrap();
+    register(context, event_name.as_ptr(), Some(counting_callback))
+}
+
+/// Callback returning the length of its payload
+unsafe extern "C-unwind" fn echo_length_callback(_context: *mut PluginContext, _event_data: *const c_char, data_len: u32) -> c_int {
+    data_len as c_int
+}
+
+/// Plugin init registering the payload length callback
+unsafe extern "C" fn echo_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = match (*context).register_callback {
+        Some(register) => register,
+        None => return -1,
+    };
+    
+    let event_name = CString::new("echo").unwrap();
+    register(context, event_name.as_ptr(), Some(echo_length_callback))
+}
+
+/// Plugin teardown that always succeeds
+unsafe extern "C" fn plugin_teardown(_context: *mut PluginContext) -> i32 {
+    0
+}
+
+/// Build a statically linked test plugin
+fn static_plugin(name: &str) -> LoadedPlugin {
+    static_plugin_with_init(name, slow_plugin_init)
+}
+
+/// Plugin teardown that always fails
+unsafe extern "C" fn failing_teardown(_context: *mut PluginContext) -> i32 {
+    5
+}
+
+/// Host data of the last plugin initialized by `retaining_plugin_init`
+static RETAINED_HOST_DATA: AtomicPtr<Mutex<HostData>> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Plugin init taking an extra reference to its host data
+unsafe extern "C" fn retaining_plugin_init(context: *mut PluginContext) -> i32 {
+    let host_data = (*context).host_data as *const Mutex<HostData>;
+    Arc::increment_strong_count(host_data);
+    RETAINED_HOST_DATA.store(host_data as *mut _, Ordering::SeqCst);
+    0
+}
+
+/// Release the reference taken by `retaining_plugin_init`, returning the references left
+fn release_retained_host_data() -> usize {
+    let host_data = RETAINED_HOST_DATA.swap(std::ptr::null_mut(), Ordering::SeqCst);
+    let host_data = unsafe { Arc::from_raw(host_data as *const Mutex<HostData>) };
+    Arc::strong_count(&host_data) - 1
+}
+
+/// Build a statically linked test plugin with a custom init function
+fn static_plugin_with_init(name: &str, init_fn: unsafe extern "C" fn(*mut PluginContext) -> i32) -> LoadedPlugin {
+    static_plugin_with(name, init_fn, plugin_teardown)
+}
+
+/// Build a statically linked test plugin with custom init and teardown functions
+fn static_plugin_with(
+    name: &str,
+    init_fn: unsafe extern "C" fn(*mut PluginContext) -> i32,
+    teardown_fn: unsafe extern "C" fn(*mut PluginContext) -> i32,
+) -> LoadedPlugin {
+    static_plugin_with_permissions(name, Vec::new(), init_fn, teardown_fn)
+}
+
+/// Build a statically linked test plugin requesting permissions
+fn static_plugin_with_permissions(
+    name: &str,
+    permissions: Vec<Permission>,
+    init_fn: unsafe extern "C" fn(*mut PluginContext) -> i32,
+    teardown_fn: unsafe extern "C" fn(*mut PluginContext) -> i32,
+) -> LoadedPlugin {
+    let metadata = PluginMetadata {
+        manifest: PluginManifest {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            entry: "plugin.dll".to_string(),
+            api_version: "1.0.0".to_string(),
+            permissions,
+            description: "Static test plugin".to_string(),
+            author: "Test Author".to_string(),
+            homepage: None,
+            resource_limits: None,
+            provides_capabilities: Vec::new(),
+            required_host_features: Vec::new(),
+            total_size_bytes: None,
+            file_count: None,
+        },
+        install_path: PathBuf::new(),
+        dll_path: PathBuf::new(),
+        installed_at: Utc::now(),
+    };
+    
+    LoadedPlugin::from_static(metadata, init_fn, teardown_fn)
+}
+
+#[test]
+fn test_synchronous_model_is_default() {
+    let host = PluginHost::new();
+    assert_eq!(host.thread_model(), CallbackThreadModel::Synchronous);
+}
+
+#[test]
+fn test_slow_callback_does_not_block_caller_in_worker_mode() {
+    // Arrange
+    let mut host = PluginHost::with_thread_model(CallbackThreadModel::WorkerThread { queue_capacity: 4 });
+    host.init_plugin("slow-plugin".to_string(), static_plugin("slow-plugin"))
+        .expect("Failed to initialize plugin");
+    
+    // Act
+    let started = Instant::now();
+    let pending = host.dispatch_event("slow-plugin", "slow", "{}")
+        .expect("Failed to dispatch event");
+    let dispatch_time = started.elapsed();
+    
+    // Assert
+    assert!(dispatch_time < Duration::from_millis(150), "Dispatch blocked for {:?}", dispatch_time);
+    assert_eq!(pending.wait().expect("Callback failed"), 7);
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    
+    host.teardown_plugin("slow-plugin").expect("Failed to teardown plugin");
+}
+
+#[test]
+fn test_duplicate_event_invokes_callback_once() {
+    // Arrange
+    let mut host = PluginHost::new();
+    host.init_plugin("counting-plugin".to_string(), static_plugin_with_init("counting-plugin", counting_plugin_init))
+        .expect("Failed to initialize plugin");
+    let event = EventEnvelope::new("{}").with_idempotency_key("click-1");
+    
+    // Act
+    let first = host.trigger_event("counting-plugin", "count", event.clone()).expect("First event failed");
+    let second = host.trigger_event("counting-plugin", "count", event).expect("Second event failed");
+    
+    // Assert
+    assert_eq!(first, 0);
+    assert_eq!(second, DEDUP_SKIPPED_CODE);
+    assert_eq!(COUNTED_CALLS.load(Ordering::SeqCst), 1);
+    
+    let stats = host.deduplicator().stats();
+    assert_eq!(stats.duplicates_caught, 1);
+    assert_eq!(stats.unique_events, 1);
+    
+    host.teardown_plugin("counting-plugin").expect("Failed to teardown plugin");
+}
+
+#[test]
+fn test_deduplicator_forgets_keys_after_window() {
+    let deduplicator = EventDeduplicator::new(Duration::from_millis(40));
+    
+    assert!(!deduplicator.is_duplicate("plugin", "event", "key"));
+    assert!(deduplicator.is_duplicate("plugin", "event", "key"));
+    assert!(!deduplicator.is_duplicate("plugin", "other-event", "key"));
+    
+    thread::sleep(Duration::from_millis(80));
+    assert!(!deduplicator.is_duplicate("plugin", "event", "key"));
+}
+
+#[test]
+fn test_default_command_timeout_is_configurable() {
+    let host = PluginHost::new().with_default_command_timeout(Duration::from_secs(5));
+    assert_eq!(host.default_command_timeout(), Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_slow_command_times_out() {
+    // Arrange
+    let mut host = PluginHost::with_thread_model(CallbackThreadModel::WorkerThread { queue_capacity: 4 });
+    host.init_plugin("timeout-plugin".to_string(), static_plugin("timeout-plugin"))
+        .expect("Failed to initialize plugin");
+    
+    // Act
+    let result = host.trigger_event_with_timeout("timeout-plugin", "slow", "{}", Duration::from_millis(50)).await;
+    
+    // Assert
+    match result {
+        Err(PluginHostError::CommandTimedOut { plugin_id, event_name, timeout_ms }) => {
+            assert_eq!(plugin_id, "timeout-plugin");
+            assert_eq!(event_name, "slow");
+            assert_eq!(timeout_ms, 50);
+        },
+        other => panic!("Expected CommandTimedOut error, got {:?}", other),
+    }
+    
+    // A command finishing in time returns its result
+    let result = host.trigger_event_with_timeout("timeout-plugin", "slow", "{}", Duration::from_secs(5)).await;
+    assert_eq!(result.expect("Callback failed"), 7);
+    
+    host.teardown_plugin("timeout-plugin").expect("Failed to teardown plugin");
+}
+
+#[tokio::test]
+async fn test_blocked_command_receives_shutdown_request() {
+    // Arrange
+    let mut host = PluginHost::new();
+    host.init_plugin("polling-plugin".to_string(), static_plugin_with_init("polling-plugin", polling_plugin_init))
+        .expect("Failed to initialize plugin");
+    let started = Instant::now();
+    
+    // Act: the callback blocks until it sees the shutdown request
+    let result = host.trigger_event_with_timeout("polling-plugin", "poll", "{}", Duration::from_millis(50)).await;
+    
+    // Assert: the request reached the blocked callback, which stopped within the grace period
+    assert!(matches!(result, Err(PluginHostError::CommandTimedOut { timeout_ms: 50, .. })));
+    assert!(SHUTDOWN_OBSERVED.load(Ordering::SeqCst));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    
+    host.teardown_plugin("polling-plugin").expect("Failed to teardown plugin");
+}
+
+#[test]
+fn test_teardown_gives_up_on_blocked_callback() {
+    // Arrange
+    let mut host = PluginHost::with_thread_model(CallbackThreadModel::WorkerThread { queue_capacity: 4 })
+        .with_teardown_timeout(Duration::from_millis(50));
+    host.init_plugin("blocked-plugin".to_string(), static_plugin("blocked-plugin"))
+        .expect("Failed to initialize plugin");
+    let _pending = host.dispatch_event("blocked-plugin", "slow", "{}").expect("Failed to dispatch event");
+    let started = Instant::now();
+    
+    // Act
+    let result = host.teardown_plugin("blocked-plugin");
+    
+    // Assert: teardown returns before the callback finishes and the plugin is unloaded
+    assert!(matches!(result, Err(PluginHostError::TeardownTimedOut(id)) if id == "blocked-plugin"));
+    assert!(started.elapsed() < Duration::from_millis(250));
+    assert!(!host.has_plugin("blocked-plugin"));
+}
+
+#[tokio::test]
+async fn test_synchronous_plugin_command_times_out_and_becomes_unhealthy() {
+    // Arrange
+    let mut host = PluginHost::new().with_unhealthy_timeout_threshold(2);
+    host.set_command_timeout("slow", Duration::from_millis(50));
+    host.init_plugin("timeout-plugin".to_string(), static_plugin("timeout-plugin"))
+        .expect("Failed to initialize plugin");
+    assert_eq!(host.command_timeout("slow"), Duration::from_millis(50));
+    assert_eq!(host.command_timeout("other"), host.default_command_timeout());
+    
+    // Act: the command sleeps past its timeout on the command worker
+    let result = host.execute_command("timeout-plugin", "slow", "{}").await;
+    
+    // Assert: commands of synchronous plugins are bounded too
+    assert!(matches!(result, Err(PluginHostError::CommandTimedOut { timeout_ms: 50, .. })));
+    assert!(host.is_plugin_healthy("timeout-plugin"));
+    
+    // A second timeout in a row makes the plugin unhealthy
+    let result = host.execute_command("timeout-plugin", "slow", "{}").await;
+    assert!(matches!(result, Err(PluginHostError::CommandTimedOut { .. })));
+    assert_eq!(host.consecutive_timeouts("timeout-plugin"), 2);
+    assert!(!host.is_plugin_healthy("timeout-plugin"));
+    
+    // A command finishing in time makes it healthy again
+    let result = host.trigger_event_with_timeout("timeout-plugin", "slow", "{}", Duration::from_secs(5)).await;
+    assert_eq!(result.expect("Callback failed"), 7);
+    assert!(host.is_plugin_healthy("timeout-plugin"));
+    
+    host.teardown_plugin("timeout-plugin").expect("Failed to teardown plugin");
+}
+
+#[tokio::test]
+async fn test_command_and_inline_event_never_run_at_once() {
+    // Arrange
+    let mut host = PluginHost::new();
+    host.init_plugin("serial-plugin".to_string(), static_plugin("serial-plugin"))
+        .expect("Failed to initialize plugin");
+    let started = Instant::now();
+    
+    // Act: the command runs on the command worker while an event runs on the caller's thread
+    let mut timed_event = host.dispatch_with_timeout("serial-plugin", "slow", "{}", Duration::from_secs(5))
+        .expect("Failed to dispatch command");
+    let inline_result = host.trigger_event("serial-plugin", "slow", "{}");
+    let command_result = timed_event.wait().await.expect("Command timed out");
+    
+    // Assert: both 300 ms callbacks ran, one after the other
+    assert_eq!(inline_result.unwrap(), 7);
+    assert_eq!(command_result.unwrap().code, 7);
+    assert!(started.elapsed() >= Duration::from_millis(600));
+    
+    host.teardown_plugin("serial-plugin").expect("Failed to teardown plugin");
+}
+
+/// Callback reporting a fixed JSON result, returning the code `set_result` gave back
+unsafe extern "C-unwind" fn reporting_callback(context: *mut PluginContext, _event_data: *const c_char, _data_len: u32) -> c_int {
+    let result = CString::new(r#"{"status": "done"}"#).unwrap();
+    match (*context).set_result {
+        Some(set_result) => set_result(context, result.as_ptr()),
+        None => -1,
+    }
+}
+
+/// Plugin init registering the reporting callback
+unsafe extern "C" fn reporting_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = match (*context).register_callback {
+        Some(register) => register,
+        None => return -1,
+    };
+    
+    let event_name = CString::new("report").unwrap();
+    register(context, event_name.as_ptr(), Some(reporting_callback))
+}
+
+#[tokio::test]
+async fn test_command_reports_json_result() {
+    // Arrange
+    let mut host = PluginHost::new();
+    host.init_plugin("reporting-plugin".to_string(), static_plugin_with_init("reporting-plugin", reporting_plugin_init))
+        .expect("Failed to initialize plugin");
+    
+    // Act
+    let mut timed_event = host.dispatch_command("reporting-plugin", "report", "{}").expect("Failed to dispatch command");
+    let output = timed_event.wait().await.expect("Command timed out").expect("Command failed");
+    
+    // Assert: results are only accepted from commands
+    assert_eq!(output.code, 0);
+    assert_eq!(output.result, Some(serde_json::json!({ "status": "done" })));
+    assert_eq!(host.trigger_event("reporting-plugin", "report", "{}").unwrap(), COMMAND_RESULT_NOT_ACCEPTED);
+    
+    host.teardown_plugin("reporting-plugin").expect("Failed to teardown plugin");
+}
+
+#[test]
+fn test_recorded_events_replay_with_same_results() {
+    // Arrange: record five events
+    let mut host = PluginHost::new();
+    host.init_plugin("echo-plugin".to_string(), static_plugin_with_init("echo-plugin", echo_plugin_init))
+        .expect("Failed to initialize plugin");
+    
+    host.recorder().start();
+    let recorded: Vec<i32> = ["a", "bb", "ccc", "dddd", "eeeee"].iter()
+        .map(|data| {
+            thread::sleep(Duration::from_millis(5));
+            host.trigger_event("echo-plugin", "echo", *data).expect("Failed to trigger event")
+        })
+        .collect();
+    let log = host.recorder().stop();
+    assert_eq!(log.entries.len(), 5);
+    assert!(!host.recorder().is_recording());
+    
+    // The log survives a round trip through disk
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let log_path = temp_dir.path().join("replay.json");
+    log.save(&log_path).expect("Failed to save replay log");
+    let log = ReplayLog::load(&log_path).expect("Failed to load replay log");
+    
+    // Act: replay on a fresh host at double speed
+    let mut replay_host = PluginHost::new();
+    replay_host.init_plugin("echo-plugin".to_string(), static_plugin_with_init("echo-plugin", echo_plugin_init))
+        .expect("Failed to initialize plugin");
+    
+    let start = log.entries[0].timestamp;
+    let results = EventReplayer::with_clock(MockClock::new(start)).replay(&log, &replay_host, 2.0);
+    
+    // Assert
+    let replayed: Vec<i32> = results.iter().map(|r| r.return_code.expect("Replayed event failed")).collect();
+    assert_eq!(replayed, recorded);
+    assert!(results.iter().all(|r| r.error.is_none()));
+    
+    // Mock clock timing is half the recorded spacing, up to rounding
+    let recorded_span = log.entries[4].timestamp - start;
+    let drift = (results[4].replayed_at - start) - recorded_span / 2;
+    assert!(drift.num_microseconds().unwrap().abs() < 1, "Replay drifted by {}", drift);
+    
+    host.teardown_plugin("echo-plugin").expect("Failed to teardown plugin");
+    replay_host.teardown_plugin("echo-plugin").expect("Failed to teardown plugin");
+}
+
+#[test]
+fn test_plugin_context_is_released_on_every_path() {
+    for (index, teardown_fn) in [plugin_teardown as _, failing_teardown as _].into_iter().cycle().take(20).enumerate() {
+        let name = format!("retaining-{}", index);
+        
+        // Explicit teardown, succeeding or failing
+        let mut host = PluginHost::new();
+        host.init_plugin(name.clone(), static_plugin_with(&name, retaining_plugin_init, teardown_fn))
+            .expect("Failed to initialize plugin");
+        let result = host.teardown_plugin(&name);
+        assert_eq!(result.is_err(), index % 2 == 1);
+        assert_eq!(release_retained_host_data(), 0, "Host data leaked after teardown");
+        
+        // Teardown when the host is dropped
+        host.init_plugin(name.clone(), static_plugin_with(&name, retaining_plugin_init, teardown_fn))
+            .expect("Failed to initialize plugin");
+        drop(host);
+        assert_eq!(release_retained_host_data(), 0, "Host data leaked after host drop");
+    }
+}
+
+/// IDs of plugins torn down by `recording_teardown`, in order
+static TORN_DOWN: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Plugin teardown recording the plugin's ID
+unsafe extern "C" fn recording_teardown(context: *mut PluginContext) -> i32 {
+    let host_data = &*((*context).host_data as *const Mutex<HostData>);
+    TORN_DOWN.lock().unwrap().push(host_data.lock().unwrap().plugin_id.clone());
+    0
+}
+
+#[test]
+fn test_parent_directory_path_never_narrows_into_a_grant() {
+    // Arrange
+    let grant = Permission::FileSystem(FileSystemPermission {
+        read: true,
+        write: true,
+        paths: vec!["C:\\Data".to_string()],
+    });
+    let escaping = Permission::FileSystem(FileSystemPermission {
+        read: true,
+        write: true,
+        paths: vec!["C:\\Data\\..\\Windows".to_string(), "C:/Data/cache/../../Windows".to_string()],
+    });
+    let redundant = Permission::FileSystem(FileSystemPermission {
+        read: true,
+        write: false,
+        paths: vec!["C:\\Data\\.\\\\cache".to_string()],
+    });
+    
+    // Act & Assert: `..` paths are dropped, redundant separators and `.` are not
+    assert_eq!(escaping.intersection(&grant), None);
+    match redundant.intersection(&grant) {
+        Some(Permission::FileSystem(fs)) => assert_eq!(fs.paths, vec!["C:\\Data\\.\\\\cache".to_string()]),
+        other => panic!("Unexpected intersection: {:?}", other),
+    }
+}
+
+#[test]
+fn test_child_plugin_inherits_intersection_of_permissions() {
+    let mut host = PluginHost::new();
+    let parent = static_plugin_with_permissions("parent", vec![
+        Permission::FileSystem(FileSystemPermission {
+            read: true,
+            write: false,
+            paths: vec!["C:/Data".to_string()],
+        }),
+        Permission::Network(NetworkPermission {
+            allowed_hosts: vec!["api.example.com".to_string()],
+        }),
+    ], echo_plugin_init, plugin_teardown);
+    host.init_plugin("parent".to_string(), parent).expect("Failed to init parent");
+    
+    let child = static_plugin_with_permissions("child", vec![
+        Permission::FileSystem(FileSystemPermission {
+            read: true,
+            write: true,
+            paths: vec!["C:/Data/cache".to_string(), "C:/Other".to_string()],
+        }),
+        Permission::Network(NetworkPermission {
+            allowed_hosts: vec!["api.example.com".to_string(), "evil.example.com".to_string()],
+        }),
+        Permission::UI(UIPermission {
+            show_notifications: true,
+            create_windows: false,
+        }),
+    ], echo_plugin_init, plugin_teardown);
+    let child_id = host.register_child_plugin("parent", child).expect("Failed to register child");
+    
+    assert_eq!(child_id, "parent/child");
+    assert_eq!(host.list_children("parent"), vec![child_id.clone()]);
+    assert_eq!(host.trigger_event(&child_id, "echo", "abc").unwrap(), 3);
+    
+    let granted = host.effective_permissions(&child_id);
+    assert_eq!(granted.len(), 2);
+    match &granted[0] {
+        Permission::FileSystem(fs) => {
+            assert!(fs.read && !fs.write);
+            assert_eq!(fs.paths, vec!["C:/Data/cache".to_string()]);
+        },
+        other => panic!("Unexpected permission: {:?}", other),
+    }
+    match &granted[1] {
+        Permission::Network(net) => assert_eq!(net.allowed_hosts, vec!["api.example.com".to_string()]),
+        other => panic!("Unexpected permission: {:?}", other),
+    }
+    
+    let orphan = static_plugin_with_init("orphan", echo_plugin_init);
+    assert!(host.register_child_plugin("missing", orphan).is_err());
+}
+
+#[test]
+fn test_teardown_removes_children_in_post_order() {
+    let mut host = PluginHost::new();
+    host.init_plugin("root".to_string(), static_plugin_with("root", echo_plugin_init, recording_teardown))
+        .expect("Failed to init root");
+    
+    let a = host.register_child_plugin("root", static_plugin_with("a", echo_plugin_init, recording_teardown)).unwrap();
+    let b = host.register_child_plugin("root", static_plugin_with("b", echo_plugin_init, recording_teardown)).unwrap();
+    let a1 = host.register_child_plugin(&a, static_plugin_with("a1", echo_plugin_init, recording_teardown)).unwrap();
+    
+    assert_eq!(host.list_children("root"), vec![a.clone(), b.clone()]);
+    assert_eq!(host.list_children(&a), vec![a1.clone()]);
+    
+    host.teardown_plugin("root").expect("Failed to teardown root");
+    
+    assert_eq!(*TORN_DOWN.lock().unwrap(), vec![a1, a, b, "root".to_string()]);
+    assert!(host.loaded_plugins().is_empty());
+    assert!(host.list_children("root").is_empty());
+}
+
+/// Size of the result produced by `streaming_execute`
+const STREAMED_RESULT_LEN: usize = 1024 * 1024;
+
+/// Streaming handler reporting a 1 MB result in 64 KB pieces
+unsafe extern "C" fn streaming_execute(
+    context: *mut PluginContext,
+    _event_name: *const c_char,
+    _event_data: *const c_char,
+    chunk_cb: PluginChunkCallback,
+) -> c_int {
+    let result: Vec<u8> = (0..STREAMED_RESULT_LEN).map(|i| (i % 251) as u8).collect();
+    for piece in result.chunks(64 * 1024) {
+        chunk_cb(context, piece.as_ptr(), piece.len());
+    }
+    0
+}
+
+#[tokio::test]
+async fn test_streamed_result_arrives_in_bounded_chunks() {
+    let mut host = PluginHost::new();
+    let plugin = static_plugin_with_init("streaming-plugin", echo_plugin_init).with_execute_streaming_fn(streaming_execute);
+    host.init_plugin("streaming-plugin".to_string(), plugin).expect("Failed to init plugin");
+    
+    let chunks: Vec<_> = host.trigger_event_streaming("streaming-plugin", "dump_logs", "{}", 4096)
+        .expect("Failed to start streaming")
+        .collect()
+        .await;
+    
+    let chunks: Vec<_> = chunks.into_iter().map(|chunk| chunk.expect("Chunk failed")).collect();
+    assert_eq!(chunks.len(), STREAMED_RESULT_LEN / 4096);
+    assert!(chunks.iter().all(|chunk| chunk.len() == 4096));
+    
+    let received: Vec<u8> = chunks.concat();
+    assert_eq!(received.len(), STREAMED_RESULT_LEN);
+    assert!(received.iter().enumerate().all(|(i, byte)| *byte == (i % 251) as u8));
+}
+
+#[tokio::test]
+async fn test_streaming_falls_back_to_callback_result() {
+    let mut host = PluginHost::new();
+    host.init_plugin("echo-plugin".to_string(), static_plugin_with_init("echo-plugin", echo_plugin_init))
+        .expect("Failed to init plugin");
+    
+    let items: Vec<_> = host.trigger_event_streaming("echo-plugin", "echo", "abcd", 4096)
+        .expect("Failed to start streaming")
+        .collect()
+        .await;
+    
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].as_ref().unwrap().as_ref(), b"4");
+}
+
+/// Plugin init storing a value and reading it back
+unsafe extern "C" fn storing_plugin_init(context: *mut PluginContext) -> i32 {
+    let (kv_set, kv_get) = match ((*context).kv_set, (*context).kv_get) {
+        (Some(kv_set), Some(kv_get)) => (kv_set, kv_get),
+        _ => return -1,
+    };
+    
+    let key = CString::new("greeting").unwrap();
+    let value = CString::new("hello").unwrap();
+    let result = kv_set(context, key.as_ptr(), value.as_ptr());
+    if result != 0 {
+        return result;
+    }
+    
+    let mut buffer = [0 as c_char; 16];
+    let len = kv_get(context, key.as_ptr(), buffer.as_mut_ptr(), buffer.len() as u32);
+    if len != 5 || CStr::from_ptr(buffer.as_ptr()) != value.as_c_str() {
+        return -100;
+    }
+    
+    0
+}
+
+/// Plugin init trying to read the value stored by another plugin
+unsafe extern "C" fn snooping_plugin_init(context: *mut PluginContext) -> i32 {
+    let kv_get = match (*context).kv_get {
+        Some(kv_get) => kv_get,
+        None => return -1,
+    };
+    
+    let key = CString::new("greeting").unwrap();
+    match kv_get(context, key.as_ptr(), std::ptr::null_mut(), 0) {
+        KV_NOT_FOUND => 0,
+        result => result,
+    }
+}
+
+#[test]
+fn test_plugin_storage_is_namespaced_per_plugin() {
+    let temp_dir = tempdir().unwrap();
+    let writer_dir = temp_dir.path().join("writer");
+    let reader_dir = temp_dir.path().join("reader");
+    let mut host = PluginHost::new();
+    
+    let writer_store = Arc::new(KvStore::open(&writer_dir, KvLimits::default()).unwrap());
+    let plugin = static_plugin_with_init("writer", storing_plugin_init);
+    host.init_plugin_with_storage("writer".to_string(), plugin, Some(writer_store))
+        .expect("Plugin failed to store and read back a value");
+    
+    let reader_store = Arc::new(KvStore::open(&reader_dir, KvLimits::default()).unwrap());
+    let plugin = static_plugin_with_init("reader", snooping_plugin_init);
+    host.init_plugin_with_storage("reader".to_string(), plugin, Some(reader_store))
+        .expect("Plugin could read another plugin's value");
+    
+    // Values persist in the writer's own data directory
+    let reopened = KvStore::open(&writer_dir, KvLimits::default()).unwrap();
+    assert_eq!(reopened.get("greeting").as_deref(), Some("hello"));
+    assert_eq!(KvStore::open(&reader_dir, KvLimits::default()).unwrap().get("greeting"), None);
+    
+    // Without a store the plugin lacks the storage permission
+    match host.init_plugin("denied".to_string(), static_plugin_with_init("denied", storing_plugin_init)) {
+        Err(PluginHostError::InitializationFailed(code)) => assert_eq!(code, KV_NO_PERMISSION),
+        other => panic!("Expected storage to be denied, got {:?}", other.err()),
+    }
+}
+
+/// Callback storing its event data under a fixed key
+unsafe extern "C-unwind" fn storing_callback(context: *mut PluginContext, event_data: *const c_char, _data_len: u32) -> c_int {
+    let key = CString::new("greeting").unwrap();
+    match (*context).kv_set {
+        Some(kv_set) => kv_set(context, key.as_ptr(), event_data),
+        None => -1,
+    }
+}
+
+/// Plugin init registering the storing callback
+unsafe extern "C" fn store_command_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = match (*context).register_callback {
+        Some(register) => register,
+        None => return -1,
+    };
+    
+    let event_name = CString::new("store").unwrap();
+    register(context, event_name.as_ptr(), Some(storing_callback))
+}
+
+#[test]
+fn test_storage_attached_to_running_plugin_is_enforced() {
+    // Arrange: the plugin starts without storage
+    let temp_dir = tempdir().unwrap();
+    let mut host = PluginHost::new();
+    host.init_plugin("late".to_string(), static_plugin_with_init("late", store_command_plugin_init))
+        .expect("Failed to init plugin");
+    assert_eq!(host.trigger_event("late", "store", "hello").unwrap(), KV_NO_PERMISSION);
+    
+    // Act
+    let store = Arc::new(KvStore::open(temp_dir.path(), KvLimits::default()).unwrap());
+    host.attach_storage("late", store.clone()).expect("Failed to attach storage");
+    
+    // Assert: the running plugin can use the store, until it is detached again
+    assert_eq!(host.trigger_event("late", "store", "hello").unwrap(), 0);
+    assert_eq!(store.get("greeting").as_deref(), Some("hello"));
+    
+    host.detach_storage("late").expect("Failed to detach storage");
+    assert_eq!(host.trigger_event("late", "store", "bye").unwrap(), KV_NO_PERMISSION);
+}
+
+#[test]
+fn test_loading_an_already_loaded_plugin_is_rejected() {
+    // Arrange
+    let mut host = PluginHost::new();
+    host.init_plugin("echo-plugin".to_string(), static_plugin_with_init("echo-plugin", echo_plugin_init))
+        .expect("Failed to initialize plugin");
+    
+    // Act
+    let result = host.init_plugin("echo-plugin".to_string(), static_plugin_with_init("echo-plugin", echo_plugin_init));
+    
+    // Assert: the running instance is left in place
+    assert!(matches!(result, Err(PluginHostError::AlreadyLoaded(id)) if id == "echo-plugin"));
+    assert_eq!(host.trigger_event("echo-plugin", "echo", "1234").unwrap(), 4);
+    host.teardown_plugin("echo-plugin").expect("Failed to teardown plugin");
+    assert!(!host.has_plugin("echo-plugin"));
+}
+
+#[test]
+fn test_plugin_storage_enforces_limits() {
+    let temp_dir = tempdir().unwrap();
+    let limits = KvLimits { max_key_bytes: 8, max_value_bytes: 16, quota_bytes: 32 };
+    let store = KvStore::open(temp_dir.path(), limits).unwrap();
+    
+    assert!(matches!(store.set("much-too-long", "value"), Err(KvStoreError::InvalidKey(13))));
+    assert!(matches!(store.set("key", &"x".repeat(17)), Err(KvStoreError::ValueTooLarge(17))));
+    
+    store.set("a", &"x".repeat(15)).unwrap();
+    store.set("b", &"x".repeat(15)).unwrap();
+    assert!(matches!(store.set("c", "x"), Err(KvStoreError::QuotaExceeded { used: 34, quota: 32 })));
+    
+    // Replacing a value only counts the difference
+    store.set("a", "y").unwrap();
+    store.set("c", "x").unwrap();
+    assert_eq!(store.used_bytes(), 20);
+    
+    assert!(store.delete("c").unwrap());
+    assert!(!store.delete("c").unwrap());
+}
+
+#[test]
+fn test_plugin_contexts_are_reused_from_pool() {
+    let mut host = PluginHost::new().with_context_pool_size(1);
+    assert_eq!(host.context_pool().available(), 1);
+    
+    for _ in 0..3 {
+        host.init_plugin("pooled".to_string(), static_plugin_with_init("pooled", echo_plugin_init)).unwrap();
+        assert_eq!(host.context_pool().available(), 0);
+        
+        host.teardown_plugin("pooled").unwrap();
+        assert_eq!(host.context_pool().available(), 1);
+    }
+    assert_eq!(host.context_pool().overflow_allocations(), 0);
+    
+    // A second concurrent plugin overflows the pool
+    host.init_plugin("first".to_string(), static_plugin_with_init("first", echo_plugin_init)).unwrap();
+    host.init_plugin("second".to_string(), static_plugin_with_init("second", echo_plugin_init)).unwrap();
+    assert_eq!(host.context_pool().overflow_allocations(), 1);
+    
+    host.teardown_plugin("first").unwrap();
+    host.teardown_plugin("second").unwrap();
+    assert_eq!(host.context_pool().available(), host.context_pool().capacity());
+}
+
+#[test]
+fn test_released_context_is_blank() {
+    let pool = Arc::new(PluginContextPool::new(1));
+    
+    let mut context = pool.acquire();
+    context.api_version = 1;
+    let address = context.as_ptr();
+    context.release();
+    
+    let context = pool.acquire();
+    assert_eq!(context.as_ptr(), address);
+    assert_eq!(context.api_version, 0);
+    assert!(context.register_callback.is_none());
+}
+
+/// Trace contexts seen by the tracing callback
+static SEEN_TRACE_CONTEXTS: Mutex<Vec<TraceContext>> = Mutex::new(Vec::new());
+
+/// Callback recording the trace context it runs in
+unsafe extern "C-unwind" fn tracing_callback(context: *mut PluginContext, _event_data: *const c_char, _data_len: u32) -> c_int {
+    SEEN_TRACE_CONTEXTS.lock().unwrap().push((*context).trace_context);
+    0
+}
+
+/// Plugin init registering the tracing callback
+unsafe extern "C" fn tracing_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = (*context).register_callback.unwrap();
+    let event_name = CString::new("traced").unwrap();
+    register(context, event_name.as_ptr(), Some(tracing_callback))
+}
+
+#[test]
+fn test_trace_context_round_trips_through_traceparent() {
+    let context = TraceContext::new_root(true);
+    let header = context.to_traceparent();
+    
+    assert_eq!(header.len(), 55);
+    assert!(header.starts_with("00-") && header.ends_with("-01"));
+    assert_eq!(TraceContext::parse_traceparent(&header), Some(context));
+    
+    let example = TraceContext::parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00")
+        .expect("Failed to parse traceparent");
+    assert!(!example.is_sampled());
+    assert_eq!(example.parent_span_id, [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]);
+    
+    assert_eq!(TraceContext::parse_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01"), None);
+    assert_eq!(TraceContext::parse_traceparent("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01"), None);
+    assert_eq!(TraceContext::parse_traceparent("00-4bf92f3577b34da6-00f067aa0ba902b7-01"), None);
+}
+
+#[test]
+fn test_trace_id_is_shared_by_parent_and_child_plugin_calls() {
+    let mut host = PluginHost::with_thread_model(CallbackThreadModel::WorkerThread { queue_capacity: 4 });
+    host.init_plugin("trace-parent".to_string(), static_plugin_with_init("trace-parent", tracing_plugin_init))
+        .expect("Failed to initialize parent plugin");
+    host.init_plugin("trace-child".to_string(), static_plugin_with_init("trace-child", tracing_plugin_init))
+        .expect("Failed to initialize child plugin");
+    host.set_trace_sampler(Box::new(|plugin_id, _event_name| plugin_id != "trace-child"));
+    
+    let host_call = TraceContext::new_root(true);
+    host.trigger_event("trace-parent", "traced", EventEnvelope::new("{}").with_trace_context(host_call))
+        .expect("Failed to trigger parent event");
+    let parent_context = SEEN_TRACE_CONTEXTS.lock().unwrap().pop().expect("Parent callback did not run");
+    
+    // The parent plugin forwards its context to the child call
+    host.trigger_event("trace-child", "traced", EventEnvelope::new("{}").with_trace_context(parent_context))
+        .expect("Failed to trigger child event");
+    let child_context = SEEN_TRACE_CONTEXTS.lock().unwrap().pop().expect("Child callback did not run");
+    
+    assert_eq!(parent_context.trace_id, host_call.trace_id);
+    assert_eq!(child_context.trace_id, host_call.trace_id);
+    assert_ne!(parent_context.parent_span_id, host_call.parent_span_id);
+    assert_ne!(child_context.parent_span_id, parent_context.parent_span_id);
+    assert!(parent_context.is_sampled());
+    assert!(!child_context.is_sampled());
+    
+    // Events without a trace start a new one
+    host.trigger_event("trace-parent", "traced", "{}").expect("Failed to trigger untraced event");
+    let root_context = SEEN_TRACE_CONTEXTS.lock().unwrap().pop().expect("Callback did not run");
+    assert!(root_context.is_valid());
+    assert_ne!(root_context.trace_id, host_call.trace_id);
+}
+
+/// Handle of an event the async callback completes from another thread
+struct SendHandle(*mut AsyncHandle);
+
+unsafe impl Send for SendHandle {}
+
+/// Async callback completing the event with the payload length after a tick
+unsafe extern "C-unwind" fn deferred_callback(
+    context: *mut PluginContext,
+    _event_data: *const c_char,
+    data_len: u32,
+    handle: *mut AsyncHandle,
+) -> c_int {
+    let complete = (*context).complete_async.unwrap();
+    let handle = SendHandle(handle);
+    
+    thread::spawn(move || {
+        let handle = handle;
+        thread::sleep(Duration::from_millis(1));
+        complete(handle.0, data_len as c_int);
+    });
+    0
+}
+
+/// Async callback refusing to start the work
+unsafe extern "C-unwind" fn refusing_async_callback(
+    _context: *mut PluginContext,
+    _event_data: *const c_char,
+    _data_len: u32,
+    _handle: *mut AsyncHandle,
+) -> c_int {
+    -9
+}
+
+/// Async handler registration export of the deferred plugin
+unsafe extern "C" fn register_deferred_handlers(context: *mut PluginContext) -> c_int {
+    let register = (*context).register_async_callback.unwrap();
+    
+    let deferred = CString::new("deferred").unwrap();
+    let refused = CString::new("refused").unwrap();
+    let result = register(context, deferred.as_ptr(), Some(deferred_callback));
+    if result != 0 {
+        return result;
+    }
+    register(context, refused.as_ptr(), Some(refusing_async_callback))
+}
+
+#[tokio::test]
+async fn test_async_handler_completes_after_returning() {
+    for thread_model in [CallbackThreadModel::Synchronous, CallbackThreadModel::WorkerThread { queue_capacity: 4 }] {
+        let mut host = PluginHost::with_thread_model(thread_model);
+        let plugin = static_plugin_with_init("deferred-plugin", echo_plugin_init)
+            .with_register_async_handler_fn(register_deferred_handlers);
+        host.init_plugin("deferred-plugin".to_string(), plugin).expect("Failed to initialize plugin");
+        
+        // The async handler's result arrives through complete_async
+        let pending = host.trigger_event_async("deferred-plugin", "deferred", "12345")
+            .expect("Failed to trigger async event");
+        assert_eq!(pending.await.expect("Async event failed"), 5);
+        
+        // A handler returning nonzero finishes the event with that code
+        let pending = host.trigger_event_async("deferred-plugin", "refused", "{}")
+            .expect("Failed to trigger async event");
+        assert_eq!(pending.await.expect("Async event failed"), -9);
+        
+        // Events without an async handler fall back to the regular callback
+        let pending = host.trigger_event_async("deferred-plugin", "echo", "1234")
+            .expect("Failed to trigger event");
+        assert_eq!(pending.await.expect("Event failed"), 4);
+        
+        // Async handlers are not reachable through regular dispatch
+        assert!(matches!(
+            host.dispatch_event("deferred-plugin", "deferred", "{}"),
+            Err(PluginHostError::InvalidEventName(_))
+        ));
+        
+        host.teardown_plugin("deferred-plugin").expect("Failed to teardown plugin");
+    }
+}
+
+/// Callback panicking instead of handling the event
+unsafe extern "C-unwind" fn panicking_callback(_context: *mut PluginContext, _event_data: *const c_char, _data_len: u32) -> c_int {
+    panic!("callback exploded");
+}
+
+/// Plugin init registering the panicking callback
+unsafe extern "C" fn panicking_plugin_init(context: *mut PluginContext) -> i32 {
+    let register = match (*context).register_callback {
+        Some(register) => register,
+        None => return -1,
+    };
+    
+    let event_name = CString::new("explode").unwrap();
+    register(context, event_name.as_ptr(), Some(panicking_callback))
+}
+
+#[tokio::test]
+async fn test_plugin_panic_is_isolated() {
+    for thread_model in [CallbackThreadModel::Synchronous, CallbackThreadModel::WorkerThread { queue_capacity: 4 }] {
+        let mut host = PluginHost::with_thread_model(thread_model);
+        let panics = Arc::new(Mutex::new(Vec::new()));
+        host.set_panic_listener(Box::new({
+            let panics = panics.clone();
+            move |plugin_panic| panics.lock().unwrap().push(plugin_panic.clone())
+        }));
+        
+        host.init_plugin("panicking-plugin".to_string(), static_plugin_with_init("panicking-plugin", panicking_plugin_init))
+            .expect("Failed to initialize panicking plugin");
+        host.init_plugin("echo-plugin".to_string(), static_plugin_with_init("echo-plugin", echo_plugin_init))
+            .expect("Failed to initialize echo plugin");
+        
+        // The panic is reported as an error instead of unwinding through the host
+        let result = host.dispatch_event("panicking-plugin", "explode", "{}")
+            .expect("Failed to dispatch event")
+            .await;
+        match result {
+            Err(PluginHostError::PluginPanicked(message)) => assert!(message.starts_with("callback exploded at ")),
+            other => panic!("Expected a plugin panic, got {:?}", other),
+        }
+        
+        let panics = panics.lock().unwrap().clone();
+        assert_eq!(panics.len(), 1);
+        assert_eq!(panics[0].plugin_id, "panicking-plugin");
+        
+        // Other plugins keep working, and so does the panicking plugin's worker
+        assert_eq!(host.dispatch_event("echo-plugin", "echo", "1234").unwrap().await.unwrap(), 4);
+        assert!(matches!(
+            host.dispatch_event("panicking-plugin", "explode", "{}").unwrap().await,
+            Err(PluginHostError::PluginPanicked(_))
+        ));
+        
+        host.teardown_plugin("panicking-plugin").expect("Failed to teardown panicking plugin");
+        host.teardown_plugin("echo-plugin").expect("Failed to teardown echo plugin");
+    }
+}
@@ -0,0 +1,431 @@
+//! Plugin loader tests
+
+use tauri_windows_plugin_system::plugin_loader::{
+    AbiCoverageAnalyzer, CompatibilityIssue, CompatibilityReport, DllArchitecture, ExtendedVersionReq, PluginLoadError, PluginLoader, PluginManifest, PluginMetadata,
+};
+use chrono::Utc;
+use semver::Version;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Parse a version literal
+fn version(text: &str) -> Version {
+    Version::parse(text).unwrap()
+}
+
+#[test]
+fn test_extended_version_req_matches_any_clause() {
+    let req: ExtendedVersionReq = "^1.0 || ^2.0".parse().expect("Failed to parse requirement");
+    
+    assert_eq!(req.clauses().len(), 2);
+    assert!(req.matches(&version("1.5.0")));
+    assert!(req.matches(&version("2.0.0")));
+    assert!(!req.matches(&version("3.0.0")));
+}
+
+#[test]
+fn test_extended_version_req_gap_range() {
+    let req = ExtendedVersionReq::parse(">=1.0, <2.0 || >=3.0").expect("Failed to parse requirement");
+    
+    assert!(req.matches(&version("1.9.9")));
+    assert!(!req.matches(&version("2.5.0")));
+    assert!(req.matches(&version("3.1.0")));
+    assert_eq!(req.to_string(), ">=1.0, <2.0 || >=3.0");
+}
+
+#[test]
+fn test_extended_version_req_rejects_malformed_input() {
+    for text in ["", "^1.0 ||", "|| ^2.0", "^1.0 |||| ^2.0", "^1.0 || banana"] {
+        let result = ExtendedVersionReq::parse(text);
+        assert!(matches!(result, Err(PluginLoadError::InvalidVersionReq(_))), "accepted '{}'", text);
+    }
+}
+
+/// Write a minimal PE image stub for the given machine type
+fn write_pe_stub(path: &Path, machine: u16) {
+    let mut image = vec![0u8; 0x48];
+    image[..2].copy_from_slice(b"MZ");
+    image[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+    image[0x40..0x44].copy_from_slice(b"PE\0\0");
+    image[0x44..0x46].copy_from_slice(&machine.to_le_bytes());
+    std::fs::write(path, image).unwrap();
+}
+
+#[test]
+fn test_compatibility_report_distinguishes_major_and_minor_mismatches() {
+    let major = CompatibilityReport::check_against("2.0.0", "1.3.0");
+    assert_eq!(major.issue, Some(CompatibilityIssue::MajorMismatch));
+    assert_eq!(
+        major.reason().unwrap(),
+        "major API version mismatch: plugin requires API 2.0.0, host provides API 1.3.0"
+    );
+    
+    let minor = CompatibilityReport::check_against("1.4.0", "1.3.0");
+    assert_eq!(minor.issue, Some(CompatibilityIssue::MinorTooNew));
+    assert_eq!(
+        minor.reason().unwrap(),
+        "plugin requires newer API minor version 1.4.0, host provides API 1.3.0; update the host"
+    );
+    
+    let older_minor = CompatibilityReport::check_against("1.2.5", "1.3.0");
+    assert!(older_minor.is_compatible());
+    assert_eq!(older_minor.reason(), None);
+    
+    let invalid = CompatibilityReport::check_against("one", "1.3.0");
+    assert!(matches!(invalid.issue, Some(CompatibilityIssue::InvalidVersion(_))));
+    assert_eq!(invalid.required_api_version, "one");
+    assert_eq!(invalid.host_api_version, "1.3.0");
+}
+
+#[test]
+fn test_dll_for_other_architecture_is_rejected() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let host_arch = DllArchitecture::host().expect("Unsupported host architecture");
+    let (machine, plugin_arch) = match host_arch {
+        DllArchitecture::X86 => (0x8664, "x64"),
+        _ => (0x014C, "x86"),
+    };
+    
+    let dll_path = temp_dir.path().join("plugin.dll");
+    write_pe_stub(&dll_path, machine);
+    assert_eq!(DllArchitecture::of_file(&dll_path).unwrap(), Some(DllArchitecture::from_machine(machine)));
+    
+    let metadata = PluginMetadata {
+        manifest: PluginManifest {
+            name: "wrong-arch".to_string(),
+            version: "1.0.0".to_string(),
+            entry: "plugin.dll".to_string(),
+            api_version: "1.0.0".to_string(),
+            permissions: Vec::new(),
+            description: "Plugin built for another architecture".to_string(),
+            author: "Test Author".to_string(),
+            homepage: None,
+            resource_limits: None,
+            provides_capabilities: Vec::new(),
+            required_host_features: Vec::new(),
+            total_size_bytes: None,
+            file_count: None,
+        },
+        install_path: temp_dir.path().to_path_buf(),
+        dll_path,
+        installed_at: Utc::now(),
+    };
+    
+    let loader = PluginLoader::new(temp_dir.path().join("extract"));
+    match loader.load_plugin_dll(&metadata) {
+        Err(PluginLoadError::Incompatible(message)) => {
+            assert_eq!(message, format!("architecture mismatch: plugin is {}, host is {}", plugin_arch, host_arch));
+        },
+        Err(e) => panic!("Unexpected error: {}", e),
+        Ok(_) => panic!("Loaded a DLL built for another architecture"),
+    }
+}
+
+/// Section data of a hand-built PE32+ image, mapped at `SECTION_RVA`
+struct SectionBuilder {
+    /// Raw section bytes
+    data: Vec<u8>,
+}
+
+/// Relative virtual address of the single section
+const SECTION_RVA: u32 = 0x1000;
+
+impl SectionBuilder {
+    /// Append bytes aligned to 8 and get their RVA
+    fn push(&mut self, bytes: &[u8]) -> u32 {
+        self.data.resize(self.data.len().next_multiple_of(8), 0);
+        let rva = SECTION_RVA + self.data.len() as u32;
+        self.data.extend_from_slice(bytes);
+        rva
+    }
+    
+    /// Append a NUL-terminated string and get its RVA
+    fn push_str(&mut self, text: &str) -> u32 {
+        self.push(&[text.as_bytes(), &[0]].concat())
+    }
+}
+
+/// Build a minimal x64 DLL with the given exports and imports per library
+fn build_pe_dll(exports: &[&str], imports: &[(&str, &[&str])]) -> Vec<u8> {
+    let mut section = SectionBuilder { data: Vec::new() };
+    let code_rva = section.push(&[0xC3; 16]);
+    
+    // Export table; names must be sorted for lookup by name
+    let mut names = exports.to_vec();
+    names.sort();
+    let dll_name_rva = section.push_str("plugin.dll");
+    let name_rvas: Vec<u32> = names.iter().map(|name| section.push_str(name)).collect();
+    let names_rva = section.push(&name_rvas.iter().flat_map(|rva| rva.to_le_bytes()).collect::<Vec<_>>());
+    let ordinals_rva = section.push(&(0..names.len() as u16).flat_map(|i| i.to_le_bytes()).collect::<Vec<_>>());
+    let functions_rva = section.push(&names.iter().flat_map(|_| code_rva.to_le_bytes()).collect::<Vec<_>>());
+    let mut export_directory = vec![0u8; 12];
+    for value in [dll_name_rva, 1, names.len() as u32, names.len() as u32, functions_rva, names_rva, ordinals_rva] {
+        export_directory.extend_from_slice(&value.to_le_bytes());
+    }
+    let export_rva = section.push(&export_directory);
+    let export_size = export_directory.len() as u32;
+    
+    // Import descriptors with one lookup table per library
+    let mut descriptors = Vec::new();
+    for (library, symbols) in imports {
+        let hint_rvas: Vec<u64> = symbols.iter()
+            .map(|symbol| section.push(&[&[0, 0], symbol.as_bytes(), &[0]].concat()) as u64)
+            .collect();
+        let thunks: Vec<u8> = hint_rvas.iter().chain([&0]).flat_map(|rva| rva.to_le_bytes()).collect();
+        let lookup_rva = section.push(&thunks);
+        let address_rva = section.push(&thunks);
+        let library_rva = section.push_str(library);
+        for value in [lookup_rva, 0, 0, library_rva, address_rva] {
+            descriptors.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    descriptors.extend_from_slice(&[0; 20]);
+    let import_rva = section.push(&descriptors);
+    let import_size = descriptors.len() as u32;
+    
+    let raw_size = (section.data.len() as u32).next_multiple_of(0x200);
+    section.data.resize(raw_size as usize, 0);
+    
+    let mut image = vec![0u8; 0x200];
+    let mut put = |offset: usize, bytes: &[u8]| image[offset..offset + bytes.len()].copy_from_slice(bytes);
+    
+    // DOS header, PE signature, and COFF header
+    put(0, b"MZ");
+    put(0x3C, &0x40u32.to_le_bytes());
+    put(0x40, b"PE\0\0");
+    put(0x44, &0x8664u16.to_le_bytes());
+    put(0x46, &1u16.to_le_bytes());
+    put(0x54, &240u16.to_le_bytes());
+    put(0x56, &0x2022u16.to_le_bytes());
+    
+    // PE32+ optional header
+    let optional = 0x58;
+    put(optional, &0x20Bu16.to_le_bytes());
+    put(optional + 24, &0x1_8000_0000u64.to_le_bytes());
+    put(optional + 32, &0x1000u32.to_le_bytes());
+    put(optional + 36, &0x200u32.to_le_bytes());
+    put(optional + 40, &6u16.to_le_bytes());
+    put(optional + 48, &6u16.to_le_bytes());
+    put(optional + 56, &(SECTION_RVA + raw_size.next_multiple_of(0x1000)).to_le_bytes());
+    put(optional + 60, &0x200u32.to_le_bytes());
+    put(optional + 68, &2u16.to_le_bytes());
+    put(optional + 108, &16u32.to_le_bytes());
+    put(optional + 112, &export_rva.to_le_bytes());
+    put(optional + 116, &export_size.to_le_bytes());
+    put(optional + 120, &import_rva.to_le_bytes());
+    put(optional + 124, &import_size.to_le_bytes());
+    
+    // Section header
+    let header = optional + 240;
+    put(header, b".data\0\0\0");
+    put(header + 8, &raw_size.to_le_bytes());
+    put(header + 12, &SECTION_RVA.to_le_bytes());
+    put(header + 16, &raw_size.to_le_bytes());
+    put(header + 20, &0x200u32.to_le_bytes());
+    put(header + 36, &0xC000_0040u32.to_le_bytes());
+    
+    image.extend_from_slice(&section.data);
+    image
+}
+
+#[test]
+fn test_abi_coverage_reports_exports_and_foreign_imports() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dll_path = temp_dir.path().join("plugin.dll");
+    let image = build_pe_dll(
+        &["plugin_init", "plugin_teardown"],
+        &[("KERNEL32.dll", &["GetLastError"]), ("helper.dll", &["helper_run"])],
+    );
+    std::fs::write(&dll_path, image).unwrap();
+    
+    let report = AbiCoverageAnalyzer::analyze(&dll_path).expect("Failed to analyze DLL");
+    
+    assert!(report.used_features.contains(&"plugin_init".to_string()));
+    assert_eq!(report.used_features, vec!["plugin_init", "plugin_teardown"]);
+    assert_eq!(report.unused_features, vec!["plugin_execute_streaming", "plugin_register_async_handler"]);
+    assert_eq!(report.unknown_imports, vec!["helper.dll!helper_run"]);
+    assert!((report.coverage_percent - 50.0).abs() < 0.01);
+}
+
+#[test]
+fn test_abi_coverage_rejects_non_pe_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dll_path = temp_dir.path().join("plugin.dll");
+    std::fs::write(&dll_path, b"not a dll").unwrap();
+    
+    assert!(matches!(AbiCoverageAnalyzer::analyze(&dll_path), Err(PluginLoadError::DllLoadFailed(_))));
+}
+
+/// Directory holding the manifest fuzzer's seed corpus
+fn manifest_corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/fuzz_manifest")
+}
+
+#[test]
+fn test_manifest_parser_rejects_fuzz_seeds_without_panicking() {
+    let mut seeds: Vec<PathBuf> = std::fs::read_dir(manifest_corpus_dir())
+        .expect("Failed to read seed corpus")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    seeds.sort();
+    assert!(!seeds.is_empty());
+    
+    for seed in &seeds {
+        let name = seed.file_name().unwrap().to_string_lossy().into_owned();
+        let result = PluginLoader::parse_manifest(&std::fs::read(seed).unwrap());
+        
+        // Only valid manifests and values the parser does not judge are accepted
+        let accepted = name.starts_with("valid") || name == "negative_version.json" || name == "long_strings.json";
+        assert_eq!(result.is_ok(), accepted, "Unexpected result for {}: {:?}", name, result.err());
+    }
+}
+
+#[test]
+fn test_truncated_manifest_is_rejected() {
+    let manifest = std::fs::read(manifest_corpus_dir().join("valid.json")).unwrap();
+    
+    for len in 0..manifest.len() {
+        assert!(PluginLoader::parse_manifest(&manifest[..len]).is_err(), "Accepted manifest truncated to {} bytes", len);
+    }
+}
+
+/// Write a ZIP archive with the given entries
+fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, contents) in entries {
+        zip.start_file(*name, options).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+/// Get the entries of the extract base directory
+fn extracted_entries(extract_dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(extract_dir).unwrap().map(|entry| entry.unwrap().path()).collect()
+}
+
+#[tokio::test]
+async fn test_failed_extraction_removes_partial_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let extract_dir = temp_dir.path().join("extract");
+    let loader = PluginLoader::new(extract_dir.clone());
+    
+    // The second entry cannot be written because its parent is a file written by the first
+    let package_path = temp_dir.path().join("broken.zip");
+    write_zip(&package_path, &[("plugin.json", b"{}"), ("plugin.json/plugin.dll", b"not a real DLL")]);
+    
+    match loader.load_plugin_package(&package_path).await {
+        Err(PluginLoadError::ExtractFailed(_)) => {},
+        Err(e) => panic!("Unexpected error: {}", e),
+        Ok(_) => panic!("Loaded a package that failed to extract"),
+    }
+    assert!(extracted_entries(&extract_dir).is_empty());
+}
+
+#[tokio::test]
+async fn test_rejected_package_leaves_no_extracted_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let extract_dir = temp_dir.path().join("extract");
+    let loader = PluginLoader::new(extract_dir.clone());
+    
+    let package_path = temp_dir.path().join("invalid.zip");
+    write_zip(&package_path, &[("plugin.json", b"not json"), ("plugin.dll", b"not a real DLL")]);
+    assert!(loader.load_plugin_package(&package_path).await.is_err());
+    assert!(loader.load_plugin_bytes(&std::fs::read(&package_path).unwrap()).is_err());
+    assert!(extracted_entries(&extract_dir).is_empty());
+    
+    // Accepted packages keep their extracted files
+    let manifest = serde_json::json!({
+        "name": "valid",
+        "version": "1.0.0",
+        "entry": "plugin.dll",
+        "api_version": "1.0.0",
+        "permissions": [],
+        "description": "Test plugin",
+        "author": "Test Author"
+    });
+    write_zip(&package_path, &[("plugin.json", manifest.to_string().as_bytes()), ("plugin.dll", b"not a real DLL")]);
+    let metadata = loader.load_plugin_package(&package_path).await.expect("Failed to load package");
+    assert_eq!(extracted_entries(&extract_dir), vec![metadata.install_path.clone()]);
+    assert!(metadata.dll_path.exists());
+}
+
+#[test]
+fn test_inspect_package_reads_manifest_without_extracting() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let manifest = serde_json::json!({
+        "name": "preview",
+        "version": "2.1.0",
+        "entry": "plugin.dll",
+        "api_version": "1.0.0",
+        "permissions": [],
+        "description": "Previewed plugin",
+        "author": "Test Author"
+    });
+    let manifest = manifest.to_string();
+    let package_path = temp_dir.path().join("preview.zip");
+    write_zip(&package_path, &[
+        ("plugin.json", manifest.as_bytes()),
+        ("plugin.dll", b"not a real DLL"),
+        ("assets/icon.png", b"icon"),
+    ]);
+    
+    let inspection = PluginLoader::inspect_package(&package_path).expect("Failed to inspect package");
+    
+    assert_eq!(inspection.manifest.name, "preview");
+    assert_eq!(inspection.manifest.version, "2.1.0");
+    assert_eq!(inspection.file_count, 3);
+    
+    // Stored entries are as large compressed as uncompressed
+    let expected_size = (manifest.len() + "not a real DLL".len() + "icon".len()) as u64;
+    assert_eq!(inspection.total_uncompressed_size, expected_size);
+    assert_eq!(inspection.total_compressed_size, expected_size);
+    
+    let expected_hash = format!("{:x}", Sha256::digest(std::fs::read(&package_path).unwrap()));
+    assert_eq!(inspection.package_hash, expected_hash);
+    assert!(!inspection.has_signature);
+    
+    // Nothing but the package itself was written
+    assert_eq!(extracted_entries(temp_dir.path()), vec![package_path]);
+}
+
+#[tokio::test]
+async fn test_package_not_matching_declared_contents_is_rejected() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let extract_dir = temp_dir.path().join("extract");
+    let loader = PluginLoader::new(extract_dir.clone());
+    let mut manifest = serde_json::json!({
+        "name": "declared",
+        "version": "1.0.0",
+        "entry": "plugin.dll",
+        "api_version": "1.0.0",
+        "permissions": [],
+        "description": "Test plugin",
+        "author": "Test Author",
+        "file_count": 3
+    });
+    
+    // A file went missing from the package
+    let package_path = temp_dir.path().join("truncated.zip");
+    write_zip(&package_path, &[("plugin.json", manifest.to_string().as_bytes()), ("plugin.dll", b"not a real DLL")]);
+    match loader.load_plugin_package(&package_path).await {
+        Err(PluginLoadError::IntegrityMismatch(reason)) => {
+            assert_eq!(reason, "manifest declares 3 files, package contains 2");
+        },
+        Err(e) => panic!("Unexpected error: {}", e),
+        Ok(_) => panic!("Loaded a package missing a declared file"),
+    }
+    assert!(extracted_entries(&extract_dir).is_empty());
+    
+    // The declared size is checked too, within the tolerance
+    manifest["file_count"] = serde_json::json!(2);
+    manifest["total_size_bytes"] = serde_json::json!(100_000);
+    write_zip(&package_path, &[("plugin.json", manifest.to_string().as_bytes()), ("plugin.dll", b"not a real DLL")]);
+    assert!(matches!(loader.load_plugin_package(&package_path).await, Err(PluginLoadError::IntegrityMismatch(_))));
+    
+    manifest["total_size_bytes"] = serde_json::json!(300);
+    write_zip(&package_path, &[("plugin.json", manifest.to_string().as_bytes()), ("plugin.dll", b"not a real DLL")]);
+    loader.load_plugin_package(&package_path).await.expect("Failed to load package matching its declaration");
+}
@@ -0,0 +1,1206 @@
+//! Plugin manager tests using generated plugin packages
+
+use tauri_windows_plugin_system::plugin_loader::{host_features, PluginLoadError, PluginManifest, ResourceLimits, ResourceProfile};
+use tauri_windows_plugin_system::plugin_host::FEATURE_FLAGS_EVENT;
+use tauri_windows_plugin_system::plugin_manager::{
+    AvailableVersion, ConflictResolution, DataDirQuotaEnforcer, DesiredState, DiagnosticsRedactor, ManifestDiff, PluginError, PluginEventEmitter, PluginInstallError, PluginManager,
+    PluginDataUsage, OverflowPolicy, PluginSource, PluginStatus, PluginUpdateError, QueuedEventEmitter, QuotaAction, QuotaStatus,
+    RegistryFormat, UpdateSource, DEFAULT_QUARANTINE_THRESHOLD, MAX_IN_MEMORY_PACKAGE_BYTES, REDACTED, REDACTED_PATH,
+};
+use tauri_windows_plugin_system::permission_system::{
+    FileSystemPermission, Permission, PermissionError, PermissionSystem, NetworkPermission, VendorPolicy, VendorTrustLevel,
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use tempfile::{tempdir, TempDir};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use zip::write::FileOptions;
+
+/// Write a plugin package containing the given manifest and a dummy DLL
+fn create_package(path: &Path, manifest: &serde_json::Value) {
+    let file = File::create(path).expect("Failed to create package");
+    let mut zip = zip::ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    
+    zip.start_file("plugin.json", options).unwrap();
+    zip.write_all(manifest.to_string().as_bytes()).unwrap();
+    
+    zip.start_file("plugin.dll", options).unwrap();
+    zip.write_all(b"not a real DLL").unwrap();
+    
+    zip.finish().unwrap();
+}
+
+/// Build a valid manifest for a test plugin
+fn manifest(name: &str, version: &str) -> serde_json::Value {
+    serde_json::json!({
+        "name": name,
+        "version": version,
+        "entry": "plugin.dll",
+        "api_version": "1.0.0",
+        "permissions": [],
+        "description": "Test plugin",
+        "author": "Test Author"
+    })
+}
+
+/// Create a plugin manager in a fresh temporary directory
+fn create_manager() -> (TempDir, PluginManager) {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        Arc::new(PermissionSystem::new()),
+    ).expect("Failed to create plugin manager");
+    
+    (temp_dir, manager)
+}
+
+/// Install a test plugin from a generated package
+async fn install(manager: &PluginManager, dir: &Path, manifest: &serde_json::Value) -> String {
+    let package_path: PathBuf = dir.join(format!("{}.zip", manifest["name"].as_str().unwrap()));
+    create_package(&package_path, manifest);
+    
+    manager.install_plugin(PluginSource::File(package_path)).await
+        .expect("Failed to install plugin")
+        .id
+}
+
+/// Update source returning a fixed list of versions
+struct MockUpdateSource(Vec<AvailableVersion>);
+
+impl UpdateSource for MockUpdateSource {
+    fn available_versions(&self, _plugin_id: &str, _plugin_name: &str) -> Result<Vec<AvailableVersion>, PluginUpdateError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Build an available version entry
+fn available(version: &str, api_version: &str, min_host_version: Option<&str>) -> AvailableVersion {
+    AvailableVersion {
+        version: version.to_string(),
+        api_version: api_version.to_string(),
+        min_host_version: min_host_version.map(str::to_string),
+        changelog: Some(format!("Changes in {}", version)),
+        permissions: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_check_update_skips_incompatible_versions() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("updatable", "1.0.0")).await;
+    
+    let mut compatible = available("1.1.0", "1.0.0", Some("0.1.0"));
+    compatible.permissions.push(Permission::Network(NetworkPermission {
+        allowed_hosts: vec!["api.example.com".to_string()],
+    }));
+    
+    manager.set_update_source(MockUpdateSource(vec![
+        available("0.9.0", "1.0.0", None),
+        compatible,
+        available("1.2.0", "1.0.0", Some("99.0.0")),
+        available("2.0.0", "2.0.0", None),
+    ]));
+    
+    // Act
+    let update = manager.check_update(&plugin_id).await.expect("Failed to check update");
+    
+    // Assert
+    let update = update.expect("Expected an update");
+    assert_eq!(update.current_version, "1.0.0");
+    assert_eq!(update.version, "1.1.0");
+    assert_eq!(update.changelog.as_deref(), Some("Changes in 1.1.0"));
+    assert_eq!(update.new_permissions.len(), 1);
+}
+
+#[tokio::test]
+async fn test_check_update_without_newer_version() {
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("current", "1.0.0")).await;
+    
+    manager.set_update_source(MockUpdateSource(vec![available("1.0.0", "1.0.0", None)]));
+    
+    let update = manager.check_update(&plugin_id).await.expect("Failed to check update");
+    assert!(update.is_none());
+}
+
+/// Event emitter collecting emitted events
+#[derive(Clone, Default)]
+struct CollectingEmitter(Arc<Mutex<Vec<(String, serde_json::Value)>>>);
+
+impl PluginEventEmitter for CollectingEmitter {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        self.0.lock().unwrap().push((event.to_string(), payload));
+    }
+}
+
+/// Overwrite the first byte of an installed plugin file
+fn tamper(manager: &PluginManager, plugin_id: &str, file: &str) {
+    let path = manager.get_plugin(plugin_id).unwrap().install_path.join(file);
+    let mut contents = std::fs::read(&path).unwrap();
+    contents[0] ^= 0xff;
+    std::fs::write(&path, contents).unwrap();
+}
+
+#[tokio::test]
+async fn test_verify_plugin_detects_modified_files() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("verified", "1.0.0")).await;
+    
+    let report = manager.integrity_monitor().verify_plugin(&plugin_id).expect("Failed to verify plugin");
+    assert!(report.ok);
+    
+    // Act
+    tamper(&manager, &plugin_id, "plugin.dll");
+    let report = manager.integrity_monitor().verify_plugin(&plugin_id).expect("Failed to verify plugin");
+    
+    // Assert
+    assert!(!report.ok);
+    assert_eq!(report.modified, vec!["plugin.dll".to_string()]);
+    assert!(report.missing.is_empty());
+    assert!(report.added.is_empty());
+}
+
+/// Mark every installed plugin enabled without loading the dummy DLLs
+fn reopen_with_all_enabled(temp_dir: &TempDir, manager: PluginManager) -> PluginManager {
+    reopen_with_all_enabled_using(temp_dir, manager, Arc::new(PermissionSystem::new()))
+}
+
+/// Mark every installed plugin enabled, reopening the manager with the given permission system
+fn reopen_with_all_enabled_using(
+    temp_dir: &TempDir,
+    manager: PluginManager,
+    permission_system: Arc<PermissionSystem>,
+) -> PluginManager {
+    drop(manager);
+    
+    let registry_path = temp_dir.path().join("registry.json");
+    let registry = std::fs::read_to_string(&registry_path).unwrap();
+    std::fs::write(&registry_path, registry.replace("\"Disabled\"", "\"Enabled\"")).unwrap();
+    
+    PluginManager::new(
+        temp_dir.path().join("plugins"),
+        registry_path,
+        permission_system,
+    ).expect("Failed to create plugin manager")
+}
+
+#[tokio::test]
+async fn test_periodic_integrity_check_emits_violation() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("monitored", "1.0.0")).await;
+    let manager = reopen_with_all_enabled(&temp_dir, manager);
+    
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    tamper(&manager, &plugin_id, "plugin.dll");
+    
+    // Act
+    let handle = manager.start_periodic_integrity_check(Duration::from_millis(20));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    handle.abort();
+    
+    // Assert
+    let events = emitter.0.lock().unwrap();
+    let (event, payload) = events.first().expect("Expected an integrity violation event");
+    assert_eq!(event, "plugin-integrity-violation");
+    assert_eq!(payload["plugin_id"], plugin_id.as_str());
+    assert_eq!(payload["report"]["modified"][0], "plugin.dll");
+}
+
+#[tokio::test]
+async fn test_manifest_resource_limits_are_clamped() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let mut limited = manifest("limited", "1.0.0");
+    limited["resource_limits"] = serde_json::json!({ "max_cpu_percent": 50, "max_memory_mb": 512 });
+    let plugin_id = install(&manager, temp_dir.path(), &limited).await;
+    
+    // Act: lower the host maxima after installation
+    manager.set_max_resource_limits(ResourceLimits { max_cpu_percent: 25, max_memory_mb: 1024, data_dir_quota_bytes: None });
+    let limits = manager.effective_resource_limits(&plugin_id).expect("Failed to resolve limits");
+    
+    // Assert
+    assert_eq!(limits, ResourceLimits { max_cpu_percent: 25, max_memory_mb: 512, data_dir_quota_bytes: None });
+    
+    // Host overrides replace the manifest suggestion but are clamped too
+    manager.set_resource_limits(&plugin_id, ResourceLimits { max_cpu_percent: 10, max_memory_mb: 4096, data_dir_quota_bytes: None });
+    let limits = manager.effective_resource_limits(&plugin_id).expect("Failed to resolve limits");
+    assert_eq!(limits, ResourceLimits { max_cpu_percent: 10, max_memory_mb: 1024, data_dir_quota_bytes: None });
+}
+
+#[tokio::test]
+async fn test_resource_profile_follows_vendor_trust() {
+    // Arrange
+    let temp_dir = tempdir().unwrap();
+    let mut permission_system = PermissionSystem::new();
+    permission_system.set_vendor_verifier(Box::new(|_, install_path| {
+        let signed = install_path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("trusted"));
+        signed.then(|| "Acme".to_string())
+    }));
+    let permission_system = Arc::new(permission_system);
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        permission_system.clone(),
+    ).unwrap();
+    
+    let mut trusted = manifest("trusted", "1.0.0");
+    trusted["author"] = serde_json::json!("Acme");
+    let trusted_id = install(&manager, temp_dir.path(), &trusted).await;
+    let mut spoofed = manifest("spoofed", "1.0.0");
+    spoofed["author"] = serde_json::json!("Acme");
+    let spoofed_id = install(&manager, temp_dir.path(), &spoofed).await;
+    let unknown_id = install(&manager, temp_dir.path(), &manifest("unknown", "1.0.0")).await;
+    
+    // Act
+    permission_system.register_vendor_policy(VendorPolicy {
+        vendor_id: "Acme".to_string(),
+        auto_grant_permissions: Vec::new(),
+        trust_level: VendorTrustLevel::Trusted,
+    });
+    
+    // Assert: plugins from unverified vendors or vendors without a policy are held to the strict profile
+    assert_eq!(manager.resource_profile(&trusted_id).unwrap(), ResourceProfile::Trusted);
+    assert_eq!(manager.effective_resource_limits(&trusted_id).unwrap(), ResourceLimits::trusted());
+    assert_eq!(manager.resource_profile(&spoofed_id).unwrap(), ResourceProfile::Strict);
+    assert_eq!(manager.resource_profile(&unknown_id).unwrap(), ResourceProfile::Strict);
+    assert_eq!(manager.effective_resource_limits(&unknown_id).unwrap(), ResourceLimits::strict());
+    
+    // Host overrides take precedence over the profile
+    let limits = ResourceLimits { max_cpu_percent: 80, max_memory_mb: 768, data_dir_quota_bytes: None };
+    manager.set_resource_limits(&unknown_id, limits);
+    assert_eq!(manager.effective_resource_limits(&unknown_id).unwrap(), limits);
+}
+
+#[tokio::test]
+async fn test_manifest_resource_limits_above_host_max_are_rejected() {
+    let (temp_dir, manager) = create_manager();
+    let mut greedy = manifest("greedy", "1.0.0");
+    greedy["resource_limits"] = serde_json::json!({ "max_cpu_percent": 50, "max_memory_mb": 8192 });
+    
+    let package_path = temp_dir.path().join("greedy.zip");
+    create_package(&package_path, &greedy);
+    
+    let result = manager.install_plugin(PluginSource::File(package_path)).await;
+    assert!(matches!(result, Err(PluginInstallError::ResourceLimitsExceeded(_))));
+}
+
+#[tokio::test]
+async fn test_install_from_unpacked_directory() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_dir = temp_dir.path().join("dev-plugin");
+    std::fs::create_dir_all(&plugin_dir).unwrap();
+    std::fs::write(plugin_dir.join("plugin.json"), manifest("dev-plugin", "0.1.0").to_string()).unwrap();
+    std::fs::write(plugin_dir.join("plugin.dll"), b"not a real DLL").unwrap();
+    
+    // Act
+    let plugin = manager.install_plugin(PluginSource::Directory(plugin_dir.clone())).await
+        .expect("Failed to install plugin");
+    
+    // Assert: the plugin is used in place
+    assert!(plugin.dev);
+    assert_eq!(plugin.install_path, plugin_dir);
+    assert!(!temp_dir.path().join("plugins").join(&plugin.id).exists());
+    
+    // Enabling loads the DLL straight from the source directory
+    let result = manager.enable_plugin(&plugin.id).await;
+    assert!(matches!(result, Err(PluginError::LoadError(_))));
+    assert_eq!(manager.get_plugin(&plugin.id).unwrap().status, PluginStatus::Disabled);
+    
+    manager.disable_plugin(&plugin.id).await.expect("Failed to disable plugin");
+    
+    // Uninstalling leaves the source directory alone
+    manager.uninstall_plugin(&plugin.id).await.expect("Failed to uninstall plugin");
+    assert!(manager.get_plugin(&plugin.id).is_none());
+    assert!(plugin_dir.join("plugin.json").exists());
+}
+
+#[tokio::test]
+async fn test_installed_size_matches_files_on_disk() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let manifest = manifest("size-plugin", "1.0.0");
+    let expected_size = (manifest.to_string().len() + b"not a real DLL".len()) as u64;
+    
+    // Act
+    let plugin_id = install(&manager, temp_dir.path(), &manifest).await;
+    
+    // Assert
+    let plugin = manager.get_plugin(&plugin_id).unwrap();
+    assert_eq!(plugin.installed_size_bytes, expected_size);
+    assert_eq!(plugin.file_count, 2);
+    
+    // Files added after installation are picked up on recompute
+    std::fs::write(plugin.install_path.join("cache.bin"), [0u8; 100]).unwrap();
+    let plugin = manager.recompute_size(&plugin_id).expect("Failed to recompute size");
+    assert_eq!(plugin.installed_size_bytes, expected_size + 100);
+    assert_eq!(plugin.file_count, 3);
+    assert_eq!(manager.get_all_plugins()[0].installed_size_bytes, expected_size + 100);
+}
+
+#[tokio::test]
+async fn test_ipc_request_dispatches_to_manager() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("scripted", "1.0.0")).await;
+    
+    // Act
+    let list = manager.handle_ipc_request(r#"{"jsonrpc":"2.0","id":1,"method":"list"}"#).await;
+    let info = manager.handle_ipc_request(
+        &serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "info", "params": { "plugin_id": plugin_id } }).to_string()
+    ).await;
+    let unknown = manager.handle_ipc_request(r#"{"jsonrpc":"2.0","id":3,"method":"reboot"}"#).await;
+    let missing = manager.handle_ipc_request(r#"{"jsonrpc":"2.0","id":4,"method":"enable","params":{}}"#).await;
+    
+    // Assert
+    let list: serde_json::Value = serde_json::from_str(&list).unwrap();
+    assert_eq!(list["id"], 1);
+    assert_eq!(list["result"][0]["id"], plugin_id.as_str());
+    
+    let info: serde_json::Value = serde_json::from_str(&info).unwrap();
+    assert_eq!(info["result"]["name"], "scripted");
+    
+    let unknown: serde_json::Value = serde_json::from_str(&unknown).unwrap();
+    assert_eq!(unknown["error"]["code"], -32601);
+    assert!(unknown.get("result").is_none());
+    
+    let missing: serde_json::Value = serde_json::from_str(&missing).unwrap();
+    assert_eq!(missing["error"]["code"], -32602);
+}
+
+/// Write a data file with the given size and age in seconds
+fn write_data_file(dir: &Path, name: &str, size: usize, age_secs: u64) {
+    std::fs::create_dir_all(dir).unwrap();
+    let mut file = File::create(dir.join(name)).unwrap();
+    file.write_all(&vec![0u8; size]).unwrap();
+    file.set_modified(SystemTime::now() - Duration::from_secs(age_secs)).unwrap();
+}
+
+#[test]
+fn test_quota_eviction_removes_oldest_files() {
+    // Arrange
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let enforcer = DataDirQuotaEnforcer::new(temp_dir.path().to_path_buf());
+    let data_dir = enforcer.data_dir("busy-plugin");
+    write_data_file(&data_dir, "oldest.bin", 400, 300);
+    write_data_file(&data_dir, "older.bin", 400, 200);
+    write_data_file(&data_dir.join("cache"), "newest.bin", 400, 100);
+    
+    assert_eq!(enforcer.get_usage("busy-plugin"), 1200);
+    assert!(enforcer.check_quota("busy-plugin", 1000).is_exceeded());
+    
+    // Act
+    let evicted = enforcer.evict_to_quota("busy-plugin", 500).expect("Failed to evict files");
+    
+    // Assert
+    assert_eq!(evicted, vec![data_dir.join("oldest.bin"), data_dir.join("older.bin")]);
+    assert!(data_dir.join("cache").join("newest.bin").exists());
+    assert_eq!(
+        enforcer.check_quota("busy-plugin", 500),
+        QuotaStatus::WithinQuota { used_bytes: 400, limit_bytes: 500 },
+    );
+}
+
+#[tokio::test]
+async fn test_data_quota_actions() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let mut hoarder = manifest("hoarder", "1.0.0");
+    hoarder["resource_limits"] = serde_json::json!({
+        "max_cpu_percent": 10,
+        "max_memory_mb": 64,
+        "data_dir_quota_bytes": 1000
+    });
+    let plugin_id = install(&manager, temp_dir.path(), &hoarder).await;
+    
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    
+    let data_dir = manager.plugin_data_dir(&plugin_id);
+    write_data_file(&data_dir, "a.bin", 800, 20);
+    write_data_file(&data_dir, "b.bin", 800, 10);
+    
+    // Warn reports the violation as an event
+    let status = manager.enforce_data_quota(&plugin_id).expect("Failed to check quota");
+    assert_eq!(status, Some(QuotaStatus::Exceeded { used_bytes: 1600, limit_bytes: 1000 }));
+    let events: Vec<String> = emitter.0.lock().unwrap().iter().map(|(name, _)| name.clone()).collect();
+    assert_eq!(events, vec!["plugin-data-quota-exceeded"]);
+    
+    // Block reports the violation as an error
+    manager.set_quota_action(QuotaAction::Block);
+    let result = manager.enforce_data_quota(&plugin_id);
+    assert!(matches!(result, Err(PluginError::QuotaExceeded { used_bytes: 1600, .. })));
+    
+    // Evict frees space
+    manager.set_quota_action(QuotaAction::Evict);
+    let status = manager.enforce_data_quota(&plugin_id).expect("Failed to check quota");
+    assert_eq!(status, Some(QuotaStatus::WithinQuota { used_bytes: 800, limit_bytes: 1000 }));
+    assert!(!data_dir.join("a.bin").exists());
+}
+
+#[tokio::test]
+async fn test_data_usage_reporting_and_trimming() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let mut limited = manifest("limited", "1.0.0");
+    limited["resource_limits"] = serde_json::json!({
+        "max_cpu_percent": 10,
+        "max_memory_mb": 64,
+        "data_dir_quota_bytes": 1000
+    });
+    let limited_id = install(&manager, temp_dir.path(), &limited).await;
+    let idle_id = install(&manager, temp_dir.path(), &manifest("idle", "1.0.0")).await;
+    
+    let data_dir = manager.plugin_data_dir(&limited_id);
+    write_data_file(&data_dir, "old.log", 300, 30);
+    write_data_file(&data_dir.join("cache"), "older.bin", 200, 40);
+    write_data_file(&data_dir, "new.log", 100, 10);
+    
+    // Act & Assert: usage is summed across subdirectories
+    assert_eq!(manager.get_plugin_data_usage(&limited_id), 600);
+    assert_eq!(manager.get_plugin_data_usage(&idle_id), 0);
+    assert_eq!(manager.get_all_data_usage(), vec![
+        PluginDataUsage { plugin_id: limited_id.clone(), used_bytes: 600, limit_bytes: Some(1000) },
+        PluginDataUsage { plugin_id: idle_id.clone(), used_bytes: 0, limit_bytes: None },
+    ]);
+    
+    // Trimming deletes the oldest files first
+    let deleted = manager.trim_plugin_data(&limited_id, 150).expect("Failed to trim plugin data");
+    assert_eq!(deleted, vec![data_dir.join("cache").join("older.bin"), data_dir.join("old.log")]);
+    assert_eq!(manager.get_plugin_data_usage(&limited_id), 100);
+    assert!(data_dir.join("new.log").exists());
+    
+    assert!(matches!(manager.trim_plugin_data("missing", 0), Err(PluginError::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_per_plugin_registry_only_writes_changed_records() {
+    // Arrange
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let manager = PluginManager::with_registry_format(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        Arc::new(PermissionSystem::new()),
+        RegistryFormat::PerPlugin,
+    ).expect("Failed to create plugin manager");
+    
+    let first = install(&manager, temp_dir.path(), &manifest("first", "1.0.0")).await;
+    let second = install(&manager, temp_dir.path(), &manifest("second", "1.0.0")).await;
+    
+    let records_dir = temp_dir.path().join("registry.d");
+    let second_record = records_dir.join(format!("{}.json", second));
+    let old_time = SystemTime::now() - Duration::from_secs(3600);
+    File::options().write(true).open(&second_record).unwrap().set_modified(old_time).unwrap();
+    
+    // Act
+    let third = install(&manager, temp_dir.path(), &manifest("third", "1.0.0")).await;
+    manager.uninstall_plugin(&first).await.expect("Failed to uninstall plugin");
+    
+    // Assert
+    assert_eq!(std::fs::metadata(&second_record).unwrap().modified().unwrap(), old_time);
+    assert!(records_dir.join(format!("{}.json", third)).exists());
+    assert!(!records_dir.join(format!("{}.json", first)).exists());
+    assert!(!temp_dir.path().join("registry.json").exists());
+    
+    let index: Vec<String> = serde_json::from_str(&std::fs::read_to_string(records_dir.join("index.json")).unwrap()).unwrap();
+    assert_eq!(index, vec![second.clone(), third.clone()]);
+}
+
+#[tokio::test]
+async fn test_monolithic_registry_is_migrated_to_per_plugin_records() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("legacy", "1.0.0")).await;
+    drop(manager);
+    
+    // Act
+    let manager = PluginManager::with_registry_format(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        Arc::new(PermissionSystem::new()),
+        RegistryFormat::PerPlugin,
+    ).expect("Failed to create plugin manager");
+    
+    // Assert
+    assert!(manager.get_plugin(&plugin_id).is_some());
+    assert!(temp_dir.path().join("registry.d").join(format!("{}.json", plugin_id)).exists());
+    assert!(temp_dir.path().join("registry.json.migrated").exists());
+    assert!(!temp_dir.path().join("registry.json").exists());
+}
+
+#[tokio::test]
+async fn test_disable_all_disables_every_plugin() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    for name in ["alpha", "beta", "gamma"] {
+        install(&manager, temp_dir.path(), &manifest(name, "1.0.0")).await;
+    }
+    let manager = reopen_with_all_enabled(&temp_dir, manager);
+    assert_eq!(manager.get_enabled_plugins().len(), 3);
+    
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    
+    // Act
+    let report = manager.disable_all().await;
+    
+    // Assert
+    assert_eq!(report.disabled, vec!["alpha-1.0.0", "beta-1.0.0", "gamma-1.0.0"]);
+    assert!(report.failed.is_empty());
+    assert!(manager.get_all_plugins().iter().all(|p| p.status == PluginStatus::Disabled));
+    
+    let events = emitter.0.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, "all-plugins-disabled");
+}
+
+#[tokio::test]
+async fn test_disable_all_disables_failed_plugins() {
+    // Arrange: a plugin left in the error state
+    let (temp_dir, manager) = create_manager();
+    install(&manager, temp_dir.path(), &manifest("broken", "1.0.0")).await;
+    drop(manager);
+    
+    let registry_path = temp_dir.path().join("registry.json");
+    let registry = std::fs::read_to_string(&registry_path).unwrap();
+    std::fs::write(&registry_path, registry.replace("\"status\": \"Disabled\"", "\"status\": {\"Error\": \"crashed\"}")).unwrap();
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        registry_path,
+        Arc::new(PermissionSystem::new()),
+    ).expect("Failed to create plugin manager");
+    assert!(matches!(manager.get_all_plugins()[0].status, PluginStatus::Error(_)));
+    
+    // Act
+    let report = manager.disable_all().await;
+    
+    // Assert
+    assert_eq!(report.disabled, vec!["broken-1.0.0"]);
+    assert_eq!(manager.get_all_plugins()[0].status, PluginStatus::Disabled);
+}
+
+#[tokio::test]
+async fn test_failed_plugin_rejects_events() {
+    // Arrange: a plugin left in the error state
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("failed", "1.0.0")).await;
+    drop(manager);
+    
+    let registry_path = temp_dir.path().join("registry.json");
+    let registry = std::fs::read_to_string(&registry_path).unwrap();
+    std::fs::write(&registry_path, registry.replace("\"status\": \"Disabled\"", "\"status\": {\"Error\": \"unhealthy\"}")).unwrap();
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        registry_path,
+        Arc::new(PermissionSystem::new()),
+    ).expect("Failed to create plugin manager");
+    
+    // Act
+    let result = manager.trigger_plugin_event(&plugin_id, "greet", "{}").await;
+    
+    // Assert
+    match result {
+        Err(PluginError::InvalidState(message)) => assert!(message.contains("failed")),
+        other => panic!("Expected the event to be rejected, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_pause_and_resume_plugin() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("pausable", "1.0.0")).await;
+    
+    // Only enabled plugins can be paused
+    let result = manager.pause_plugin(&plugin_id).await;
+    assert!(matches!(result, Err(PluginError::InvalidState(_))));
+    
+    let manager = reopen_with_all_enabled(&temp_dir, manager);
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    
+    // Act: pause
+    manager.pause_plugin(&plugin_id).await.expect("Failed to pause plugin");
+    
+    // Assert: paused plugins refuse events
+    assert_eq!(manager.get_plugin(&plugin_id).unwrap().status, PluginStatus::Paused);
+    let result = manager.trigger_plugin_event(&plugin_id, "tick", "{}").await;
+    assert!(matches!(result, Err(PluginError::InvalidState(_))));
+    
+    // Act: resume through enable
+    manager.enable_plugin(&plugin_id).await.expect("Failed to resume plugin");
+    
+    // Assert
+    assert_eq!(manager.get_plugin(&plugin_id).unwrap().status, PluginStatus::Enabled);
+    let events = emitter.0.lock().unwrap();
+    let statuses: Vec<&str> = events.iter()
+        .filter(|(name, _)| name == "plugin-status-changed")
+        .map(|(_, payload)| payload["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["Paused", "Enabled"]);
+}
+
+/// Parse a test manifest with the given permissions
+fn parsed_manifest(version: &str, permissions: serde_json::Value) -> PluginManifest {
+    let mut manifest = manifest("diffed", version);
+    manifest["permissions"] = permissions;
+    serde_json::from_value(manifest).unwrap()
+}
+
+#[test]
+fn test_manifest_diff_reports_each_change() {
+    let network = serde_json::json!({ "Network": { "allowed_hosts": ["api.example.com"] } });
+    let system = serde_json::json!({
+        "System": { "read_clipboard": true, "write_clipboard": false, "read_system_info": false }
+    });
+    let old = parsed_manifest("1.0.0", serde_json::json!([network]));
+    let mut new = parsed_manifest("2.0.0", serde_json::json!([system]));
+    new.description = "Rewritten plugin".to_string();
+    new.api_version = "2.0.0".to_string();
+    
+    let diff = ManifestDiff::compute(&old, &new);
+    
+    assert_eq!(diff.added_permissions, new.permissions);
+    assert_eq!(diff.removed_permissions, old.permissions);
+    assert_eq!(diff.changed_version, Some(("1.0.0".to_string(), "2.0.0".to_string())));
+    assert_eq!(diff.changed_description, Some(("Test plugin".to_string(), "Rewritten plugin".to_string())));
+    assert_eq!(diff.api_version_changed, Some(("1.0.0".to_string(), "2.0.0".to_string())));
+    assert!(diff.has_security_implications());
+    
+    // Unchanged manifests produce an empty diff
+    assert!(ManifestDiff::compute(&old, &old).is_empty());
+    
+    // Gaining read-only file access or network access is not flagged
+    let read_only = serde_json::json!({ "FileSystem": { "read": true, "write": false, "paths": ["C:/data"] } });
+    let write = serde_json::json!({ "FileSystem": { "read": false, "write": true, "paths": ["C:/data"] } });
+    let diff = ManifestDiff::compute(&old, &parsed_manifest("1.1.0", serde_json::json!([network, read_only])));
+    assert!(!diff.has_security_implications());
+    let diff = ManifestDiff::compute(&old, &parsed_manifest("1.1.0", serde_json::json!([write])));
+    assert!(diff.has_security_implications());
+}
+
+#[tokio::test]
+async fn test_update_emits_manifest_diff() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("diffed", "1.0.0")).await;
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    
+    let mut update = manifest("diffed", "1.1.0");
+    update["permissions"] = serde_json::json!([{ "Network": { "allowed_hosts": ["api.example.com"] } }]);
+    let package_path = temp_dir.path().join("diffed-1.1.0.zip");
+    create_package(&package_path, &update);
+    
+    // Act
+    manager.update_plugin(&plugin_id, Some(PluginSource::File(package_path))).await
+        .expect("Failed to update plugin");
+    
+    // Assert
+    let events = emitter.0.lock().unwrap();
+    let (_, payload) = events.iter()
+        .find(|(name, _)| name == "plugin-update-manifest-diff")
+        .expect("No manifest diff event");
+    assert_eq!(payload["plugin_id"], plugin_id.as_str());
+    assert_eq!(payload["diff"]["added_permissions"].as_array().unwrap().len(), 1);
+    assert_eq!(payload["diff"]["changed_version"], serde_json::json!(["1.0.0", "1.1.0"]));
+    assert_eq!(payload["has_security_implications"], false);
+}
+
+#[tokio::test]
+async fn test_execute_plugin_command_is_rate_limited() {
+    // Arrange
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let permission_system = Arc::new(PermissionSystem::new());
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        permission_system.clone(),
+    ).expect("Failed to create plugin manager");
+    let first = install(&manager, temp_dir.path(), &manifest("first", "1.0.0")).await;
+    let second = install(&manager, temp_dir.path(), &manifest("second", "1.0.0")).await;
+    let args = serde_json::json!({ "text": "hello" });
+    
+    // Disabled plugins refuse commands
+    let result = manager.execute_plugin_command(&first, "process_text", &args).await;
+    assert!(matches!(result, Err(PluginError::InvalidState(_))));
+    
+    let manager = reopen_with_all_enabled_using(&temp_dir, manager, permission_system.clone());
+    
+    // Plugins without the Command permission refuse commands
+    let result = manager.execute_plugin_command(&first, "process_text", &args).await;
+    assert!(matches!(result, Err(PluginError::Permission(PermissionError::Denied(_)))));
+    
+    for plugin_id in [&first, &second] {
+        permission_system.grant_permissions(plugin_id, vec![Permission::Command], true).unwrap();
+    }
+    manager.set_command_rate_limit(2, Duration::from_secs(60));
+    
+    // Act: the plugins are not loaded in this test, so delivery itself fails
+    for _ in 0..2 {
+        let result = manager.execute_plugin_command(&first, "process_text", &args).await;
+        assert!(matches!(result, Err(PluginError::InvalidState(_))));
+    }
+    let limited = manager.execute_plugin_command(&first, "process_text", &args).await;
+    let other_plugin = manager.execute_plugin_command(&second, "process_text", &args).await;
+    
+    // Assert
+    assert!(matches!(limited, Err(PluginError::RateLimited(id)) if id == first));
+    assert!(matches!(other_plugin, Err(PluginError::InvalidState(_))));
+}
+
+/// Build a manifest for a clipboard manager plugin
+fn clipboard_manager(name: &str) -> serde_json::Value {
+    let mut manifest = manifest(name, "1.0.0");
+    manifest["provides_capabilities"] = serde_json::json!(["clipboard-manager"]);
+    manifest
+}
+
+#[tokio::test]
+async fn test_duplicate_capability_providers_conflict() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    let first = install(&manager, temp_dir.path(), &clipboard_manager("clip-one")).await;
+    assert!(manager.detect_capability_conflicts().is_empty());
+    
+    // Act
+    let second = install(&manager, temp_dir.path(), &clipboard_manager("clip-two")).await;
+    
+    // Assert
+    let conflicts = manager.detect_capability_conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].capability_id, "clipboard-manager");
+    assert_eq!(conflicts[0].conflicting_plugins, vec![first.clone(), second.clone()]);
+    assert_eq!(conflicts[0].resolution, ConflictResolution::Manual);
+    
+    {
+        let events = emitter.0.lock().unwrap();
+        let reported: Vec<_> = events.iter().filter(|(name, _)| name == "plugin-capability-conflict").collect();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].1["conflicting_plugins"], serde_json::json!([first, second]));
+    }
+    
+    // Conflicts with an automatic resolution are not reported to the user
+    manager.set_conflict_resolution_policy("clipboard-manager", ConflictResolution::PreferFirst);
+    install(&manager, temp_dir.path(), &clipboard_manager("clip-three")).await;
+    
+    let conflicts = manager.detect_capability_conflicts();
+    assert_eq!(conflicts[0].conflicting_plugins.len(), 3);
+    assert_eq!(conflicts[0].resolution, ConflictResolution::PreferFirst);
+    let events = emitter.0.lock().unwrap();
+    assert_eq!(events.iter().filter(|(name, _)| name == "plugin-capability-conflict").count(), 1);
+}
+
+#[tokio::test]
+async fn test_export_diagnostics_bundle() {
+    // Arrange
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let permission_system = Arc::new(PermissionSystem::new());
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        permission_system.clone(),
+    ).expect("Failed to create plugin manager");
+    
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("diagnosed", "1.0.0")).await;
+    let data_dir = manager.plugin_data_dir(&plugin_id).to_string_lossy().into_owned();
+    permission_system.grant_permissions(&plugin_id, vec![
+        Permission::FileSystem(FileSystemPermission {
+            read: true,
+            write: true,
+            paths: vec!["C:/Users/alice/Documents".to_string(), data_dir.clone()],
+        }),
+    ], true).unwrap();
+    
+    // Act
+    let bundle_path = temp_dir.path().join("diagnostics.zip");
+    manager.export_diagnostics(&bundle_path).await.expect("Failed to export diagnostics");
+    
+    // Assert: every section is present
+    let mut archive = zip::ZipArchive::new(File::open(&bundle_path).unwrap()).unwrap();
+    let mut names: Vec<String> = archive.file_names().map(str::to_owned).collect();
+    names.sort();
+    assert_eq!(names, vec![
+        "host.json".to_string(),
+        "permissions.json".to_string(),
+        format!("plugins/{}.json", plugin_id),
+        "registry.json".to_string(),
+    ]);
+    
+    let mut read_section = |name: &str| -> serde_json::Value {
+        serde_json::from_reader(archive.by_name(name).unwrap()).unwrap()
+    };
+    
+    let status = read_section(&format!("plugins/{}.json", plugin_id));
+    assert_eq!(status["status"], "Disabled");
+    assert_eq!(status["loaded"], false);
+    assert_eq!(status["integrity"]["ok"], true);
+    
+    // Paths outside the application directory are redacted, paths inside are kept
+    let permissions = read_section("permissions.json");
+    let paths = &permissions[&plugin_id][0]["FileSystem"]["paths"];
+    assert_eq!(paths, &serde_json::json!([REDACTED_PATH, data_dir]));
+    for name in &names {
+        assert!(!read_section(name).to_string().contains("alice"), "{} leaks a redacted path", name);
+    }
+    
+    // Secrets are redacted wherever they appear
+    let mut value = serde_json::json!({ "auth": { "api_token": "abc123" }, "header": "Bearer abc123" });
+    DiagnosticsRedactor::new(temp_dir.path()).redact(&mut value);
+    assert_eq!(value, serde_json::json!({ "auth": { "api_token": REDACTED }, "header": REDACTED }));
+}
+
+#[tokio::test]
+async fn test_repeated_load_failures_quarantine_plugin() {
+    // Arrange: the package's DLL is not a loadable library
+    let (temp_dir, manager) = create_manager();
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("broken", "1.0.0")).await;
+    
+    // Act: fail up to the threshold
+    for attempt in 1..=DEFAULT_QUARANTINE_THRESHOLD {
+        assert!(matches!(manager.enable_plugin(&plugin_id).await, Err(PluginError::LoadError(_))));
+        assert_eq!(manager.load_failure_count(&plugin_id), attempt);
+    }
+    
+    // Assert
+    assert!(matches!(manager.get_plugin(&plugin_id).unwrap().status, PluginStatus::Quarantined(_)));
+    {
+        let events = emitter.0.lock().unwrap();
+        let quarantined: Vec<_> = events.iter().filter(|(name, _)| name == "plugin-quarantined").collect();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].1["plugin_id"], plugin_id.as_str());
+        assert!(quarantined[0].1["error"].as_str().unwrap().contains("load"));
+    }
+    
+    // Further enable attempts are refused without trying to load
+    assert!(matches!(manager.enable_plugin(&plugin_id).await, Err(PluginError::InvalidState(_))));
+    assert_eq!(manager.load_failure_count(&plugin_id), DEFAULT_QUARANTINE_THRESHOLD);
+    
+    // An explicit retry attempts the load again with a fresh count
+    assert!(matches!(manager.retry_quarantined_plugin(&plugin_id).await, Err(PluginError::LoadError(_))));
+    assert_eq!(manager.load_failure_count(&plugin_id), 1);
+    assert_eq!(manager.get_plugin(&plugin_id).unwrap().status, PluginStatus::Disabled);
+}
+
+/// Emitter that blocks until its gate is released
+struct GatedEmitter {
+    /// Held by the test to stall delivery
+    gate: Arc<Mutex<()>>,
+    /// Receives events once the gate is released
+    inner: CollectingEmitter,
+}
+
+impl PluginEventEmitter for GatedEmitter {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        let _gate = self.gate.lock().unwrap();
+        self.inner.emit(event, payload);
+    }
+}
+
+#[test]
+fn test_event_queue_stays_bounded_when_flooded() {
+    let (_temp_dir, manager) = create_manager();
+    let collected = CollectingEmitter::default();
+    let gate = Arc::new(Mutex::new(()));
+    let stalled = gate.lock().unwrap();
+    
+    let capacity = 64;
+    let emitter = QueuedEventEmitter::new(GatedEmitter { gate: gate.clone(), inner: collected.clone() }, capacity)
+        .with_policy("plugin-progress", OverflowPolicy::Coalesce);
+    let queue = emitter.queue();
+    manager.set_queued_event_emitter(emitter);
+    
+    // Flood the queue while the consumer is stalled
+    for i in 0..10_000 {
+        let plugin_id = format!("plugin-{}", i % 4);
+        queue.push("plugin-log", serde_json::json!({ "plugin_id": plugin_id, "line": i }));
+        queue.push("plugin-progress", serde_json::json!({ "plugin_id": plugin_id, "percent": i }));
+    }
+    
+    let stats = manager.event_queue_stats().expect("Emitter should report queue stats");
+    assert!(queue.len() <= capacity);
+    assert!(stats.queued <= capacity);
+    assert!(stats.dropped > 0);
+    assert!(stats.coalesced > 0);
+    assert!(stats.dropped_by_event["plugin-log"] > 0);
+    
+    // Releasing the consumer delivers what is left, newest progress included
+    drop(stalled);
+    manager.set_event_emitter(collected.clone());
+    let events = collected.0.lock().unwrap();
+    assert!(events.len() <= capacity + 1);
+    assert!(events.iter().any(|(name, payload)| name == "plugin-progress" && payload["percent"] == 9_999));
+    assert_eq!(manager.event_queue_stats(), None);
+}
+
+#[tokio::test]
+async fn test_concurrent_registry_readers_and_writer() {
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("shared", "1.0.0")).await;
+    let install_path = manager.get_plugin(&plugin_id).unwrap().install_path;
+    let initial_count = manager.get_plugin(&plugin_id).unwrap().file_count;
+    let manager = Arc::new(manager);
+    
+    let (done_sender, done_receiver) = std::sync::mpsc::channel();
+    let mut threads = Vec::new();
+    
+    for _ in 0..8 {
+        let manager = manager.clone();
+        let plugin_id = plugin_id.clone();
+        let done_sender = done_sender.clone();
+        threads.push(std::thread::spawn(move || {
+            let mut last_count = 0;
+            for _ in 0..500 {
+                let plugin = manager.get_plugin(&plugin_id).expect("Plugin disappeared during reads");
+                assert!(plugin.file_count >= last_count, "File count went backwards");
+                last_count = plugin.file_count;
+                assert_eq!(manager.get_all_plugins().len(), 1);
+                assert!(manager.get_enabled_plugins().is_empty());
+            }
+            done_sender.send(()).unwrap();
+        }));
+    }
+    
+    let writer = {
+        let manager = manager.clone();
+        let plugin_id = plugin_id.clone();
+        let done_sender = done_sender.clone();
+        std::thread::spawn(move || {
+            for i in 0..50 {
+                std::fs::write(install_path.join(format!("extra-{}.dat", i)), b"data").unwrap();
+                manager.recompute_size(&plugin_id).expect("Failed to recompute size");
+            }
+            done_sender.send(()).unwrap();
+        })
+    };
+    threads.push(writer);
+    
+    for _ in 0..threads.len() {
+        done_receiver.recv_timeout(Duration::from_secs(30)).expect("Registry access deadlocked");
+    }
+    for thread in threads {
+        thread.join().expect("Registry access panicked");
+    }
+    
+    assert_eq!(manager.get_plugin(&plugin_id).unwrap().file_count, initial_count + 50);
+}
+
+#[tokio::test]
+async fn test_install_plugin_from_base64_bytes() {
+    let (temp_dir, manager) = create_manager();
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    
+    let package_path = temp_dir.path().join("dropped.zip");
+    create_package(&package_path, &manifest("dropped", "1.0.0"));
+    let encoded = BASE64.encode(std::fs::read(&package_path).unwrap());
+    
+    let package = BASE64.decode(encoded).expect("Failed to decode package");
+    let plugin = manager.install_plugin(PluginSource::Bytes(package)).await
+        .expect("Failed to install plugin from bytes");
+    
+    assert_eq!(plugin.id, "dropped-1.0.0");
+    assert!(plugin.install_path.starts_with(temp_dir.path().join("plugins")));
+    assert!(plugin.install_path.join("plugin.json").exists());
+    
+    let events = emitter.0.lock().unwrap();
+    let installed = events.iter().find(|(event, _)| event == "plugin-installed")
+        .expect("Missing plugin-installed event");
+    assert_eq!(installed.1["id"], "dropped-1.0.0");
+}
+
+#[tokio::test]
+async fn test_install_plugin_from_bytes_rejects_oversized_package() {
+    let (_temp_dir, manager) = create_manager();
+    let package = vec![0u8; MAX_IN_MEMORY_PACKAGE_BYTES + 1];
+    
+    let result = manager.install_plugin(PluginSource::Bytes(package)).await;
+    assert!(matches!(
+        result,
+        Err(PluginInstallError::PackageTooLarge { max: MAX_IN_MEMORY_PACKAGE_BYTES, .. })
+    ));
+    assert!(manager.get_all_plugins().is_empty());
+}
+
+#[tokio::test]
+async fn test_installed_plugin_with_unsupported_api_is_incompatible() {
+    let (temp_dir, manager) = create_manager();
+    let major_id = install(&manager, temp_dir.path(), &manifest("major", "1.0.0")).await;
+    let minor_id = install(&manager, temp_dir.path(), &manifest("minor", "1.0.0")).await;
+    
+    // Simulate plugins built for another host API version
+    for (plugin_id, api_version) in [(&major_id, "2.0.0"), (&minor_id, "1.9.0")] {
+        let manifest_path = manager.get_plugin(plugin_id).unwrap().install_path.join("plugin.json");
+        let mut plugin_manifest: serde_json::Value = serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        plugin_manifest["api_version"] = api_version.into();
+        std::fs::write(&manifest_path, plugin_manifest.to_string()).unwrap();
+    }
+    drop(manager);
+    
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        Arc::new(PermissionSystem::new()),
+    ).expect("Failed to reopen plugin manager");
+    
+    match manager.get_plugin(&major_id).unwrap().status {
+        PluginStatus::Incompatible(reason) => {
+            assert!(reason.starts_with("major API version mismatch"), "{}", reason);
+            assert!(reason.contains("2.0.0"));
+        },
+        status => panic!("Unexpected status: {:?}", status),
+    }
+    match manager.get_plugin(&minor_id).unwrap().status {
+        PluginStatus::Incompatible(reason) => {
+            assert!(reason.starts_with("plugin requires newer API minor version 1.9.0"), "{}", reason);
+        },
+        status => panic!("Unexpected status: {:?}", status),
+    }
+    
+    assert!(matches!(manager.enable_plugin(&major_id).await, Err(PluginError::InvalidState(_))));
+}
+
+#[tokio::test]
+async fn test_desired_enabled_plugins_are_restored_after_restart() {
+    let (temp_dir, manager) = create_manager();
+    let first_id = install(&manager, temp_dir.path(), &manifest("restored-a", "1.0.0")).await;
+    let second_id = install(&manager, temp_dir.path(), &manifest("restored-b", "1.0.0")).await;
+    let disabled_id = install(&manager, temp_dir.path(), &manifest("user-disabled", "1.0.0")).await;
+    
+    // Simulate a crash while all plugins were enabled, then the user disabling one
+    let manager = reopen_with_all_enabled(&temp_dir, manager);
+    manager.disable_plugin(&disabled_id).await.expect("Failed to disable plugin");
+    drop(manager);
+    
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        Arc::new(PermissionSystem::new()),
+    ).expect("Failed to reopen plugin manager");
+    let emitter = CollectingEmitter::default();
+    manager.set_event_emitter(emitter.clone());
+    
+    assert_eq!(manager.get_plugin(&first_id).unwrap().desired_state, DesiredState::Enabled);
+    assert_eq!(manager.get_plugin(&disabled_id).unwrap().desired_state, DesiredState::Disabled);
+    
+    // The test packages hold dummy DLLs, so restoring attempts them and reports the failures
+    let report = manager.restore_enabled_plugins().await;
+    
+    assert!(report.restored.is_empty());
+    let failed: Vec<&str> = report.failed.iter().map(|f| f.plugin_id.as_str()).collect();
+    assert_eq!(failed, [first_id.as_str(), second_id.as_str()]);
+    
+    for plugin_id in [&first_id, &second_id] {
+        let plugin = manager.get_plugin(plugin_id).unwrap();
+        assert_eq!(plugin.status, PluginStatus::Disabled);
+        assert_eq!(plugin.desired_state, DesiredState::Enabled, "Restore failure cleared the desired state");
+    }
+    assert_eq!(manager.get_plugin(&disabled_id).unwrap().status, PluginStatus::Disabled);
+    assert!(emitter.0.lock().unwrap().iter().any(|(event, _)| event == "plugins-restored"));
+    
+    // The desire survives another restart, and disabling clears it
+    drop(manager);
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        Arc::new(PermissionSystem::new()),
+    ).expect("Failed to reopen plugin manager");
+    assert_eq!(manager.get_plugin(&first_id).unwrap().desired_state, DesiredState::Enabled);
+    
+    manager.disable_plugin(&first_id).await.expect("Failed to disable plugin");
+    assert_eq!(manager.get_plugin(&first_id).unwrap().desired_state, DesiredState::Disabled);
+    let report = manager.restore_enabled_plugins().await;
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].plugin_id, second_id);
+}
+
+#[tokio::test]
+async fn test_stored_desired_state_is_kept_on_startup() {
+    // Arrange: a paused plugin the user wants disabled, and one from a registry without desired states
+    let (temp_dir, manager) = create_manager();
+    let kept_id = install(&manager, temp_dir.path(), &manifest("kept", "1.0.0")).await;
+    let legacy_id = install(&manager, temp_dir.path(), &manifest("legacy", "1.0.0")).await;
+    drop(manager);
+    
+    let registry_path = temp_dir.path().join("registry.json");
+    let mut registry: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&registry_path).unwrap()).unwrap();
+    registry["plugins"][&kept_id]["status"] = serde_json::json!("Paused");
+    registry["plugins"][&legacy_id]["status"] = serde_json::json!("Enabled");
+    registry["plugins"][&legacy_id].as_object_mut().unwrap().remove("desired_state");
+    std::fs::write(&registry_path, registry.to_string()).unwrap();
+    
+    // Act
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        registry_path,
+        Arc::new(PermissionSystem::new()),
+    ).expect("Failed to reopen plugin manager");
+    
+    // Assert
+    assert_eq!(manager.get_plugin(&kept_id).unwrap().desired_state, DesiredState::Disabled);
+    assert_eq!(manager.get_plugin(&legacy_id).unwrap().desired_state, DesiredState::Enabled);
+}
+
+#[tokio::test]
+async fn test_enable_requires_host_features() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let mut manifest = manifest("wasm-plugin", "1.0.0");
+    manifest["required_host_features"] = serde_json::json!(["dynamic-loading", "wasm"]);
+    let plugin_id = install(&manager, temp_dir.path(), &manifest).await;
+    assert!(!host_features().contains(&"wasm"));
+    
+    // Act
+    let result = manager.enable_plugin(&plugin_id).await;
+    
+    // Assert: only the missing feature is named
+    let reason = "plugin requires host features not in this build: wasm";
+    assert!(matches!(result, Err(PluginError::LoadError(PluginLoadError::Incompatible(r))) if r == reason));
+    assert_eq!(manager.get_plugin(&plugin_id).unwrap().status, PluginStatus::Incompatible(reason.to_string()));
+}
+
+#[tokio::test]
+async fn test_feature_flag_change_notifies_plugin_and_persists() {
+    // Arrange
+    let (temp_dir, manager) = create_manager();
+    let plugin_id = install(&manager, temp_dir.path(), &manifest("flagged", "1.0.0")).await;
+    let manager = reopen_with_all_enabled(&temp_dir, manager);
+    manager.start_event_recording().await;
+    
+    // Act
+    manager.set_plugin_feature(&plugin_id, "new-editor", true).await.expect("Failed to set feature");
+    manager.set_plugin_feature(&plugin_id, "new-editor", false).await.expect("Failed to set feature");
+    
+    // Assert: each change is sent to the plugin with all its flags
+    let log = manager.stop_event_recording().await;
+    assert_eq!(log.entries.len(), 2);
+    assert!(log.entries.iter().all(|entry| entry.plugin_id == plugin_id && entry.event_name == FEATURE_FLAGS_EVENT));
+    let payload: serde_json::Value = serde_json::from_str(&log.entries[1].data).unwrap();
+    assert_eq!(payload, serde_json::json!({ "flags": { "new-editor": false }, "changed": "new-editor" }));
+    
+    // Flags survive a restart
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        Arc::new(PermissionSystem::new()),
+    ).unwrap();
+    let flags = manager.get_plugin(&plugin_id).unwrap().feature_flags;
+    assert_eq!(flags.get("new-editor"), Some(&false));
+    
+    let result = manager.set_plugin_feature("missing", "new-editor", true).await;
+    assert!(matches!(result, Err(PluginError::NotFound(_))));
+}
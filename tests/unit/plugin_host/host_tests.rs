@@ -1,244 +1,99 @@
-//! Unit tests for the plugin host functionality
+//! Unit tests for the plugin host functionality, run against the harness plugin
 
-use tauri_windows_plugin_system::plugin_host::{PluginHost, PluginContext, PluginError};
-use std::path::Path;
-use std::ptr::null_mut;
-use tempfile::tempdir;
-use std::fs;
+use tauri_windows_plugin_system::plugin_host::{PluginHost, PluginHostError};
+use tauri_windows_plugin_system::plugin_loader::{LoadedPlugin, PluginLoader, PluginMetadata};
+use crate::common::harness;
+use libloading::{Library, Symbol};
+use tempfile::{tempdir, TempDir};
 
-/// Mock plugin initialization function for testing
-#[no_mangle]
-extern "C" fn mock_plugin_init(context: *mut std::ffi::c_void) -> i32 {
-    if context.is_null() {
-        return -1;
-    }
-    0 // Success
-}
-
-/// Mock plugin teardown function for testing
-#[no_mangle]
-extern "C" fn mock_plugin_teardown() -> i32 {
-    0 // Success
+/// Extract a fresh copy of the harness plugin and load its DLL
+///
+/// Each copy is a separate library, so its counters start at zero. Returns
+/// `None` when the harness plugin has not been built.
+async fn load_harness_plugin(name: &str) -> Option<(TempDir, LoadedPlugin)> {
+    let dll_path = harness::harness_plugin_dll()?;
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let package_path = temp_dir.path().join(format!("{}.zip", name));
+    harness::create_harness_plugin_package(&package_path, name, dll_path)
+        .expect("Failed to create harness plugin package");
+    
+    let loader = PluginLoader::new(temp_dir.path().join("extracted"));
+    let metadata = loader.load_plugin_package(&package_path).await
+        .expect("Harness package should load");
+    let loaded_plugin = loader.load_plugin_dll(&metadata)
+        .expect("Harness DLL should load");
+    
+    Some((temp_dir, loaded_plugin))
 }
 
-#[test]
-fn test_plugin_initialization_with_valid_context() {
-    // Arrange
-    let plugin_id = "test-plugin";
-    let mut host = PluginHost::new();
-    let context = PluginContext {
-        plugin_id: plugin_id.to_string(),
-        app_data_dir: "/tmp/app_data".to_string(),
-        log_level: 0,
-    };
-    
-    // Mock the DLL loading - in a real test we'd need to use dynamic library loading
-    // For now, we're just testing the logic around it
-    
-    // Act
-    let result = host.initialize_plugin(plugin_id, &context, mock_plugin_init);
-    
-    // Assert
-    assert!(result.is_ok());
-    assert!(host.has_plugin(plugin_id));
+/// Open the harness plugin's DLL to read the counters it exports
+fn open_counters(metadata: &PluginMetadata) -> Library {
+    unsafe { Library::new(&metadata.dll_path).expect("Failed to open harness DLL") }
 }
 
-#[test]
-fn test_plugin_initialization_with_null_context() {
-    // Arrange
-    let plugin_id = "test-plugin";
-    let mut host = PluginHost::new();
-    
-    // This would be a function that passes null as the context
-    let null_context_init = |_: *mut std::ffi::c_void| -> i32 { -1 };
-    
-    // Act
-    let result = host.initialize_plugin(plugin_id, &PluginContext {
-        plugin_id: plugin_id.to_string(),
-        app_data_dir: "/tmp/app_data".to_string(),
-        log_level: 0,
-    }, null_context_init);
-    
-    // Assert
-    assert!(result.is_err());
-    match result {
-        Err(PluginError::InitializationFailed(_)) => {
-            // Expected error
-        },
-        _ => panic!("Expected InitializationFailed error, got {:?}", result),
+/// Read one of the harness plugin's counters
+fn counter(library: &Library, name: &[u8]) -> u32 {
+    unsafe {
+        let counter_fn: Symbol<extern "C" fn() -> u32> = library.get(name).expect("Missing counter export");
+        counter_fn()
     }
-    assert!(!host.has_plugin(plugin_id));
 }
 
-#[test]
-fn test_plugin_teardown() {
+#[tokio::test]
+async fn test_full_plugin_lifecycle() {
     // Arrange
-    let plugin_id = "test-plugin";
-    let mut host = PluginHost::new();
-    let context = PluginContext {
-        plugin_id: plugin_id.to_string(),
-        app_data_dir: "/tmp/app_data".to_string(),
-        log_level: 0,
+    let Some((_temp_dir, loaded_plugin)) = load_harness_plugin("lifecycle").await else {
+        return;
     };
-    
-    // Initialize plugin first
-    host.initialize_plugin(plugin_id, &context, mock_plugin_init)
-        .expect("Failed to initialize plugin");
-    
-    // Act
-    let result = host.teardown_plugin(plugin_id, mock_plugin_teardown);
-    
-    // Assert
-    assert!(result.is_ok());
-    assert!(!host.has_plugin(plugin_id));
-}
-
-#[test]
-fn test_plugin_teardown_not_loaded() {
-    // Arrange
-    let plugin_id = "test-plugin";
-    let mut host = PluginHost::new();
-    
-    // Act
-    let result = host.teardown_plugin(plugin_id, mock_plugin_teardown);
-    
-    // Assert
-    assert!(result.is_err());
-    match result {
-        Err(PluginError::PluginNotFound(_)) => {
-            // Expected error
-        },
-        _ => panic!("Expected PluginNotFound error, got {:?}", result),
-    }
-}
-
-#[test]
-fn test_callback_registration() {
-    // Arrange
-    let plugin_id = "test-plugin";
+    let counters = open_counters(loaded_plugin.metadata());
     let mut host = PluginHost::new();
-    let context = PluginContext {
-        plugin_id: plugin_id.to_string(),
-        app_data_dir: "/tmp/app_data".to_string(),
-        log_level: 0,
-    };
-    
-    // Initialize plugin first
-    host.initialize_plugin(plugin_id, &context, mock_plugin_init)
-        .expect("Failed to initialize plugin");
     
-    // Act
-    let result = host.register_callback(plugin_id, "test_event", |data| {
-        // Test callback function
-        println!("Callback called with data: {:?}", data);
-        Ok(())
-    });
+    // Act & Assert: init registers the plugin's command
+    host.init_plugin("lifecycle".to_string(), loaded_plugin).expect("Failed to initialize plugin");
+    assert!(host.has_plugin("lifecycle"));
+    assert_eq!(counter(&counters, b"harness_init_calls"), 1);
     
-    // Assert
-    assert!(result.is_ok());
-}
-
-#[test]
-fn test_callback_registration_plugin_not_found() {
-    // Arrange
-    let plugin_id = "test-plugin";
-    let mut host = PluginHost::new();
+    // The command registers a callback for another event
+    let result = host.trigger_event("lifecycle", "harness_register", "greet").expect("Failed to trigger command");
+    assert_eq!(result, 0);
     
-    // Act
-    let result = host.register_callback(plugin_id, "test_event", |data| {
-        // Test callback function
-        println!("Callback called with data: {:?}", data);
-        Ok(())
-    });
+    // The callback receives the event and reports the length of its data
+    let result = host.trigger_event("lifecycle", "greet", "hello").expect("Failed to trigger event");
+    assert_eq!(result, 5);
+    assert_eq!(counter(&counters, b"harness_event_calls"), 1);
     
-    // Assert
-    assert!(result.is_err());
-    match result {
-        Err(PluginError::PluginNotFound(_)) => {
-            // Expected error
-        },
-        _ => panic!("Expected PluginNotFound error, got {:?}", result),
-    }
+    // Teardown calls into the plugin and unloads it from the host
+    host.teardown_plugin("lifecycle").expect("Failed to tear down plugin");
+    assert!(!host.has_plugin("lifecycle"));
+    assert_eq!(counter(&counters, b"harness_teardown_calls"), 1);
 }
 
-#[test]
-fn test_event_triggering() {
+#[tokio::test]
+async fn test_event_without_registered_callback() {
     // Arrange
-    let plugin_id = "test-plugin";
-    let mut host = PluginHost::new();
-    let context = PluginContext {
-        plugin_id: plugin_id.to_string(),
-        app_data_dir: "/tmp/app_data".to_string(),
-        log_level: 0,
+    let Some((_temp_dir, loaded_plugin)) = load_harness_plugin("unregistered").await else {
+        return;
     };
-    
-    // Initialize plugin first
-    host.initialize_plugin(plugin_id, &context, mock_plugin_init)
-        .expect("Failed to initialize plugin");
-    
-    // Register a callback
-    host.register_callback(plugin_id, "test_event", |data| {
-        // Test callback function
-        assert_eq!(data, "test_data");
-        Ok(())
-    }).expect("Failed to register callback");
-    
-    // Act
-    let result = host.trigger_event("test_event", "test_data");
-    
-    // Assert
-    assert!(result.is_ok());
-}
-
-#[test]
-fn test_event_triggering_no_subscribers() {
-    // Arrange
     let mut host = PluginHost::new();
+    host.init_plugin("unregistered".to_string(), loaded_plugin).expect("Failed to initialize plugin");
     
     // Act
-    let result = host.trigger_event("test_event", "test_data");
+    let result = host.trigger_event("unregistered", "greet", "hello");
     
     // Assert
-    assert!(result.is_ok()); // Should not fail even if no subscribers
-}
-
-#[test]
-fn test_logging_functionality() {
-    // Arrange
-    let plugin_id = "test-plugin";
-    let mut host = PluginHost::new();
-    let context = PluginContext {
-        plugin_id: plugin_id.to_string(),
-        app_data_dir: "/tmp/app_data".to_string(),
-        log_level: 0, // Debug level
-    };
+    assert!(matches!(result, Err(PluginHostError::InvalidEventName(_))));
     
-    // Initialize plugin first
-    host.initialize_plugin(plugin_id, &context, mock_plugin_init)
-        .expect("Failed to initialize plugin");
-    
-    // Act
-    let result = host.log(plugin_id, 0, "Test log message");
-    
-    // Assert
-    assert!(result.is_ok());
+    host.teardown_plugin("unregistered").expect("Failed to tear down plugin");
 }
 
 #[test]
-fn test_logging_plugin_not_found() {
+fn test_plugin_teardown_not_loaded() {
     // Arrange
-    let plugin_id = "test-plugin";
     let mut host = PluginHost::new();
     
     // Act
-    let result = host.log(plugin_id, 0, "Test log message");
+    let result = host.teardown_plugin("test-plugin");
     
     // Assert
-    assert!(result.is_err());
-    match result {
-        Err(PluginError::PluginNotFound(_)) => {
-            // Expected error
-        },
-        _ => panic!("Expected PluginNotFound error, got {:?}", result),
-    }
+    assert!(matches!(result, Err(PluginHostError::CommunicationError(_))));
 }
@@ -1,158 +1,92 @@
 //! Unit tests for the plugin manifest validation functionality
 
-use tauri_windows_plugin_system::plugin_loader::{PluginManifest, PluginLoadError};
-use serde_json;
+use tauri_windows_plugin_system::plugin_loader::{PluginLoadError, PluginLoader};
+use crate::common::{harness, helpers};
 
 #[test]
 fn test_valid_manifest() {
     // Arrange
-    let manifest_json = r#"{
-        "name": "test-plugin",
-        "version": "1.0.0",
-        "description": "A test plugin for unit testing",
-        "author": "Test Author",
-        "permissions": ["read_file", "write_file"],
-        "min_host_version": "1.0.0",
-        "entry_point": "plugin.dll"
-    }"#;
+    let manifest_json = harness::create_manifest_json("test-plugin");
     
     // Act
-    let result = PluginManifest::from_json(manifest_json);
+    let result = PluginLoader::parse_manifest(manifest_json.as_bytes());
     
     // Assert
-    assert!(result.is_ok());
-    let manifest = result.unwrap();
+    let manifest = result.expect("Valid manifest should parse");
     assert_eq!(manifest.name, "test-plugin");
     assert_eq!(manifest.version, "1.0.0");
-    assert_eq!(manifest.description, "A test plugin for unit testing");
+    assert_eq!(manifest.entry, "plugin.dll");
+    assert_eq!(manifest.api_version, "1.0.0");
+    assert_eq!(manifest.description, "Test harness plugin");
     assert_eq!(manifest.author, "Test Author");
-    assert_eq!(manifest.permissions, vec!["read_file", "write_file"]);
-    assert_eq!(manifest.min_host_version, "1.0.0");
-    assert_eq!(manifest.entry_point, "plugin.dll");
+    assert!(manifest.permissions.is_empty());
 }
 
 #[test]
 fn test_missing_required_fields() {
     // Arrange
-    let manifest_json = r#"{
-        "name": "test-plugin",
-        "version": "1.0.0"
-    }"#;
+    let manifest_json = helpers::create_invalid_manifest_json();
     
     // Act
-    let result = PluginManifest::from_json(manifest_json);
+    let result = PluginLoader::parse_manifest(manifest_json.as_bytes());
     
     // Assert
-    assert!(result.is_err());
     match result {
-        Err(ManifestError::MissingField(field)) => {
-            // One of the required fields should be missing
-            assert!(field == "description" || field == "author" || 
-                   field == "permissions" || field == "min_host_version" || 
-                   field == "entry_point");
-        },
-        _ => panic!("Expected MissingField error, got {:?}", result),
+        Err(PluginLoadError::JsonError(e)) => assert!(e.to_string().contains("missing field")),
+        other => panic!("Expected JsonError, got {:?}", other.map(|m| m.name)),
     }
 }
 
 #[test]
-fn test_invalid_version_format() {
+fn test_empty_entry_point() {
     // Arrange
-    let manifest_json = r#"{
-        "name": "test-plugin",
-        "version": "invalid-version",
-        "description": "A test plugin for unit testing",
-        "author": "Test Author",
-        "permissions": ["read_file", "write_file"],
-        "min_host_version": "1.0.0",
-        "entry_point": "plugin.dll"
-    }"#;
+    let manifest_json = harness::create_manifest_json("test-plugin").replace("\"plugin.dll\"", "\"\"");
     
     // Act
-    let result = PluginManifest::from_json(manifest_json);
+    let result = PluginLoader::parse_manifest(manifest_json.as_bytes());
     
     // Assert
-    assert!(result.is_err());
     match result {
-        Err(ManifestError::InvalidVersionFormat(field)) => {
-            assert_eq!(field, "version");
-        },
-        _ => panic!("Expected InvalidVersionFormat error, got {:?}", result),
+        Err(PluginLoadError::ManifestError(message)) => assert_eq!(message, "Plugin entry point cannot be empty"),
+        other => panic!("Expected ManifestError, got {:?}", other.map(|m| m.name)),
     }
 }
 
 #[test]
-fn test_invalid_min_host_version_format() {
+fn test_invalid_json_format() {
     // Arrange
     let manifest_json = r#"{
         "name": "test-plugin",
         "version": "1.0.0",
-        "description": "A test plugin for unit testing",
-        "author": "Test Author",
-        "permissions": ["read_file", "write_file"],
-        "min_host_version": "invalid-version",
-        "entry_point": "plugin.dll"
+        INVALID_JSON
     }"#;
     
     // Act
-    let result = PluginManifest::from_json(manifest_json);
+    let result = PluginLoader::parse_manifest(manifest_json.as_bytes());
     
     // Assert
-    assert!(result.is_err());
-    match result {
-        Err(ManifestError::InvalidVersionFormat(field)) => {
-            assert_eq!(field, "min_host_version");
-        },
-        _ => panic!("Expected InvalidVersionFormat error, got {:?}", result),
-    }
+    assert!(matches!(result, Err(PluginLoadError::JsonError(_))));
 }
 
 #[test]
-fn test_invalid_json_format() {
-    // Arrange
-    let manifest_json = r#"{
-        "name": "test-plugin",
-        "version": "1.0.0",
-        INVALID_JSON
-    }"#;
-    
+fn test_invalid_utf8() {
     // Act
-    let result = PluginManifest::from_json(manifest_json);
+    let result = PluginLoader::parse_manifest(&[0x7b, 0xff, 0xfe, 0x7d]);
     
     // Assert
-    assert!(result.is_err());
-    match result {
-        Err(ManifestError::InvalidJson(_)) => {
-            // Error message should contain details about the JSON parsing error
-        },
-        _ => panic!("Expected InvalidJson error, got {:?}", result),
-    }
+    assert!(matches!(result, Err(PluginLoadError::ManifestError(_))));
 }
 
 #[test]
-fn test_permissions_validation() {
+fn test_incompatible_api_version() {
     // Arrange
-    let manifest_json = r#"{
-        "name": "test-plugin",
-        "version": "1.0.0",
-        "description": "A test plugin for unit testing",
-        "author": "Test Author",
-        "permissions": ["read_file", "invalid_permission"],
-        "min_host_version": "1.0.0",
-        "entry_point": "plugin.dll"
-    }"#;
+    let manifest_json = harness::create_manifest_json("test-plugin").replace("\"api_version\": \"1.0.0\"", "\"api_version\": \"99.0.0\"");
+    let manifest = PluginLoader::parse_manifest(manifest_json.as_bytes()).expect("Manifest should parse");
     
     // Act
-    let result = PluginManifest::from_json(manifest_json).and_then(|manifest| {
-        manifest.validate_permissions()
-    });
+    let report = PluginLoader::compatibility_report(&manifest);
     
     // Assert
-    assert!(result.is_err());
-    match result {
-        Err(ManifestError::InvalidPermission(permission)) => {
-            assert_eq!(permission, "invalid_permission");
-        },
-        _ => panic!("Expected InvalidPermission error, got {:?}", result),
-    }
+    assert!(!report.is_compatible());
+    assert!(report.reason().is_some());
 }
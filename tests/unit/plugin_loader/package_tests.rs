@@ -1,146 +1,109 @@
 //! Unit tests for the plugin package loading functionality
 
-use tauri_windows_plugin_system::plugin_loader::package::{PluginPackage, PackageError};
-use std::path::Path;
+use tauri_windows_plugin_system::plugin_loader::{PluginLoadError, PluginLoader};
+use crate::common::{harness, helpers};
 use tempfile::tempdir;
 use std::fs;
 
-#[test]
-fn test_extract_valid_package() {
+#[tokio::test]
+async fn test_extract_valid_package() {
     // Arrange
     let temp_dir = tempdir().expect("Failed to create temp directory");
     let package_path = temp_dir.path().join("valid_plugin_package.zip");
-    let extraction_dir = temp_dir.path().join("extracted");
+    let loader = PluginLoader::new(temp_dir.path().join("extracted"));
     
-    helpers::create_test_plugin_package(&package_path, true)
+    harness::create_dummy_plugin_package(&package_path, "test-plugin")
         .expect("Failed to create test plugin package");
     
     // Act
-    let result = PluginPackage::extract_package(&package_path, &extraction_dir);
+    let result = loader.load_plugin_package(&package_path).await;
     
     // Assert
-    assert!(result.is_ok());
-    assert!(extraction_dir.exists());
-    assert!(extraction_dir.join("plugin.json").exists());
-    assert!(extraction_dir.join("plugin.dll").exists());
-    assert!(extraction_dir.join("resources").exists());
-    assert!(extraction_dir.join("resources/test.txt").exists());
+    let metadata = result.expect("Valid package should load");
+    assert_eq!(metadata.manifest.name, "test-plugin");
+    assert_eq!(metadata.dll_path, metadata.install_path.join("plugin.dll"));
+    assert!(metadata.install_path.join("plugin.json").exists());
+    assert!(metadata.dll_path.exists());
+    assert!(metadata.install_path.join("resources/test.txt").exists());
 }
 
-#[test]
-fn test_extract_corrupted_package() {
+#[tokio::test]
+async fn test_extract_corrupted_package() {
     // Arrange
     let temp_dir = tempdir().expect("Failed to create temp directory");
     let package_path = temp_dir.path().join("corrupted_plugin_package.zip");
     let extraction_dir = temp_dir.path().join("extracted");
+    let loader = PluginLoader::new(extraction_dir.clone());
     
     helpers::create_corrupted_zip(&package_path)
         .expect("Failed to create corrupted zip file");
     
     // Act
-    let result = PluginPackage::extract_package(&package_path, &extraction_dir);
+    let result = loader.load_plugin_package(&package_path).await;
     
     // Assert
-    assert!(result.is_err());
-    match result {
-        Err(PackageError::ExtractionFailed(_)) => {
-            // Error message should contain details about the extraction failure
-        },
-        _ => panic!("Expected ExtractionFailed error, got {:?}", result),
-    }
+    assert!(matches!(result, Err(PluginLoadError::ZipError(_))));
+    let leftovers = fs::read_dir(&extraction_dir).map(|entries| entries.count()).unwrap_or(0);
+    assert_eq!(leftovers, 0);
 }
 
-#[test]
-fn test_read_manifest_from_package() {
+#[tokio::test]
+async fn test_package_with_invalid_manifest() {
     // Arrange
     let temp_dir = tempdir().expect("Failed to create temp directory");
-    let package_path = temp_dir.path().join("valid_plugin_package.zip");
-    let extraction_dir = temp_dir.path().join("extracted");
+    let package_path = temp_dir.path().join("invalid_manifest_plugin.zip");
+    let loader = PluginLoader::new(temp_dir.path().join("extracted"));
     
-    helpers::create_test_plugin_package(&package_path, true)
+    helpers::create_test_plugin_package(&package_path, false)
         .expect("Failed to create test plugin package");
     
-    fs::create_dir_all(&extraction_dir).expect("Failed to create extraction directory");
-    PluginPackage::extract_package(&package_path, &extraction_dir)
-        .expect("Failed to extract package");
-    
     // Act
-    let result = PluginPackage::read_manifest_from_directory(&extraction_dir);
+    let result = loader.load_plugin_package(&package_path).await;
     
     // Assert
-    assert!(result.is_ok());
-    let manifest = result.unwrap();
-    assert_eq!(manifest.name, "test-plugin");
-    assert_eq!(manifest.version, "1.0.0");
+    assert!(matches!(result, Err(PluginLoadError::JsonError(_))));
 }
 
-#[test]
-fn test_read_manifest_missing_file() {
+#[tokio::test]
+async fn test_load_harness_plugin_dll() {
     // Arrange
+    let Some(dll_path) = harness::harness_plugin_dll() else {
+        return;
+    };
     let temp_dir = tempdir().expect("Failed to create temp directory");
-    let extraction_dir = temp_dir.path().join("extracted");
+    let package_path = temp_dir.path().join("harness.zip");
+    let loader = PluginLoader::new(temp_dir.path().join("extracted"));
     
-    fs::create_dir_all(&extraction_dir).expect("Failed to create extraction directory");
+    harness::create_harness_plugin_package(&package_path, "harness", dll_path)
+        .expect("Failed to create harness plugin package");
+    let metadata = loader.load_plugin_package(&package_path).await
+        .expect("Harness package should load");
     
     // Act
-    let result = PluginPackage::read_manifest_from_directory(&extraction_dir);
+    let result = loader.load_plugin_dll(&metadata);
     
     // Assert
-    assert!(result.is_err());
-    match result {
-        Err(PackageError::ManifestNotFound) => {
-            // Expected error
-        },
-        _ => panic!("Expected ManifestNotFound error, got {:?}", result),
-    }
+    let loaded_plugin = result.expect("Harness DLL should load");
+    assert_eq!(loaded_plugin.metadata().manifest.name, "harness");
+    assert!(loaded_plugin.get_execute_streaming_fn().is_none());
+    assert!(loaded_plugin.get_register_async_handler_fn().is_none());
 }
 
-#[test]
-fn test_validate_package_structure() {
+#[tokio::test]
+async fn test_load_dll_that_is_not_a_library() {
     // Arrange
     let temp_dir = tempdir().expect("Failed to create temp directory");
     let package_path = temp_dir.path().join("valid_plugin_package.zip");
-    let extraction_dir = temp_dir.path().join("extracted");
+    let loader = PluginLoader::new(temp_dir.path().join("extracted"));
     
-    helpers::create_test_plugin_package(&package_path, true)
+    harness::create_dummy_plugin_package(&package_path, "test-plugin")
         .expect("Failed to create test plugin package");
-    
-    PluginPackage::extract_package(&package_path, &extraction_dir)
-        .expect("Failed to extract package");
-    
-    // Act
-    let result = PluginPackage::validate_package_structure(&extraction_dir);
-    
-    // Assert
-    assert!(result.is_ok());
-}
-
-#[test]
-fn test_validate_package_structure_missing_dll() {
-    // Arrange
-    let temp_dir = tempdir().expect("Failed to create temp directory");
-    let package_path = temp_dir.path().join("valid_plugin_package.zip");
-    let extraction_dir = temp_dir.path().join("extracted");
-    
-    helpers::create_test_plugin_package(&package_path, true)
-        .expect("Failed to create test plugin package");
-    
-    PluginPackage::extract_package(&package_path, &extraction_dir)
-        .expect("Failed to extract package");
-    
-    // Remove the DLL file to simulate a missing DLL
-    fs::remove_file(extraction_dir.join("plugin.dll"))
-        .expect("Failed to remove DLL file");
+    let metadata = loader.load_plugin_package(&package_path).await
+        .expect("Valid package should load");
     
     // Act
-    let result = PluginPackage::validate_package_structure(&extraction_dir);
+    let result = loader.load_plugin_dll(&metadata);
     
     // Assert
-    assert!(result.is_err());
-    match result {
-        Err(PackageError::MissingEntryPoint(_)) => {
-            // Expected error
-        },
-        _ => panic!("Expected MissingEntryPoint error, got {:?}", result),
-    }
+    assert!(matches!(result, Err(PluginLoadError::DllLoadFailed(_))));
 }
@@ -0,0 +1,44 @@
+//! Unit tests for plugin commands, run against the harness plugin
+
+use tauri_windows_plugin_system::permission_system::{Permission, PermissionSystem};
+use tauri_windows_plugin_system::plugin_manager::{PluginError, PluginManager, PluginSource};
+use crate::common::harness;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_process_text_returns_json_result() {
+    // Arrange: the harness plugin stands in for the data-processor plugin
+    let Some(dll_path) = harness::harness_plugin_dll() else {
+        return;
+    };
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let package_path = temp_dir.path().join("data-processor.zip");
+    harness::create_harness_plugin_package(&package_path, "data-processor", dll_path)
+        .expect("Failed to create harness plugin package");
+    
+    let permission_system = Arc::new(PermissionSystem::new());
+    let manager = PluginManager::new(
+        temp_dir.path().join("plugins"),
+        temp_dir.path().join("registry.json"),
+        permission_system.clone(),
+    ).expect("Failed to create plugin manager");
+    let plugin_id = manager.install_plugin(PluginSource::File(package_path)).await
+        .expect("Failed to install plugin")
+        .id;
+    permission_system.grant_permissions(&plugin_id, vec![Permission::Command], true).unwrap();
+    manager.enable_plugin(&plugin_id).await.expect("Failed to enable plugin");
+    let args = serde_json::json!({ "text": "the quick brown fox" });
+    
+    // Act
+    let result = manager.execute_plugin_command(&plugin_id, "process_text", &args).await;
+    
+    // Assert
+    assert_eq!(result.expect("Command failed"), serde_json::json!({ "words": 4 }));
+    
+    // A command the plugin does not handle fails instead of returning a result
+    let result = manager.execute_plugin_command(&plugin_id, "unknown_command", &args).await;
+    assert!(matches!(result, Err(PluginError::HostError(_))));
+    
+    manager.disable_plugin(&plugin_id).await.expect("Failed to disable plugin");
+}